@@ -5,16 +5,22 @@ async fn main() {
     if let Err(err) =  libcli::run().await {
 
             eprintln!("{}", err);
-        eprintln!("{}", exit_code(&err));
         std::process::exit(exit_code(&err));
     }
 }
 
 fn exit_code(err: &anyhow::Error) -> i32 {
     for cause in err.chain() {
+        if cause.downcast_ref::<libruntime::thresholds::ThresholdsFailed>().is_some() {
+            return 99;
+        }
+        if cause.downcast_ref::<libruntime::compare::RegressionsFound>().is_some() {
+            return 98;
+        }
         if let Some(pe) = cause.downcast_ref::<libprotocol::ProtocolError>() {
             return match pe {
                 libprotocol::ProtocolError::Json(_) => 3,
+                libprotocol::ProtocolError::Syntax(_) => 3,
                 libprotocol::ProtocolError::Validation(_) => 3,
                 libprotocol::ProtocolError::Io(_) => 2,
             };
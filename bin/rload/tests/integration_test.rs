@@ -26,7 +26,23 @@ pub fn it_check_dry_run_command_for_valid_scenario() {
         .arg("--iterations=100")
         .assert()
         .code(0);
-    insta::assert_debug_snapshot!(cmd.output())
+    insta::with_settings!({filters => vec![(r"scenario path: [^\\]*?/crates/", "scenario path: [root]/crates/")]}, {
+        insta::assert_debug_snapshot!(cmd.output())
+    });
+}
+
+#[test]
+pub fn it_check_dry_run_command_reports_an_unknown_profile() {
+    let scenario_path = fixture_path("crates/libprotocol/tests/fixtures/profiles-scenario.yaml");
+
+    Command::new(cargo::cargo_bin!("rload"))
+        .arg("dry-run")
+        .arg(format!("--scenario={}", scenario_path.display()))
+        .arg("--iterations=10")
+        .args(["--profile", "nope"])
+        .assert()
+        .code(3)
+        .stderr(predicates::str::contains("unknown profile 'nope'"));
 }
 
 #[test]
@@ -52,4 +68,207 @@ pub fn it_check_validate_command_for_broken_json() {
         .arg(format!("--scenario={}", scenario_path.display()))
         .assert()
         .code(3);
-}
\ No newline at end of file
+}
+#[test]
+pub fn it_check_validate_command_for_broken_yaml() {
+    let scenario_path = fixture_path("crates/libprotocol/tests/fixtures/broken-yaml-scenario.yaml");
+
+    Command::new(cargo::cargo_bin!("rload"))
+        .arg("validate")
+        .arg(format!("--scenario={}", scenario_path.display()))
+        .assert()
+        .code(3)
+        .stderr(predicates::str::contains("invalid yaml at line 6, column 1"));
+}
+#[test]
+pub fn it_check_validate_command_applies_profile_env_and_set_in_order() {
+    let scenario_path = fixture_path("crates/libprotocol/tests/fixtures/profiles-scenario.yaml");
+    let validate = || {
+        let mut command = Command::new(cargo::cargo_bin!("rload"));
+        command
+            .arg("validate")
+            .arg(format!("--scenario={}", scenario_path.display()))
+            .env("RLOAD_PROFILE", "broken");
+        command
+    };
+
+    validate()
+        .assert()
+        .code(3)
+        .stderr(predicates::str::contains("Stages must be non empty array"));
+    validate()
+        .args(["--profile", "staging"])
+        .assert()
+        .success();
+    validate()
+        .args(["--profile", "staging"])
+        .env("RLOAD_TARGET__BASE_URL", "ftp://env.example.com")
+        .assert()
+        .code(3)
+        .stderr(predicates::str::contains("url must starts with http or https"));
+    validate()
+        .args(["--profile", "staging", "--set", "target.base_url=https://cli.example.com"])
+        .env("RLOAD_TARGET__BASE_URL", "ftp://env.example.com")
+        .assert()
+        .success();
+}
+#[test]
+pub fn it_check_migrate_command_writes_a_valid_v2_scenario() {
+    let scenario_path = fixture_path("crates/libprotocol/tests/fixtures/v1-scenario.yaml");
+    let out_path = std::env::temp_dir().join(format!("rload-migrated-{}.yaml", std::process::id()));
+
+    Command::new(cargo::cargo_bin!("rload"))
+        .arg("migrate")
+        .arg(format!("--scenario={}", scenario_path.display()))
+        .args(["--to", "2"])
+        .arg("--out")
+        .arg(&out_path)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("from version 1 to 2 (3 changes)"))
+        .stdout(predicates::str::contains("/metadata: removed, it could only be null"))
+        .stdout(predicates::str::contains("/target/proxy: unknown field, renamed to /target/x-proxy"));
+
+    Command::new(cargo::cargo_bin!("rload"))
+        .arg("validate")
+        .arg(format!("--scenario={}", out_path.display()))
+        .assert()
+        .success();
+    std::fs::remove_file(&out_path).unwrap();
+}
+#[test]
+pub fn it_check_generate_command_writes_toml_that_runs() {
+    let scenario_path = std::env::temp_dir().join(format!("rload-generated-{}.conf", std::process::id()));
+
+    Command::new(cargo::cargo_bin!("rload"))
+        .arg("generate")
+        .arg(format!("--path={}", scenario_path.display()))
+        .arg("--format=toml")
+        .assert()
+        .code(0);
+    assert!(std::fs::read_to_string(&scenario_path).unwrap().contains("[[journeys]]"));
+
+    Command::new(cargo::cargo_bin!("rload"))
+        .arg("validate")
+        .arg(format!("--scenario={}", scenario_path.display()))
+        .arg("--format=toml")
+        .assert()
+        .code(0);
+
+    let output = Command::new(cargo::cargo_bin!("rload"))
+        .arg("run-mock")
+        .arg(format!("--scenario={}", scenario_path.display()))
+        .arg("--format=toml")
+        .arg("--set=workload.stages.0.duration_sec=1")
+        .output()
+        .unwrap();
+    let _ = std::fs::remove_file(&scenario_path);
+    assert_eq!(Some(0), output.status.code(), "{}", String::from_utf8_lossy(&output.stderr));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!("default_scenario", report["scenario"]["name"]);
+    assert!(report["requests"]["total"].as_u64().unwrap() > 0, "{}", report["requests"]);
+}
+#[test]
+pub fn it_check_run_mock_command_exits_with_99_on_failed_thresholds() {
+    let scenario_path = fixture_path("crates/libruntime/tests/fixtures/failing-thresholds-scenario.json");
+
+    let mut cmd = Command::new(cargo::cargo_bin!("rload"));
+
+    cmd
+        .arg("run-mock")
+        .arg(format!("--scenario={}", scenario_path.display()))
+        .assert()
+        .code(99);
+}
+
+#[test]
+pub fn it_check_report_command_renders_html_from_run_mock_output() {
+    let scenario_path = fixture_path("crates/libruntime/tests/fixtures/valid-extended-scenario-for_check_run_engine.json");
+    let dir = std::env::temp_dir();
+    let report_path = dir.join(format!("rload-report-{}.json", std::process::id()));
+    let html_path = dir.join(format!("rload-report-{}.html", std::process::id()));
+
+    let output = Command::new(cargo::cargo_bin!("rload"))
+        .arg("run-mock")
+        .arg(format!("--scenario={}", scenario_path.display()))
+        .output()
+        .unwrap();
+    std::fs::write(&report_path, &output.stdout).unwrap();
+
+    Command::new(cargo::cargo_bin!("rload"))
+        .arg("report")
+        .arg(format!("--input={}", report_path.display()))
+        .arg(format!("--html={}", html_path.display()))
+        .assert()
+        .code(0);
+
+    let html = std::fs::read_to_string(&html_path).unwrap();
+    std::fs::remove_file(&report_path).unwrap();
+    std::fs::remove_file(&html_path).unwrap();
+    assert!(html.contains("<h2>Latency by endpoint</h2>"), "{}", html);
+}
+
+#[test]
+pub fn it_check_run_mock_command_writes_junit_before_failing_thresholds() {
+    let scenario_path = fixture_path("crates/libruntime/tests/fixtures/failing-thresholds-scenario.json");
+    let junit_path = std::env::temp_dir().join(format!("rload-junit-{}.xml", std::process::id()));
+
+    Command::new(cargo::cargo_bin!("rload"))
+        .arg("run-mock")
+        .arg(format!("--scenario={}", scenario_path.display()))
+        .arg(format!("--out=junit={}", junit_path.display()))
+        .assert()
+        .code(99);
+
+    let xml = std::fs::read_to_string(&junit_path).unwrap();
+    std::fs::remove_file(&junit_path).unwrap();
+    assert!(xml.contains("<testsuites name=\"rload\" tests=\"2\" failures=\"1\""), "{}", xml);
+}
+
+#[test]
+pub fn it_check_compare_command_exits_with_98_on_regression() {
+    let scenario_path = fixture_path("crates/libruntime/tests/fixtures/valid-extended-scenario-for_check_run_engine.json");
+    let dir = std::env::temp_dir();
+    let baseline_path = dir.join(format!("rload-baseline-{}.json", std::process::id()));
+    let current_path = dir.join(format!("rload-current-{}.json", std::process::id()));
+
+    let output = Command::new(cargo::cargo_bin!("rload"))
+        .arg("run-mock")
+        .arg(format!("--scenario={}", scenario_path.display()))
+        .output()
+        .unwrap();
+    std::fs::write(&baseline_path, &output.stdout).unwrap();
+    let mut current: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    current["requests"]["error"] = current["requests"]["total"].clone();
+    std::fs::write(&current_path, current.to_string()).unwrap();
+
+    let compare = |current: &std::path::Path| {
+        let mut cmd = Command::new(cargo::cargo_bin!("rload"));
+        cmd.arg("compare")
+            .arg(format!("--baseline={}", baseline_path.display()))
+            .arg(format!("--current={}", current.display()));
+        cmd
+    };
+    compare(&baseline_path).assert().code(0);
+    compare(&current_path).assert().code(98);
+
+    std::fs::remove_file(&baseline_path).unwrap();
+    std::fs::remove_file(&current_path).unwrap();
+}
+
+#[test]
+pub fn it_check_schema_command_exports_the_documented_report_schema() {
+    let schema_path = std::env::temp_dir().join(format!("rload-report-schema-{}.json", std::process::id()));
+
+    Command::new(cargo::cargo_bin!("rload"))
+        .arg("schema")
+        .arg("--report")
+        .arg(format!("--path={}", schema_path.display()))
+        .assert()
+        .code(0);
+
+    let exported = std::fs::read_to_string(&schema_path).unwrap();
+    std::fs::remove_file(&schema_path).unwrap();
+    let documented = std::fs::read_to_string(fixture_path("docs/report-schema.json")).unwrap();
+    assert_eq!(documented, exported, "docs/report-schema.json is stale, regenerate it with `rload schema --report`");
+}
@@ -0,0 +1,3 @@
+//! Live metrics for external systems, fed by the run `Event` stream.
+
+pub mod prometheus;
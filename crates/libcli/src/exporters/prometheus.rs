@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use libruntime::events::Event;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use crate::stats::live::LiveStats;
+
+/// Upper bounds of the latency histogram buckets, in seconds
+const LATENCY_BUCKETS: [f64; 12] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Prometheus view of a run, updated from the event stream.
+#[derive(Debug)]
+pub struct PrometheusMetrics {
+    live: LiveStats,
+    started_at: Instant,
+    ticks: u64,
+    /// (endpoint, status, error) → finished requests
+    requests: BTreeMap<(String, String, String), u64>,
+    latency: BTreeMap<String, LatencyHistogram>,
+}
+
+#[derive(Debug, Default)]
+struct LatencyHistogram {
+    /// not cumulative, one count per bucket of `LATENCY_BUCKETS`
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum_seconds: f64,
+    count: u64,
+}
+
+impl PrometheusMetrics {
+    pub fn new(live: LiveStats) -> Self {
+        PrometheusMetrics {
+            live,
+            started_at: Instant::now(),
+            ticks: 0,
+            requests: BTreeMap::new(),
+            latency: BTreeMap::new(),
+        }
+    }
+
+    pub fn on_event(&mut self, event: Event) {
+        self.live.advance(self.started_at.elapsed().as_millis() as u64);
+        match &event {
+            Event::TickExecuted { .. } => self.ticks += 1,
+            Event::RequestFinished { latency_ms, error, endpoint, status, .. } => {
+                let key = (
+                    endpoint.clone(),
+                    status.map(|s| s.to_string()).unwrap_or_default(),
+                    error.clone().unwrap_or_default(),
+                );
+                *self.requests.entry(key).or_insert(0) += 1;
+
+                let seconds = *latency_ms as f64 / 1000.0;
+                let histogram = self.latency.entry(endpoint.clone()).or_default();
+                if let Some(i) = LATENCY_BUCKETS.iter().position(|le| seconds <= *le) {
+                    histogram.buckets[i] += 1;
+                }
+                histogram.sum_seconds += seconds;
+                histogram.count += 1;
+            }
+            Event::InFlight { .. } | Event::Warning { .. } | Event::RunFinished => {}
+        }
+        self.live.on_event(event);
+    }
+
+    /// Text exposition format
+    pub fn render(&mut self) -> String {
+        self.live.advance(self.started_at.elapsed().as_millis() as u64);
+        let snapshot = self.live.snapshot();
+        let mut out = String::new();
+
+        family(&mut out, "rload_requests_total", "counter", "Finished requests");
+        for ((endpoint, status, error), count) in &self.requests {
+            let _ = writeln!(out, "rload_requests_total{{endpoint=\"{}\",status=\"{}\",error=\"{}\"}} {}", escape(endpoint), status, escape(error), count);
+        }
+
+        family(&mut out, "rload_request_duration_seconds", "histogram", "Request latency");
+        for (endpoint, histogram) in &self.latency {
+            let endpoint = escape(endpoint);
+            let mut cumulative = 0;
+            for (le, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                let _ = writeln!(out, "rload_request_duration_seconds_bucket{{endpoint=\"{}\",le=\"{}\"}} {}", endpoint, le, cumulative);
+            }
+            let _ = writeln!(out, "rload_request_duration_seconds_bucket{{endpoint=\"{}\",le=\"+Inf\"}} {}", endpoint, histogram.count);
+            let _ = writeln!(out, "rload_request_duration_seconds_sum{{endpoint=\"{}\"}} {}", endpoint, histogram.sum_seconds);
+            let _ = writeln!(out, "rload_request_duration_seconds_count{{endpoint=\"{}\"}} {}", endpoint, histogram.count);
+        }
+
+        gauge(&mut out, "rload_in_flight_requests", "Requests waiting for a response", snapshot.in_flight as f64);
+        gauge(&mut out, "rload_stage", "Index of the current stage", snapshot.stage_index as f64);
+        gauge(&mut out, "rload_target_rps", "Planned rate of the current tick", snapshot.target_rps as f64);
+        gauge(&mut out, "rload_achieved_rps", "Requests finished in the last completed second", snapshot.rps_1s as f64);
+        family(&mut out, "rload_ticks_total", "counter", "Executed scheduler ticks");
+        let _ = writeln!(out, "rload_ticks_total {}", self.ticks);
+
+        out
+    }
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    family(out, name, "gauge", help);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+type Shared = Arc<Mutex<PrometheusMetrics>>;
+
+async fn metrics(State(metrics): State<Shared>) -> impl IntoResponse {
+    let body = metrics.lock().unwrap().render();
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+/// Serves `/metrics` on `addr` during the run and for `grace` after it, so the last scrape sees
+/// the final values. Ctrl-C ends the grace period early.
+/// Binding happens before returning, so a busy address fails the run before it starts.
+pub async fn serve(addr: SocketAddr, mut rx: mpsc::UnboundedReceiver<Event>, metrics: PrometheusMetrics, grace: Duration) -> anyhow::Result<(SocketAddr, JoinHandle<()>)> {
+    let listener = tokio::net::TcpListener::bind(addr).await
+        .map_err(|e| anyhow::anyhow!("prometheus listener on {}: {}", addr, e))?;
+    let local_addr = listener.local_addr()?;
+    let shared: Shared = Arc::new(Mutex::new(metrics));
+
+    let app = Router::new()
+        .route("/metrics", get(self::metrics))
+        .with_state(shared.clone());
+
+    let handle = tokio::spawn(async move {
+        let finished = async move {
+            while let Some(event) = rx.recv().await {
+                let done = matches!(event, Event::RunFinished);
+                shared.lock().unwrap().on_event(event);
+                if done {
+                    break;
+                }
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(grace) => {}
+                _ = tokio::signal::ctrl_c() => {}
+            }
+        };
+        let _ = axum::serve(listener, app).with_graceful_shutdown(finished).await;
+    });
+
+    Ok((local_addr, handle))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::exporters::prometheus::{serve, PrometheusMetrics};
+    use crate::stats::live::LiveStats;
+    use libruntime::events::Event;
+    use libruntime::scheduler::Tick;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    fn request(endpoint: &str, latency_ms: u32, status: Option<u16>, error: Option<&str>) -> Event {
+        Event::RequestFinished {
+            ok: error.is_none(),
+            latency_ms,
+            error: error.map(str::to_string),
+            endpoint: endpoint.to_string(),
+            status,
+            journey: "j".to_string(),
+            stage_index: 0,
+            span: None,
+        }
+    }
+
+    #[test]
+    fn it_renders_exposition_format() {
+        let mut metrics = PrometheusMetrics::new(LiveStats::new(100, vec![10_000]));
+        metrics.on_event(Event::TickExecuted { tick: Tick { tick_index: 0, stage_index: 0, planned_at_ms: 0, target_rps: 10, is_new_stage: true } });
+        metrics.on_event(request("GET /users/{{id}}", 3, Some(200), None));
+        metrics.on_event(request("GET /users/{{id}}", 120, Some(200), None));
+        metrics.on_event(request("GET /users/{{id}}", 40, Some(503), Some("http_503")));
+        metrics.on_event(request("POST /orders", 2000, None, Some("timeout")));
+        metrics.on_event(Event::InFlight { value: 3 });
+
+        let text = metrics.render();
+
+        for expected in [
+            "# TYPE rload_requests_total counter",
+            "rload_requests_total{endpoint=\"GET /users/{{id}}\",status=\"200\",error=\"\"} 2",
+            "rload_requests_total{endpoint=\"GET /users/{{id}}\",status=\"503\",error=\"http_503\"} 1",
+            "rload_requests_total{endpoint=\"POST /orders\",status=\"\",error=\"timeout\"} 1",
+            "# TYPE rload_request_duration_seconds histogram",
+            "rload_request_duration_seconds_bucket{endpoint=\"GET /users/{{id}}\",le=\"0.005\"} 1",
+            "rload_request_duration_seconds_bucket{endpoint=\"GET /users/{{id}}\",le=\"0.05\"} 2",
+            "rload_request_duration_seconds_bucket{endpoint=\"GET /users/{{id}}\",le=\"+Inf\"} 3",
+            "rload_request_duration_seconds_sum{endpoint=\"GET /users/{{id}}\"} 0.163",
+            "rload_request_duration_seconds_bucket{endpoint=\"POST /orders\",le=\"1\"} 0",
+            "rload_request_duration_seconds_bucket{endpoint=\"POST /orders\",le=\"2.5\"} 1",
+            "rload_in_flight_requests 3",
+            "rload_stage 0",
+            "rload_target_rps 10",
+            "rload_achieved_rps 0",
+            "rload_ticks_total 1",
+        ] {
+            assert!(text.lines().any(|line| line == expected), "missing '{}' in\n{}", expected, text);
+        }
+    }
+
+    async fn scrape(addr: std::net::SocketAddr) -> String {
+        reqwest::get(format!("http://{}/metrics", addr)).await.unwrap().text().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn it_serves_metrics_until_the_grace_period_after_run_finished() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let metrics = PrometheusMetrics::new(LiveStats::new(100, vec![10_000]));
+        let (addr, handle) = serve("127.0.0.1:0".parse().unwrap(), rx, metrics, Duration::from_millis(300)).await.unwrap();
+
+        tx.send(request("GET /ok", 5, Some(200), None)).unwrap();
+        let expected = "rload_requests_total{endpoint=\"GET /ok\",status=\"200\",error=\"\"} 1";
+        let mut body = String::new();
+        for _ in 0..20 {
+            body = scrape(addr).await;
+            if body.contains(expected) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(body.contains(expected), "{}", body);
+
+        tx.send(Event::RunFinished).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(scrape(addr).await.contains(expected));
+        handle.await.unwrap();
+        assert!(reqwest::get(format!("http://{}/metrics", addr)).await.is_err());
+    }
+}
@@ -1,14 +1,28 @@
 mod ui;
 mod stats;
+mod exporters;
 
+use anyhow::anyhow;
 use clap::{Parser, Subcommand};
 use console::style;
-use std::path::Path;
-use indicatif::{ProgressBar, ProgressStyle};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::sync::mpsc;
-use libruntime::events::{Event, EventSink};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+use libprotocol::{Override, Overrides, ScenarioFormat, CURRENT_VERSION};
+use libprotocol::migration::MIGRATIONS;
+use libruntime::compare::{compare, RegressionsFound, Tolerances};
+use libruntime::events::{Event, EventSink, StopSignal};
+use libruntime::report::REPORT_VERSION;
+use libruntime::outputs::{OutputTarget, Outputs};
 use libruntime::scheduler::Scheduler;
+use libruntime::thresholds::ThresholdsFailed;
+use libruntime::RunReport;
 use crate::stats::live::LiveStats;
+use crate::ui::UiMode;
+use crate::exporters::prometheus::PrometheusMetrics;
 
 const BANNER: &str = r#"
  ____  _                 _
@@ -41,6 +55,40 @@ enum Commands {
             require_equals = true,
         )]
         scenario: String,
+        /// json, yaml or toml. Defaults to the scenario extension
+        #[arg(long, value_name = "FORMAT")]
+        format: Option<ScenarioFormat>,
+        /// Live view while the run is in progress
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = UiMode::Progress
+        )]
+        ui: UiMode,
+        /// Serve Prometheus metrics on http://<ADDR>/metrics during the run, e.g. 127.0.0.1:9464
+        #[arg(
+            long,
+            value_name = "ADDR",
+        )]
+        prometheus_listen: Option<SocketAddr>,
+        /// Keep serving Prometheus metrics for this long after the run, so the last scrape sees the
+        /// final values. Ctrl-C stops earlier
+        #[arg(
+            long,
+            value_name = "SECS",
+            default_value_t = 15,
+        )]
+        prometheus_grace_sec: u64,
+        /// Send metrics to an output while the run is in progress, repeatable:
+        /// influxdb=<write url>, influxdb=<file>, statsd=<host:port>, otlp=<endpoint>,
+        /// raw=<file.ndjson|file.csv> or junit=<file.xml>
+        #[arg(
+            long = "out",
+            value_name = "KIND=TARGET",
+        )]
+        outputs: Vec<OutputTarget>,
+        #[command(flatten)]
+        overrides: OverrideArgs,
     },
     /// Run mock a scenario
     #[command(arg_required_else_help = true)]
@@ -52,6 +100,17 @@ enum Commands {
             require_equals = true,
         )]
         scenario: String,
+        /// json, yaml or toml. Defaults to the scenario extension
+        #[arg(long, value_name = "FORMAT")]
+        format: Option<ScenarioFormat>,
+        /// Same outputs as run, e.g. junit=<file.xml>
+        #[arg(
+            long = "out",
+            value_name = "KIND=TARGET",
+        )]
+        outputs: Vec<OutputTarget>,
+        #[command(flatten)]
+        overrides: OverrideArgs,
     },
     /// Dry-run a scenario
     #[command(arg_required_else_help = true)]
@@ -63,6 +122,9 @@ enum Commands {
             require_equals = true,
         )]
         scenario: String,
+        /// json, yaml or toml. Defaults to the scenario extension
+        #[arg(long, value_name = "FORMAT")]
+        format: Option<ScenarioFormat>,
         #[arg(
             short,
             long,
@@ -93,14 +155,15 @@ enum Commands {
             default_value_t = 1000u32
         )]
         limit_steps: u32,
-        /// Print plan to console
+        /// Print planned ticks (up to --limit-steps) to console
         #[arg(
             short,
             long,
             require_equals = false,
         )]
         print_plan: bool,
-
+        #[command(flatten)]
+        overrides: OverrideArgs,
     },
     #[command(arg_required_else_help = false)]
     Generate {
@@ -110,13 +173,16 @@ enum Commands {
             default_missing_value = "./demo-scenario.json"
         )]
         path: Option<String>,
+        /// Scenario format version, the current one when given without a value
         #[arg(
             long,
             require_equals = false,
-            default_missing_value = "1"
+            num_args = 0..=1,
         )]
-        version: Option<String>,
-
+        version: Option<Option<u16>>,
+        /// json, yaml or toml. Defaults to the --path extension
+        #[arg(long, value_name = "FORMAT")]
+        format: Option<ScenarioFormat>,
     },
     /// Export a json schema for scenario
     #[command(arg_required_else_help = false)]
@@ -127,12 +193,16 @@ enum Commands {
             default_missing_value = "./demo-scenario.json"
         )]
         path: Option<String>,
+        /// Scenario format version, also added to the file name. The current one when given without a value
         #[arg(
             long,
             require_equals = false,
-            default_missing_value = "1"
+            num_args = 0..=1,
         )]
-        version: Option<String>
+        version: Option<Option<u16>>,
+        /// Export the run report schema instead, to --path as is (its version is in `$version`)
+        #[arg(long)]
+        report: bool,
     },
     /// Validate given scenario
     #[command(arg_required_else_help = true)]
@@ -143,110 +213,300 @@ enum Commands {
             require_equals = true,
         )]
         scenario: Option<String>,
+        /// json, yaml or toml. Defaults to the scenario extension
+        #[arg(long, value_name = "FORMAT")]
+        format: Option<ScenarioFormat>,
+        #[command(flatten)]
+        overrides: OverrideArgs,
+    },
+    /// Rewrite a scenario in a newer version of the format
+    #[command(arg_required_else_help = true)]
+    Migrate {
+        #[arg(
+            long,
+            required = true,
+            require_equals = true,
+        )]
+        scenario: String,
+        #[arg(long, value_name = "VERSION", default_value_t = CURRENT_VERSION)]
+        to: u16,
+        /// Defaults to <scenario>-v<VERSION>.<ext> next to the scenario
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+    /// Render a saved run report
+    #[command(arg_required_else_help = true)]
+    Report {
+        /// JSON report printed by run or run-mock
+        #[arg(long, value_name = "REPORT_JSON")]
+        input: PathBuf,
+        /// Write a self-contained HTML page
+        #[arg(long, value_name = "FILE")]
+        html: PathBuf,
     },
+    /// Compare two saved run reports, fails when the current one regressed
+    #[command(arg_required_else_help = true)]
+    Compare {
+        #[arg(long, value_name = "REPORT_JSON")]
+        baseline: PathBuf,
+        #[arg(long, value_name = "REPORT_JSON")]
+        current: PathBuf,
+        #[arg(long, value_enum, default_value_t = CompareFormat::Table)]
+        output: CompareFormat,
+        /// Allowed p50/p95/p99 increase, in percent
+        #[arg(long, value_name = "PCT", default_value_t = Tolerances::default().latency_pct)]
+        latency_tolerance: f64,
+        /// Allowed error rate increase, in percentage points
+        #[arg(long, value_name = "POINTS", default_value_t = Tolerances::default().error_rate_points)]
+        error_rate_tolerance: f64,
+        /// Allowed achieved RPS decrease, in percent
+        #[arg(long, value_name = "PCT", default_value_t = Tolerances::default().rps_pct)]
+        rps_tolerance: f64,
+    },
+}
+
+/// Profile and overrides applied to the scenario, see `libprotocol::overrides`
+#[derive(Debug, clap::Args)]
+struct OverrideArgs {
+    /// One of the scenario profiles. Defaults to RLOAD_PROFILE
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+    /// Override a scenario value by its dotted path, e.g. target.base_url=https://staging.example.com.
+    /// Repeatable, wins over the profile and RLOAD_* variables
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    values: Vec<Override>,
+}
+
+impl OverrideArgs {
+    fn overrides(self) -> Overrides {
+        let mut overrides = Overrides::from_env(std::env::vars());
+        if self.profile.is_some() {
+            overrides.profile = self.profile;
+        }
+        overrides.values.extend(self.values);
+        overrides
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+enum CompareFormat {
+    Table,
+    Json,
 }
 
 pub async fn run() -> anyhow::Result<()>{
     let args = Cli::parse();
 
     match args.command {
-        Commands::Generate { path, version } => {
-            libprotocol::generate_scenario(path.unwrap_or("./demo-scenario.json".to_string()), &version.unwrap_or_else(|| "1".to_string()))
+        Commands::Generate { path, version, format } => {
+            let path = path.unwrap_or_else(|| format!("./demo-scenario.{}", format.unwrap_or_default().extension()));
+            let format = format.unwrap_or_else(|| ScenarioFormat::from_path(&path));
+            let version = version.flatten().unwrap_or(CURRENT_VERSION);
+            libprotocol::generate_scenario_as(path, &version.to_string(), format)
         },
-        Commands::Schema { path, version } => {
+        Commands::Schema { path, report: true, .. } => {
+            let path = path.unwrap_or("./report-schema.json".to_string());
+            libruntime::report::export_schema(&path)
+                .map_err(|e| anyhow!("cannot write {}: {}", path, e))?;
+            println!("Schema exported successfully !");
+            Ok(())
+        },
+        Commands::Schema { path, version, .. } => {
+            let version = version.map(|version| version.unwrap_or(CURRENT_VERSION).to_string());
             libprotocol::export_schema(path.unwrap_or("./schema.json".to_string()), version)
         },
-        Commands::Validate { scenario } => {
-            Ok(libprotocol::validate(scenario.unwrap())?)
+        Commands::Validate { scenario, format, overrides } => {
+            let scenario = scenario.unwrap();
+            let format = format.unwrap_or_else(|| ScenarioFormat::from_path(&scenario));
+            eprintln!("scenario path: {}", scenario);
+            let changes = libprotocol::validate_with(scenario, format, &overrides.overrides())?;
+            if !changes.is_empty() {
+                eprintln!("scenario is migrated to version {} in memory ({} changes), `rload migrate` rewrites the file", CURRENT_VERSION, changes.len());
+            }
+            Ok(())
         },
-        Commands::DryRun { scenario, seed, iterations, is_simulated, .. } => {
-            let (tx, _rx) = mpsc::unbounded_channel();
-            let sink = EventSink::new(tx);
-
-            libruntime::dry_run(scenario, seed, iterations, is_simulated, sink).await;
+        Commands::Migrate { scenario, to, out } => {
+            let migrated = libprotocol::migrate_scenario(&scenario, to, out.as_deref())?;
+            if migrated.from == migrated.to {
+                println!("{} is already at version {}", scenario, migrated.to);
+            } else {
+                println!("Migrated {} from version {} to {} ({} changes):", scenario, migrated.from, migrated.to, migrated.changes.len());
+                for migration in MIGRATIONS.iter().filter(|m| m.from >= migrated.from && m.from < migrated.to) {
+                    println!("  v{}: {}", migration.from + 1, migration.summary);
+                    for change in migrated.changes.iter().filter(|c| c.version == migration.from + 1) {
+                        println!("    {}: {}", change.path, change.message);
+                    }
+                }
+            }
+            println!("Written to {}", migrated.out_path.display());
+            if !migrated.references.is_empty() {
+                println!("Not migrated, referenced with $ref or include: {}", migrated.references.join(", "));
+            }
             Ok(())
         },
-        Commands::RunMock { scenario} => {
-            let (tx, _rx) = mpsc::unbounded_channel();
-            let sink = EventSink::new(tx);
-
-            libruntime::run(scenario, Some(true), sink).await;
+        Commands::Report { input, html } => {
+            let report = read_report(&input)?;
+            std::fs::write(&html, libruntime::html_report::render(&report))
+                .map_err(|e| anyhow!("cannot write {}: {}", html.display(), e))?;
+            Ok(())
+        },
+        Commands::Compare { baseline, current, output, latency_tolerance, error_rate_tolerance, rps_tolerance } => {
+            let tolerances = Tolerances {
+                latency_pct: latency_tolerance,
+                error_rate_points: error_rate_tolerance,
+                rps_pct: rps_tolerance,
+            };
+            let comparison = compare(&read_report(&baseline)?, &read_report(&current)?, &tolerances);
+            match output {
+                CompareFormat::Table => print!("{}", comparison.table()),
+                CompareFormat::Json => println!("{}", serde_json::to_string_pretty(&comparison)?),
+            }
+            if comparison.regressions > 0 {
+                return Err(RegressionsFound { regressions: comparison.regressions }.into());
+            }
+            Ok(())
+        },
+        Commands::DryRun { scenario, format, seed, iterations, is_simulated, print_plan, limit_steps, overrides, .. } => {
+            let format = format.unwrap_or_else(|| ScenarioFormat::from_path(&scenario));
+            let print_plan = print_plan.then_some(limit_steps);
+            eprintln!("scenario path: {}", scenario);
+            libruntime::dry_run(scenario, format, &overrides.overrides(), seed, iterations, is_simulated, print_plan).await?;
             Ok(())
         },
-        Commands::Run { scenario} => {
-            let scenario_instance = &libprotocol::parse_scenario(&scenario);
+        Commands::RunMock { scenario, format, outputs, overrides } => {
+            let format = format.unwrap_or_else(|| ScenarioFormat::from_path(&scenario));
+            let overrides = overrides.overrides();
+            let name = libprotocol::read_scenario(&scenario, format, &overrides)?.name;
+            let (tx, rx) = mpsc::unbounded_channel();
+            let mut txs = vec![tx];
+            let warnings = tokio::spawn(ui::warnings(rx));
+            let outputs_task = spawn_outputs(name, &outputs, &mut txs).await?;
+            let sink = EventSink::fanout(txs);
+
+            let report = libruntime::run(scenario, format, &overrides, Some(true), sink, &outputs, StopSignal::default()).await;
+            warnings.await.ok();
+            report_output_failures(outputs_task).await;
+            let report = report?;
+            print_report(&report);
+            write_report_outputs(&outputs, &report)?;
+            check_thresholds(&report)
+        },
+        Commands::Run { scenario, format, ui, prometheus_listen, prometheus_grace_sec, outputs, overrides } => {
+            let format = format.unwrap_or_else(|| ScenarioFormat::from_path(&scenario));
+            let overrides = overrides.overrides();
+            let scenario_instance = &libprotocol::read_scenario(&scenario, format, &overrides)?;
             let scheduler: Scheduler = Scheduler::new(&scenario_instance.workload);
 
-            let (tx, rx) = mpsc::unbounded_channel();
-            let sink = EventSink::new(tx);
+            let stage_durations_ms: Vec<u64> = scenario_instance.workload.stages.iter()
+                .map(|stage| stage.duration_sec as u64 * 1000)
+                .collect();
+            let live_stats = || LiveStats::new(scheduler.total_ticks, stage_durations_ms.clone());
 
-            let total_ticks = scheduler.total_ticks;
-            //
-            print_banner(env!("CARGO_PKG_VERSION"));
-            println!("OS: {}  CPU: {}",
-                     std::env::consts::OS,
-                     num_cpus::get()
-            );
-            println!("\n\n");
+            let (tx, rx) = mpsc::unbounded_channel();
+            let mut txs = vec![tx];
+            let mut prometheus = None;
+            if let Some(addr) = prometheus_listen {
+                let (tx, rx) = mpsc::unbounded_channel();
+                txs.push(tx);
+                let grace = Duration::from_secs(prometheus_grace_sec);
+                let (addr, handle) = exporters::prometheus::serve(addr, rx, PrometheusMetrics::new(live_stats()), grace).await?;
+                eprintln!("prometheus metrics on http://{}/metrics", addr);
+                prometheus = Some(handle);
+            }
+            let outputs_task = spawn_outputs(scenario_instance.name.clone(), &outputs, &mut txs).await?;
+            let sink = EventSink::fanout(txs);
 
-            let ui = tokio::spawn(async move {
-                ui_task(rx, total_ticks).await;
-            });
+            let stats = live_stats();
+            let stop = StopSignal::default();
+            let ui = match ui {
+                UiMode::Progress => {
+                    print_banner(env!("CARGO_PKG_VERSION"));
+                    println!("OS: {}  CPU: {}",
+                             std::env::consts::OS,
+                             num_cpus::get()
+                    );
+                    println!("\n\n");
+                    tokio::spawn(ui::progress::run(rx, stats))
+                }
+                UiMode::Tui => tokio::spawn(ui::tui::run(rx, stats, scenario_instance.name.clone(), stop.clone())),
+            };
 
-            let report = libruntime::run(scenario, Option::from(false), sink).await;
+            let report = libruntime::run(scenario, format, &overrides, Option::from(false), sink, &outputs, stop).await;
 
+            // the dashboard owns the terminal until it is closed
             ui.await.ok();
-            Ok(report)
+            report_output_failures(outputs_task).await;
+            let report = report?;
+            print_report(&report);
+            write_report_outputs(&outputs, &report)?;
+            let thresholds = check_thresholds(&report);
+            if let Some(prometheus) = prometheus && prometheus_grace_sec > 0 {
+                eprintln!("prometheus metrics stay up for {}s, Ctrl-C to stop", prometheus_grace_sec);
+                prometheus.await.ok();
+            }
+            thresholds
         },
 
     }
 }
 
-async fn ui_task(
-    mut rx: mpsc::UnboundedReceiver<Event>,
-    total_ticks: u64,
-) {
-    let pb = ProgressBar::new(total_ticks);
-    pb.set_style(
-        ProgressStyle::with_template("[{bar:60}] {pos}/{len} {msg}")
-            .unwrap()
-            .progress_chars("=>-"),
-    );
+/// Connects the `--out` sinks and feeds them from a new event channel, `None` without outputs
+async fn spawn_outputs(scenario_name: String, outputs: &[OutputTarget], txs: &mut Vec<UnboundedSender<Event>>) -> anyhow::Result<Option<JoinHandle<Vec<String>>>> {
+    if outputs.is_empty() {
+        return Ok(None);
+    }
+    let (tx, rx) = mpsc::unbounded_channel();
+    txs.push(tx);
+    let sinks = libruntime::outputs::connect(outputs).await?;
+    let interval = Duration::from_millis(libruntime::outputs::FLUSH_INTERVAL_MS);
+    Ok(Some(Outputs::new(scenario_name, sinks).spawn(rx, interval)))
+}
 
-    let mut s = LiveStats::default();
+/// Waits for the last flush, then prints the failed ones now that the live view is gone
+async fn report_output_failures(outputs_task: Option<JoinHandle<Vec<String>>>) {
+    let Some(task) = outputs_task else {
+        return;
+    };
+    for failure in task.await.unwrap_or_default() {
+        eprintln!("{} {}", style("!").yellow(), failure);
+    }
+}
 
-    // таймер обновления UI
-    let mut ticker = tokio::time::interval(std::time::Duration::from_millis(250));
+/// Outputs built from the final report, written before the thresholds decide the exit code
+fn write_report_outputs(outputs: &[OutputTarget], report: &RunReport) -> anyhow::Result<()> {
+    for output in outputs {
+        if let OutputTarget::Junit(path) = output {
+            libruntime::outputs::junit::write(path, report)?;
+        }
+    }
+    Ok(())
+}
 
-    loop {
-        tokio::select! {
-            _ = ticker.tick() => {
-                pb.set_position(s.totals.ticks_executed);
-                let err_rate = if (s.totals.requests_ok + s.totals.requests_err) > 0 {
-                    (s.totals.requests_err as f64) / ((s.totals.requests_ok + s.totals.requests_err) as f64) * 100.0
-                } else { 0.0 };
+/// A report saved from the output of run or run-mock
+fn read_report(path: &Path) -> anyhow::Result<RunReport> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("cannot read report {}: {}", path.display(), e))?;
+    let report: RunReport = serde_json::from_str(&content)
+        .map_err(|e| anyhow!("{} is not a run report: {}", path.display(), e))?;
+    if report.report_version > REPORT_VERSION {
+        return Err(anyhow!("{} has report version {}, this rload reads up to {}", path.display(), report.report_version, REPORT_VERSION));
+    }
+    Ok(report)
+}
 
-                pb.set_message(format!(
-                    "ok={} err={} ({:.2}%) in_flight={}",
-                    s.totals.requests_ok, s.totals.requests_err, err_rate, s.totals.in_flight
-                ));
-            }
-            ev = rx.recv() => {
-                match ev {
-                    Some(Event::TickExecuted{..}) => s.totals.ticks_executed += 1,
-                    Some(Event::RequestFinished{ok, latency_ms: _}) => {
-                        if ok { s.totals.requests_ok += 1 } else { s.totals.requests_err += 1 }
-                        // тут же обновляешь latency-агрегаты / окно RPS
-                    }
-                    Some(Event::InFlight{value}) => s.totals.in_flight = value,
-                    Some(Event::RunFinished) | None => {
-                        pb.finish_with_message("done");
-                        break;
-                    }
-                }
-            }
-        }
+fn print_report(report: &RunReport) {
+    println!("{}", serde_json::to_string_pretty(report).unwrap());
+}
+
+fn check_thresholds(report: &RunReport) -> anyhow::Result<()> {
+    let thresholds = report.thresholds();
+    for result in thresholds.results.iter().filter(|r| !r.passed) {
+        eprintln!("{} {}", style("✗").red(), result.message);
     }
+    if !thresholds.passed {
+        return Err(ThresholdsFailed { failed: thresholds.failed, total: thresholds.total }.into());
+    }
+    Ok(())
 }
 
 fn print_banner(version: &str) {
@@ -261,9 +521,9 @@ pub fn validate(path: impl AsRef<Path>) ->anyhow::Result<()> {
 }
 
 pub fn export_schema(out_path: impl AsRef<Path>) -> anyhow::Result<()> {
-    libprotocol::export_schema(out_path, Some("1".to_string()))
+    libprotocol::export_schema(out_path, Some(CURRENT_VERSION.to_string()))
 }
 
 pub fn generate_scenario(out_path: impl AsRef<Path>) -> anyhow::Result<()> {
-    libprotocol::generate_scenario(out_path, "1")
+    libprotocol::generate_scenario(out_path, &CURRENT_VERSION.to_string())
 }
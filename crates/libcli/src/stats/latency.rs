@@ -1,17 +1,70 @@
 use hdrhistogram::Histogram;
+use std::collections::VecDeque;
+
+/// Completed seconds kept for the rolling percentiles
+pub const ROLLING_SECS: usize = 10;
 
 #[derive(Debug)]
-#[allow(dead_code)]
 pub struct LatencyStats {
     pub total: Histogram<u64>, // latency in ms
     pub per_sec: Histogram<u64>, // current second
+    pub recent: VecDeque<Histogram<u64>>, // last completed seconds, oldest first
 }
 
 impl Default for LatencyStats {
     fn default() -> Self {
         Self {
-            total: Histogram::new_with_bounds(1, 120_000, 3).expect("LatencyStat Histogram create failed"),
-            per_sec: Histogram::new_with_bounds(1, 120_000, 3).expect("LatencyStat Histogram create failed"),
+            total: new_histogram(),
+            per_sec: new_histogram(),
+            recent: VecDeque::with_capacity(ROLLING_SECS),
+        }
+    }
+}
+
+/// p50 / p95 / p99 in ms
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Percentiles {
+    pub p50: u64,
+    pub p95: u64,
+    pub p99: u64,
+}
+
+impl LatencyStats {
+    pub fn record(&mut self, latency_ms: u64) {
+        self.total.saturating_record(latency_ms);
+        self.per_sec.saturating_record(latency_ms);
+    }
+
+    /// Closes the current second
+    pub fn roll(&mut self) {
+        if self.recent.len() == ROLLING_SECS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(std::mem::replace(&mut self.per_sec, new_histogram()));
+    }
+
+    /// Percentiles over the last completed seconds and the current one
+    pub fn rolling(&self) -> Percentiles {
+        let mut merged = self.per_sec.clone();
+        for h in &self.recent {
+            merged.add(h).expect("LatencyStat Histogram merge failed");
         }
+        percentiles(&merged)
+    }
+
+    pub fn overall(&self) -> Percentiles {
+        percentiles(&self.total)
+    }
+}
+
+fn percentiles(h: &Histogram<u64>) -> Percentiles {
+    Percentiles {
+        p50: h.value_at_quantile(0.50),
+        p95: h.value_at_quantile(0.95),
+        p99: h.value_at_quantile(0.99),
     }
-}
\ No newline at end of file
+}
+
+fn new_histogram() -> Histogram<u64> {
+    Histogram::new_with_bounds(1, 120_000, 3).expect("LatencyStat Histogram create failed")
+}
@@ -1,72 +1,201 @@
-use tokio::time::Instant;
-use crate::stats::latency::LatencyStats;
+use std::collections::BTreeMap;
+use libruntime::events::Event;
+use crate::stats::latency::{LatencyStats, Percentiles, ROLLING_SECS};
 use crate::stats::window::SlidingWindow;
 
-#[derive(Debug)]
-#[allow(dead_code)]
+#[derive(Debug, Default)]
 pub struct Totals {
     pub ticks_executed: u64,
     pub requests_ok: u64,
     pub requests_err: u64,
     pub in_flight: i32,
 
-    pub _started_at: Instant,
     pub planned_total_ticks: u64,
     pub planned_duration_ms: u64,
 }
 
-impl Default for Totals {
-    fn default() -> Self {
-        Self {
-            ticks_executed: 0,
-            requests_ok: 0,
-            requests_err: 0,
-            in_flight: 0,
-            _started_at: Instant::now(),
-
-            planned_total_ticks: 0,
-            planned_duration_ms: 0,
-        }
-    }
-}
-
-
+/// Aggregates the event stream of a run for the live views.
 #[derive(Debug, Default)]
-#[allow(dead_code)]
 pub struct LiveStats {
     pub totals: Totals,
     pub window: SlidingWindow,
     pub latency: LatencyStats,
     pub last_stage_index: u32,
+    /// `Tick.target_rps` of the last executed tick
+    pub target_rps: u32,
+    /// Planned time of the last executed tick
+    pub planned_at_ms: u64,
+    /// Planned duration of every stage
+    pub stage_durations_ms: Vec<u64>,
+    /// Failed requests per error label
+    pub errors: BTreeMap<String, u64>,
+    pub warnings: u64,
+    pub last_warning: Option<String>,
+    pub elapsed_ms: u64,
 }
 
-#[allow(dead_code)]
-struct LiveSnapshot {
-    executed: u64,
-    total: u64,
-    rps_1s: u32,
-    ok: u64,
-    err: u64,
-    err_rate: f64,
-    avg_ms: u32,
-    p95_ms: u32,
-    in_flight: i32,
-    eta_ms: u64,
+/// What the live views display
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LiveSnapshot {
+    pub executed: u64,
+    pub total: u64,
+    pub rps_1s: u64,
+    pub target_rps: u32,
+    pub ok: u64,
+    pub err: u64,
+    pub err_rate: f64,
+    pub rolling: Percentiles,
+    pub overall: Percentiles,
+    pub in_flight: i32,
+    /// 0-based
+    pub stage_index: u32,
+    pub stage_count: usize,
+    /// 0.0..=1.0
+    pub stage_progress: f64,
+    pub eta_ms: u64,
+    pub rps_series: Vec<u64>,
+    pub errors: Vec<(String, u64)>,
+    pub warnings: u64,
+    pub last_warning: Option<String>,
 }
 
-impl From<LiveStats> for LiveSnapshot {
-    fn from(value: LiveStats) -> Self {
+impl LiveStats {
+    pub fn new(planned_total_ticks: u64, stage_durations_ms: Vec<u64>) -> Self {
+        LiveStats {
+            totals: Totals {
+                planned_total_ticks,
+                planned_duration_ms: stage_durations_ms.iter().sum(),
+                ..Totals::default()
+            },
+            stage_durations_ms,
+            ..LiveStats::default()
+        }
+    }
+
+    /// Moves the clock to `elapsed_ms` since the start of the run, closing finished seconds
+    pub fn advance(&mut self, elapsed_ms: u64) {
+        self.elapsed_ms = self.elapsed_ms.max(elapsed_ms);
+        let sec = self.elapsed_ms / 1000;
+        if sec <= self.window.current_sec {
+            return;
+        }
+        self.window.current_mut().p95_ms = self.latency.per_sec.value_at_quantile(0.95) as u32;
+        let closed = self.window.advance(sec);
+        // quiet seconds are rolled in empty
+        for _ in 0..closed.min(ROLLING_SECS as u64) {
+            self.latency.roll();
+        }
+    }
+
+    pub fn on_event(&mut self, event: Event) {
+        match event {
+            Event::TickExecuted { tick } => {
+                self.totals.ticks_executed += 1;
+                self.target_rps = tick.target_rps;
+                self.planned_at_ms = tick.planned_at_ms;
+                self.last_stage_index = tick.stage_index as u32;
+            }
+            Event::RequestFinished { ok, latency_ms, error, .. } => {
+                match ok {
+                    true => self.totals.requests_ok += 1,
+                    false => self.totals.requests_err += 1,
+                }
+                if let Some(error) = error {
+                    *self.errors.entry(error).or_insert(0) += 1;
+                }
+                self.window.record(ok, latency_ms as u64);
+                self.latency.record(latency_ms as u64);
+            }
+            Event::InFlight { value } => self.totals.in_flight = value,
+            Event::Warning { message } => {
+                self.warnings += 1;
+                self.last_warning = Some(message);
+            }
+            Event::RunFinished => {}
+        }
+    }
+
+    pub fn snapshot(&self) -> LiveSnapshot {
+        let requests = self.totals.requests_ok + self.totals.requests_err;
+        let stage_start_ms: u64 = self.stage_durations_ms.iter().take(self.last_stage_index as usize).sum();
+        let stage_progress = match self.stage_durations_ms.get(self.last_stage_index as usize) {
+            Some(&duration) if duration > 0 => (self.planned_at_ms.saturating_sub(stage_start_ms) as f64 / duration as f64).min(1.0),
+            _ => 0.0,
+        };
+        let mut errors: Vec<(String, u64)> = self.errors.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        errors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
         LiveSnapshot {
-            executed: value.totals.ticks_executed,
-            total: value.totals.planned_total_ticks,
-            rps_1s: 0,
-            ok: value.totals.requests_ok,
-            err: value.totals.requests_err,
-            err_rate: value.totals.requests_err.checked_div(value.totals.planned_total_ticks).unwrap_or(0) as f64,
-            avg_ms: 0,
-            p95_ms: 0,
-            in_flight: 0,
-            eta_ms: 0,
+            executed: self.totals.ticks_executed,
+            total: self.totals.planned_total_ticks,
+            rps_1s: self.window.last_rps(),
+            target_rps: self.target_rps,
+            ok: self.totals.requests_ok,
+            err: self.totals.requests_err,
+            err_rate: match requests {
+                0 => 0.0,
+                n => self.totals.requests_err as f64 / n as f64 * 100.0,
+            },
+            rolling: self.latency.rolling(),
+            overall: self.latency.overall(),
+            in_flight: self.totals.in_flight,
+            stage_index: self.last_stage_index,
+            stage_count: self.stage_durations_ms.len(),
+            stage_progress,
+            eta_ms: self.totals.planned_duration_ms.saturating_sub(self.elapsed_ms),
+            rps_series: self.window.rps_series(),
+            errors,
+            warnings: self.warnings,
+            last_warning: self.last_warning.clone(),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::live::LiveStats;
+    use libruntime::events::Event;
+    use libruntime::scheduler::Tick;
+
+    fn tick(stage_index: u64, planned_at_ms: u64, target_rps: u32) -> Event {
+        Event::TickExecuted { tick: Tick { tick_index: 0, stage_index, planned_at_ms, target_rps, is_new_stage: false } }
+    }
+
+    fn request(ok: bool, latency_ms: u32, error: Option<&str>) -> Event {
+        Event::RequestFinished { ok, latency_ms, error: error.map(str::to_string), endpoint: "GET /ok".to_string(), status: Some(200), journey: "j".to_string(), stage_index: 0, span: None }
+    }
+
+    #[test]
+    fn it_aggregates_events_per_second() {
+        let mut stats = LiveStats::new(300, vec![10_000, 20_000]);
+
+        stats.on_event(tick(0, 100, 10));
+        for latency_ms in 1..=8 {
+            stats.on_event(request(true, latency_ms * 10, None));
+        }
+        stats.on_event(request(false, 500, Some("timeout")));
+        stats.on_event(request(false, 20, Some("http_503")));
+        stats.on_event(request(false, 30, Some("timeout")));
+        stats.advance(1_200);
+        stats.on_event(tick(1, 15_000, 20));
+        stats.on_event(request(true, 10, None));
+        stats.on_event(Event::InFlight { value: 4 });
+        stats.advance(3_500);
+
+        let snapshot = stats.snapshot();
+
+        assert_eq!(vec![11, 1, 0], snapshot.rps_series);
+        assert_eq!(0, snapshot.rps_1s);
+        assert_eq!(20, snapshot.target_rps);
+        assert_eq!((9, 3), (snapshot.ok, snapshot.err));
+        assert_eq!(25.0, snapshot.err_rate);
+        assert_eq!(vec![("timeout".to_string(), 2), ("http_503".to_string(), 1)], snapshot.errors);
+        assert_eq!(4, snapshot.in_flight);
+        assert_eq!((1, 2), (snapshot.stage_index, snapshot.stage_count));
+        assert_eq!(0.25, snapshot.stage_progress);
+        assert_eq!(26_500, snapshot.eta_ms);
+        assert_eq!(500, snapshot.rolling.p99);
+        assert_eq!(30, snapshot.rolling.p50);
+        assert_eq!(500, stats.window.samples[0].p95_ms);
+    }
+}
@@ -1,3 +1,3 @@
 pub mod live;
-mod window;
-mod latency;
\ No newline at end of file
+pub mod window;
+pub mod latency;
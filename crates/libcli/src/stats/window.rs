@@ -1,5 +1,6 @@
-#[derive(Debug, Default)]
-#[allow(dead_code)]
+use std::collections::VecDeque;
+
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct SecondSample {
     pub sec_index: u64,        // sec from start (0..)
     pub req_ok: u32,
@@ -10,10 +11,74 @@ pub struct SecondSample {
     pub p95_ms: u32,
 }
 
-#[derive(Debug, Default)]
-#[allow(dead_code)]
+impl SecondSample {
+    pub fn requests(&self) -> u64 {
+        self.req_ok as u64 + self.req_err as u64
+    }
+}
+
+#[derive(Debug)]
 pub struct SlidingWindow {
     pub capacity: usize,              //  60 sec
-    pub samples: std::collections::VecDeque<SecondSample>,
+    pub samples: VecDeque<SecondSample>,
     pub current_sec: u64,
-}
\ No newline at end of file
+}
+
+impl Default for SlidingWindow {
+    fn default() -> Self {
+        Self::new(60)
+    }
+}
+
+impl SlidingWindow {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            samples: VecDeque::from([SecondSample::default()]),
+            current_sec: 0,
+        }
+    }
+
+    /// Moves the window to `sec`, seconds without requests get empty samples.
+    /// Returns how many seconds were closed.
+    pub fn advance(&mut self, sec: u64) -> u64 {
+        let closed = sec.saturating_sub(self.current_sec);
+        // more than a window of silence: only the last `capacity` seconds matter
+        for sec_index in sec.saturating_sub(self.capacity as u64).max(self.current_sec) + 1..=sec {
+            if self.samples.len() == self.capacity {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(SecondSample { sec_index, ..SecondSample::default() });
+        }
+        self.current_sec = self.current_sec.max(sec);
+        closed
+    }
+
+    pub fn record(&mut self, ok: bool, latency_ms: u64) {
+        let sample = self.samples.back_mut().expect("window always has the current second");
+        match ok {
+            true => sample.req_ok += 1,
+            false => sample.req_err += 1,
+        }
+        sample.latency_sum_ms += latency_ms;
+        sample.latency_count += 1;
+    }
+
+    pub fn current_mut(&mut self) -> &mut SecondSample {
+        self.samples.back_mut().expect("window always has the current second")
+    }
+
+    /// Requests of the last completed second
+    pub fn last_rps(&self) -> u64 {
+        self.completed().last().map(SecondSample::requests).unwrap_or(0)
+    }
+
+    /// Requests per completed second, oldest first
+    pub fn rps_series(&self) -> Vec<u64> {
+        self.completed().map(SecondSample::requests).collect()
+    }
+
+    fn completed(&self) -> impl Iterator<Item = &SecondSample> {
+        self.samples.iter().take(self.samples.len().saturating_sub(1))
+    }
+}
@@ -1,2 +1,26 @@
-mod progress;
-mod tui;
\ No newline at end of file
+use libruntime::events::Event;
+use tokio::sync::mpsc;
+
+pub mod progress;
+pub mod tui;
+
+/// Live view of a run
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum UiMode {
+    /// Single progress bar
+    #[default]
+    Progress,
+    /// Full-screen dashboard
+    Tui,
+}
+
+/// Without a live view only the warnings of the run are shown
+pub async fn warnings(mut rx: mpsc::UnboundedReceiver<Event>) {
+    while let Some(ev) = rx.recv().await {
+        match ev {
+            Event::Warning { message } => eprintln!("{}", message),
+            Event::RunFinished => break,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use libruntime::events::Event;
+use tokio::sync::mpsc;
+use crate::stats::live::LiveStats;
+
+/// Default view: a single progress bar over the planned ticks
+pub async fn run(mut rx: mpsc::UnboundedReceiver<Event>, mut s: LiveStats) {
+    let pb = ProgressBar::new(s.totals.planned_total_ticks);
+    pb.set_style(
+        ProgressStyle::with_template("[{bar:60}] {pos}/{len} {msg}")
+            .unwrap()
+            .progress_chars("=>-"),
+    );
+
+    // таймер обновления UI
+    let mut ticker = tokio::time::interval(std::time::Duration::from_millis(250));
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                let snapshot = s.snapshot();
+                pb.set_position(snapshot.executed);
+                pb.set_message(format!(
+                    "ok={} err={} ({:.2}%) in_flight={}",
+                    snapshot.ok, snapshot.err, snapshot.err_rate, snapshot.in_flight
+                ));
+            }
+            ev = rx.recv() => {
+                match ev {
+                    Some(Event::RunFinished) | None => {
+                        pb.finish_with_message("done");
+                        break;
+                    }
+                    Some(Event::Warning { message }) => pb.println(message),
+                    Some(ev) => s.on_event(ev),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::time::Duration;
+use libruntime::events::{Event, StopSignal};
+use ratatui::crossterm::event::{self as term, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, Paragraph, Sparkline};
+use ratatui::{DefaultTerminal, Frame};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use crate::stats::latency::Percentiles;
+use crate::stats::live::{LiveSnapshot, LiveStats};
+
+/// Full-screen dashboard, redrawn every 250ms until the run finishes.
+/// `q` closes it and lets the run continue, Ctrl-C closes it and stops the run early.
+pub async fn run(mut rx: mpsc::UnboundedReceiver<Event>, mut s: LiveStats, title: String, stop: StopSignal) {
+    let mut terminal = Some(ratatui::init());
+    let started_at = Instant::now();
+    let mut ticker = tokio::time::interval(Duration::from_millis(250));
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                s.advance(started_at.elapsed().as_millis() as u64);
+                let Some(t) = terminal.as_mut() else {
+                    continue;
+                };
+                let snapshot = s.snapshot();
+                let _ = t.draw(|frame| render(frame, &snapshot, &title));
+                match pressed_key() {
+                    Some((KeyCode::Char('c'), KeyModifiers::CONTROL)) => {
+                        stop.stop();
+                        close(&mut terminal);
+                    }
+                    Some((KeyCode::Char('q'), _)) | Some((KeyCode::Esc, _)) => {
+                        close(&mut terminal);
+                    }
+                    _ => {}
+                }
+            }
+            ev = rx.recv() => {
+                match ev {
+                    Some(Event::RunFinished) | None => break,
+                    // once closed, the terminal is free again
+                    Some(Event::Warning { message }) if terminal.is_none() => eprintln!("{}", message),
+                    Some(ev) => s.on_event(ev),
+                }
+            }
+        }
+    }
+    close(&mut terminal);
+}
+
+fn close(terminal: &mut Option<DefaultTerminal>) {
+    if terminal.take().is_some() {
+        ratatui::restore();
+    }
+}
+
+/// The terminal is in raw mode, so Ctrl-C arrives as a key press
+fn pressed_key() -> Option<(KeyCode, KeyModifiers)> {
+    while term::poll(Duration::ZERO).ok()? {
+        if let term::Event::Key(key) = term::read().ok()? && key.kind == KeyEventKind::Press {
+            return Some((key.code, key.modifiers));
+        }
+    }
+    None
+}
+
+pub(crate) fn render(frame: &mut Frame, s: &LiveSnapshot, title: &str) {
+    let [header, progress, stage, stats, rps, errors] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(3),
+        Constraint::Length(3),
+        Constraint::Length(6),
+        Constraint::Length(8),
+        Constraint::Min(3),
+    ]).areas(frame.area());
+
+    frame.render_widget(Line::from(vec![
+        " rload ".bold().cyan(),
+        title.into(),
+        format!("  eta {}s", s.eta_ms.div_ceil(1000)).dark_gray(),
+        "  q: close dashboard".dark_gray(),
+    ]), header);
+
+    frame.render_widget(Gauge::default()
+        .block(Block::bordered().title(" ticks "))
+        .gauge_style(Style::default().fg(Color::Cyan))
+        .ratio(ratio(s.executed, s.total))
+        .label(format!("{}/{}", s.executed, s.total)), progress);
+
+    frame.render_widget(Gauge::default()
+        .block(Block::bordered().title(format!(" stage {}/{} ", s.stage_index + 1, s.stage_count.max(1))))
+        .gauge_style(Style::default().fg(Color::Blue))
+        .ratio(s.stage_progress.clamp(0.0, 1.0))
+        .label(format!("{:.0}%", s.stage_progress * 100.0)), stage);
+
+    let [throughput, latency] = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(stats);
+    frame.render_widget(Paragraph::new(vec![
+        Line::from(format!("rps        {} / {} planned", s.rps_1s, s.target_rps)),
+        Line::from(format!("requests   ok={} err={} ({:.2}%)", s.ok, s.err, s.err_rate)),
+        Line::from(format!("in flight  {}", s.in_flight)),
+    ]).block(Block::bordered().title(" throughput ")), throughput);
+    frame.render_widget(Paragraph::new(vec![
+        Line::from(format!("rolling  {}", percentiles(&s.rolling))),
+        Line::from(format!("overall  {}", percentiles(&s.overall))),
+    ]).block(Block::bordered().title(" latency ms ")), latency);
+
+    // newest seconds on the right
+    let width = rps.width.saturating_sub(2) as usize;
+    let series = &s.rps_series[s.rps_series.len().saturating_sub(width)..];
+    frame.render_widget(Sparkline::default()
+        .block(Block::bordered().title(format!(" rps, last {}s ", series.len())))
+        .style(Style::default().fg(Color::Green))
+        .data(series), rps);
+
+    render_errors(frame, s, errors);
+}
+
+fn render_errors(frame: &mut Frame, s: &LiveSnapshot, area: Rect) {
+    let mut lines: Vec<Line> = match s.errors.is_empty() {
+        true => vec![Line::from("no errors").dark_gray()],
+        false => s.errors.iter()
+            .map(|(label, count)| Line::from(format!("{:<16} {:>8}  {:.1}%", label, count, ratio(*count, s.err) * 100.0)))
+            .collect(),
+    };
+    if let Some(warning) = &s.last_warning {
+        lines.push(Line::from(format!("{} warnings, last: {}", s.warnings, warning)).yellow());
+    }
+    frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" errors ")), area);
+}
+
+fn percentiles(p: &Percentiles) -> String {
+    format!("p50={} p95={} p99={}", p.p50, p.p95, p.p99)
+}
+
+fn ratio(part: u64, total: u64) -> f64 {
+    match total {
+        0 => 0.0,
+        total => (part as f64 / total as f64).min(1.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::latency::Percentiles;
+    use crate::stats::live::LiveSnapshot;
+    use crate::ui::tui::render;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    #[test]
+    fn it_renders_dashboard() {
+        let snapshot = LiveSnapshot {
+            executed: 150,
+            total: 300,
+            rps_1s: 48,
+            target_rps: 50,
+            ok: 140,
+            err: 10,
+            err_rate: 6.67,
+            rolling: Percentiles { p50: 12, p95: 40, p99: 85 },
+            stage_index: 1,
+            stage_count: 3,
+            stage_progress: 0.5,
+            rps_series: vec![10, 30, 48],
+            errors: vec![("timeout".to_string(), 7), ("http_500".to_string(), 3)],
+            warnings: 2,
+            last_warning: Some("executor error: dns".to_string()),
+            ..LiveSnapshot::default()
+        };
+        let mut terminal = Terminal::new(TestBackend::new(80, 30)).unwrap();
+
+        terminal.draw(|frame| render(frame, &snapshot, "demo")).unwrap();
+
+        let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+        for expected in ["150/300", "stage 2/3", "rps        48 / 50 planned", "p50=12 p95=40 p99=85", "timeout", "http_500", "rps, last 3s", "2 warnings, last: executor error: dns"] {
+            assert!(screen.contains(expected), "missing '{}'", expected);
+        }
+    }
+}
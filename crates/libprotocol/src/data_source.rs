@@ -0,0 +1,77 @@
+//! Rows of the `data_sources` files. Loading lives here so `validate` checks
+//! the same columns the runtime will read.
+
+use crate::schema::DataFormat;
+use serde_json::Value;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DataTable {
+    pub columns: Vec<String>,
+    /// One value per column, missing JSON fields are empty
+    pub rows: Vec<Vec<String>>,
+}
+
+impl DataTable {
+    /// Errors do not name the file, callers know how the user wrote its path
+    pub fn load(path: &Path, format: DataFormat) -> Result<DataTable, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        match format {
+            DataFormat::Csv => Self::parse_csv(&content),
+            DataFormat::Jsonl => Self::parse_jsonl(&content),
+        }
+    }
+
+    pub fn parse_csv(content: &str) -> Result<DataTable, String> {
+        let mut reader = csv::Reader::from_reader(content.as_bytes());
+        let columns = reader.headers()
+            .map_err(|e| e.to_string())?
+            .iter()
+            .map(str::to_string)
+            .collect();
+        let rows = reader.records()
+            .map(|record| record.map(|r| r.iter().map(str::to_string).collect()))
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
+
+        Ok(DataTable { columns, rows })
+    }
+
+    /// Columns are the keys of all objects, in order of first appearance.
+    /// Strings are taken as is, other values as their JSON text.
+    pub fn parse_jsonl(content: &str) -> Result<DataTable, String> {
+        let mut objects = Vec::new();
+        for (i, line) in content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            match serde_json::from_str::<Value>(line) {
+                Ok(Value::Object(object)) => objects.push(object),
+                Ok(_) => return Err(format!("line {}: expected a JSON object", i + 1)),
+                Err(e) => return Err(format!("line {}: {}", i + 1, e)),
+            }
+        }
+        let mut columns: Vec<String> = Vec::new();
+        for key in objects.iter().flat_map(|object| object.keys()) {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+        let rows = objects.iter()
+            .map(|object| columns.iter().map(|column| match object.get(column) {
+                None | Some(Value::Null) => String::new(),
+                Some(Value::String(s)) => s.clone(),
+                Some(value) => value.to_string(),
+            }).collect())
+            .collect();
+
+        Ok(DataTable { columns, rows })
+    }
+
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|column| column == name)
+    }
+
+    pub fn value(&self, row: usize, column: &str) -> Option<&str> {
+        let column = self.column(column)?;
+        self.rows.get(row)?.get(column).map(String::as_str)
+    }
+}
+
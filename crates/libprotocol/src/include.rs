@@ -0,0 +1,344 @@
+//! Scenario composition, resolved into one JSON value before validation:
+//! - `{"$ref": "file.yaml#/pointer"}` is replaced by that part of the file (the whole file without
+//!   a pointer). Inside an array, a `$ref` to an array is spliced in, e.g. a group of steps.
+//! - a root `"include": ["file.yaml"]` merges partial scenarios: missing keys are taken, arrays
+//!   are appended and objects get the keys they lack.
+//!
+//! Paths are relative to the file that contains them. Every node keeps where it came from,
+//! so validation errors in included content point into the included file.
+
+use crate::scenario_format::ScenarioFormat;
+use crate::{ProtocolError, ValidationError};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub(crate) struct Composed {
+    pub(crate) value: Value,
+    origins: Vec<Origin>,
+    root: PathBuf,
+}
+
+/// The node at `at` in the composed value is `pointer` of `file`
+#[derive(Clone)]
+struct Origin {
+    at: Vec<String>,
+    file: PathBuf,
+    pointer: String,
+}
+
+struct Resolver {
+    root: PathBuf,
+    /// Files and pointers being resolved, to detect cycles
+    stack: Vec<(PathBuf, String)>,
+    origins: Vec<Origin>,
+    errors: Vec<ValidationError>,
+    files: HashMap<PathBuf, Value>,
+}
+
+/// Syntax errors of the main file are returned as is, problems with referenced files as
+/// validation errors located at the `$ref` or `include` that names them
+pub(crate) fn compose(path: &Path, format: ScenarioFormat) -> Result<Composed, ProtocolError> {
+    let value = format.parse(&fs::read_to_string(path)?)?;
+    let root = fs::canonicalize(path)?;
+    let mut resolver = Resolver {
+        root: root.clone(),
+        stack: vec![(root.clone(), String::new())],
+        origins: Vec::new(),
+        errors: Vec::new(),
+        files: HashMap::from([(root.clone(), value.clone())]),
+    };
+    let value = resolver.document(value, &root, "");
+
+    if !resolver.errors.is_empty() {
+        return Err(crate::ValidationErrors { items: resolver.errors }.into());
+    }
+    Ok(Composed { value, origins: resolver.origins, root })
+}
+
+impl Composed {
+    /// Turns a JSON-pointer `path` of the composed value into `file#/pointer` when the node comes
+    /// from an included file. Paths into the main file are kept as they are.
+    pub(crate) fn locate(&self, path: &str) -> String {
+        let Some(tokens) = path.strip_prefix('/').map(|p| p.split('/').collect::<Vec<_>>()) else {
+            return path.to_string();
+        };
+        let origin = self.origins.iter()
+            .filter(|o| o.at.len() <= tokens.len() && o.at.iter().zip(&tokens).all(|(a, b)| a == b))
+            .max_by_key(|o| o.at.len());
+        let Some(origin) = origin else {
+            return path.to_string();
+        };
+        let rest: String = tokens[origin.at.len()..].iter().map(|t| format!("/{}", t)).collect();
+        location(&self.root, &origin.file, &format!("{}{}", origin.pointer, rest))
+    }
+}
+
+/// Files named by the `$ref`s and root `include` of a scenario, as written
+pub(crate) fn references(value: &Value) -> Vec<String> {
+    fn walk(value: &Value, files: &mut Vec<String>) {
+        match value {
+            Value::Object(object) => {
+                if let Some(reference) = object.get("$ref").and_then(Value::as_str) {
+                    files.push(reference.split('#').next().unwrap_or_default().to_string());
+                }
+                object.values().for_each(|value| walk(value, files));
+            }
+            Value::Array(items) => items.iter().for_each(|item| walk(item, files)),
+            _ => {}
+        }
+    }
+    let mut files: Vec<String> = value.get("include").and_then(Value::as_array).into_iter().flatten()
+        .filter_map(Value::as_str)
+        .map(str::to_string)
+        .collect();
+    walk(value, &mut files);
+    let mut unique = Vec::new();
+    for file in files.into_iter().filter(|file| !file.is_empty()) {
+        if !unique.contains(&file) {
+            unique.push(file);
+        }
+    }
+    unique
+}
+
+/// `pointer` alone in the main file, `file#pointer` in others, with `file` relative to the main file
+fn location(root: &Path, file: &Path, pointer: &str) -> String {
+    if file == root {
+        return pointer.to_string();
+    }
+    format!("{}#{}", relative(root, file), pointer)
+}
+
+/// As written in a `$ref`
+fn reference(root: &Path, file: &Path, pointer: &str) -> String {
+    match pointer {
+        "" => relative(root, file),
+        pointer => format!("{}#{}", relative(root, file), pointer),
+    }
+}
+
+fn relative(root: &Path, file: &Path) -> String {
+    let dir = root.parent().unwrap_or(Path::new(""));
+    file.strip_prefix(dir).unwrap_or(file).display().to_string()
+}
+
+fn child(tokens: &[String], token: impl ToString) -> Vec<String> {
+    let mut tokens = tokens.to_vec();
+    tokens.push(token.to_string());
+    tokens
+}
+
+impl Resolver {
+    fn error(&mut self, file: &Path, pointer: &str, message: String) {
+        self.errors.push(ValidationError {
+            path: location(&self.root, file, pointer),
+            code: "invalid_ref".to_string(),
+            message,
+        });
+    }
+
+    /// Root of a file: resolves it, then merges its `include`s
+    fn document(&mut self, value: Value, file: &Path, pointer: &str) -> Value {
+        let Value::Object(mut object) = value else {
+            return self.node(value, file, pointer, &[]);
+        };
+        let includes = match object.remove("include") {
+            None => Vec::new(),
+            Some(Value::Array(items)) if items.iter().all(Value::is_string) => {
+                items.into_iter().filter_map(|i| i.as_str().map(str::to_string)).collect()
+            }
+            Some(_) => {
+                self.error(file, &format!("{}/include", pointer), "include must be an array of file paths".to_string());
+                Vec::new()
+            }
+        };
+        let Value::Object(mut merged) = self.node(Value::Object(object), file, pointer, &[]) else {
+            unreachable!("an object resolves to an object");
+        };
+
+        for (i, include) in includes.iter().enumerate() {
+            let at = format!("{}/include/{}", pointer, i);
+            let Some((included_file, value)) = self.load(file, &at, include, "") else {
+                continue;
+            };
+            if !value.is_object() {
+                self.error(file, &at, format!("{} must contain an object to be included", include));
+                continue;
+            }
+            // the included document is resolved on its own, its origins are moved where its keys land
+            let outer = std::mem::take(&mut self.origins);
+            self.stack.push((included_file.clone(), String::new()));
+            let Value::Object(included) = self.document(value, &included_file, "") else {
+                unreachable!("an object resolves to an object");
+            };
+            self.stack.pop();
+            let inner = std::mem::replace(&mut self.origins, outer);
+
+            for (key, value) in included {
+                self.merge(&mut merged, key, value, &included_file, &inner);
+            }
+        }
+        Value::Object(merged)
+    }
+
+    fn merge(&mut self, into: &mut Map<String, Value>, key: String, value: Value, file: &Path, inner: &[Origin]) {
+        // origins of the included subtree `from`, moved to `to`
+        let moved = |from: Vec<String>, to: Vec<String>, pointer: String, origins: &mut Vec<Origin>| {
+            origins.push(Origin { at: to.clone(), file: file.to_path_buf(), pointer });
+            for origin in inner.iter().filter(|o| o.at.starts_with(&from)) {
+                let mut at = to.clone();
+                at.extend(origin.at[from.len()..].iter().cloned());
+                origins.push(Origin { at, ..origin.clone() });
+            }
+        };
+        match (into.get_mut(&key), value) {
+            (None, value) => {
+                moved(vec![key.clone()], vec![key.clone()], format!("/{}", key), &mut self.origins);
+                into.insert(key, value);
+            }
+            (Some(Value::Array(own)), Value::Array(items)) => {
+                for (j, item) in items.into_iter().enumerate() {
+                    let to = vec![key.clone(), own.len().to_string()];
+                    moved(vec![key.clone(), j.to_string()], to, format!("/{}/{}", key, j), &mut self.origins);
+                    own.push(item);
+                }
+            }
+            (Some(Value::Object(own)), Value::Object(fields)) => {
+                for (name, field) in fields {
+                    if own.contains_key(&name) {
+                        continue;
+                    }
+                    let at = vec![key.clone(), name.clone()];
+                    moved(at.clone(), at, format!("/{}/{}", key, name), &mut self.origins);
+                    own.insert(name, field);
+                }
+            }
+            // the including file wins
+            (Some(_), _) => {}
+        }
+    }
+
+    fn node(&mut self, value: Value, file: &Path, pointer: &str, at: &[String]) -> Value {
+        match value {
+            Value::Object(object) if object.contains_key("$ref") => {
+                let Some((target, pointer, value)) = self.reference(&object, file, pointer) else {
+                    return Value::Null;
+                };
+                self.origins.push(Origin { at: at.to_vec(), file: target.clone(), pointer: pointer.clone() });
+                self.stack.push((target.clone(), pointer.clone()));
+                let value = self.node(value, &target, &pointer, at);
+                self.stack.pop();
+                value
+            }
+            Value::Object(object) => Value::Object(object.into_iter()
+                .map(|(key, value)| {
+                    let value = self.node(value, file, &format!("{}/{}", pointer, key), &child(at, &key));
+                    (key, value)
+                })
+                .collect()),
+            Value::Array(items) => {
+                let mut out = Vec::with_capacity(items.len());
+                for (i, item) in items.into_iter().enumerate() {
+                    let item_pointer = format!("{}/{}", pointer, i);
+                    let spliced = match &item {
+                        Value::Object(object) if object.contains_key("$ref") => self.reference(object, file, &item_pointer),
+                        _ => None,
+                    };
+                    match spliced {
+                        Some((target, target_pointer, Value::Array(group))) => {
+                            self.stack.push((target.clone(), target_pointer.clone()));
+                            for (j, item) in group.into_iter().enumerate() {
+                                let item_at = child(at, out.len());
+                                let item_pointer = format!("{}/{}", target_pointer, j);
+                                self.origins.push(Origin { at: item_at.clone(), file: target.clone(), pointer: item_pointer.clone() });
+                                out.push(self.node(item, &target, &item_pointer, &item_at));
+                            }
+                            self.stack.pop();
+                        }
+                        Some((target, target_pointer, value)) => {
+                            let item_at = child(at, out.len());
+                            self.origins.push(Origin { at: item_at.clone(), file: target.clone(), pointer: target_pointer.clone() });
+                            self.stack.push((target.clone(), target_pointer.clone()));
+                            out.push(self.node(value, &target, &target_pointer, &item_at));
+                            self.stack.pop();
+                        }
+                        None if item.get("$ref").is_some() => out.push(Value::Null),
+                        None => {
+                            // earlier splices moved the item
+                            if out.len() != i {
+                                self.origins.push(Origin { at: child(at, out.len()), file: file.to_path_buf(), pointer: item_pointer.clone() });
+                            }
+                            let item_at = child(at, out.len());
+                            out.push(self.node(item, file, &item_pointer, &item_at));
+                        }
+                    }
+                }
+                Value::Array(out)
+            }
+            value => value,
+        }
+    }
+
+    /// Target file, pointer and value of a `{"$ref": ...}` object
+    fn reference(&mut self, object: &Map<String, Value>, file: &Path, pointer: &str) -> Option<(PathBuf, String, Value)> {
+        let at = format!("{}/$ref", pointer);
+        if object.len() > 1 {
+            self.error(file, pointer, "a $ref object must not have other keys".to_string());
+            return None;
+        }
+        let Some(reference) = object["$ref"].as_str() else {
+            self.error(file, &at, "$ref must be a string".to_string());
+            return None;
+        };
+        let (path, target_pointer) = reference.split_once('#').unwrap_or((reference, ""));
+        let (target, value) = self.load(file, &at, path, target_pointer)?;
+        Some((target, target_pointer.to_string(), value))
+    }
+
+    /// `path` relative to `file`, empty for `file` itself. Reports missing files, syntax errors,
+    /// missing pointers and cycles at `at` of `file`.
+    fn load(&mut self, file: &Path, at: &str, path: &str, pointer: &str) -> Option<(PathBuf, Value)> {
+        let target = match path {
+            "" => file.to_path_buf(),
+            path => match fs::canonicalize(file.parent().unwrap_or(Path::new("")).join(path)) {
+                Ok(target) => target,
+                Err(e) => {
+                    self.error(file, at, format!("cannot read {}: {}", path, e));
+                    return None;
+                }
+            },
+        };
+        if self.stack.iter().any(|(f, p)| *f == target && p == pointer) {
+            let mut chain: Vec<String> = self.stack.iter()
+                .skip_while(|(f, p)| !(*f == target && p == pointer))
+                .map(|(f, p)| reference(&self.root, f, p))
+                .collect();
+            chain.push(reference(&self.root, &target, pointer));
+            self.error(file, at, format!("include cycle: {}", chain.join(" -> ")));
+            return None;
+        }
+        if !self.files.contains_key(&target) {
+            let parsed = fs::read_to_string(&target)
+                .map_err(ProtocolError::from)
+                .and_then(|content| ScenarioFormat::from_path(&target).parse(&content));
+            match parsed {
+                Ok(value) => {
+                    self.files.insert(target.clone(), value);
+                }
+                Err(e) => {
+                    self.error(file, at, format!("{}: {}", path, e));
+                    return None;
+                }
+            }
+        }
+        match self.files[&target].pointer(pointer) {
+            Some(value) => Some((target, value.clone())),
+            None => {
+                self.error(file, at, format!("{} has nothing at #{}", path, pointer));
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! Minimal JSONPath used by `extract` and `checks`: `$`, `.key`, `['key']` and `[index]`.
+
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    pub segments: Vec<PathSegment>,
+}
+
+impl JsonPath {
+    pub fn parse(input: &str) -> Result<JsonPath, String> {
+        let rest = input.strip_prefix('$')
+            .ok_or_else(|| format!("JSONPath must start with '$': '{}'", input))?;
+        let mut segments = Vec::new();
+        let mut chars = rest.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '.' => {
+                    let mut key = String::new();
+                    while let Some((_, c)) = chars.peek() {
+                        if *c == '.' || *c == '[' {
+                            break;
+                        }
+                        key.push(*c);
+                        chars.next();
+                    }
+                    if key.is_empty() {
+                        return Err(format!("empty key at {} in JSONPath '{}'", i + 1, input));
+                    }
+                    segments.push(PathSegment::Key(key));
+                }
+                '[' => {
+                    let close = rest[i..].find(']')
+                        .ok_or_else(|| format!("unclosed '[' at {} in JSONPath '{}'", i + 1, input))?;
+                    let inner = &rest[i + 1..i + close];
+                    let segment = if let Some(key) = inner.strip_prefix('\'').and_then(|k| k.strip_suffix('\'')) {
+                        PathSegment::Key(key.to_string())
+                    } else {
+                        PathSegment::Index(inner.parse::<usize>()
+                            .map_err(|_| format!("invalid index '{}' in JSONPath '{}'", inner, input))?)
+                    };
+                    segments.push(segment);
+                    while let Some((j, _)) = chars.peek() {
+                        if *j > i + close {
+                            break;
+                        }
+                        chars.next();
+                    }
+                }
+                _ => return Err(format!("unexpected '{}' at {} in JSONPath '{}'", c, i + 1, input)),
+            }
+        }
+
+        Ok(JsonPath { segments })
+    }
+
+    pub fn select<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        self.segments.iter().try_fold(value, |current, segment| match segment {
+            PathSegment::Key(key) => current.get(key),
+            PathSegment::Index(index) => current.get(index),
+        })
+    }
+}
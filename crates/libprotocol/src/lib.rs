@@ -1,14 +1,24 @@
 use std::io::Write;
 mod protocol_error;
 mod semantic_validator;
+mod include;
 pub mod schema;
+pub mod template;
+pub mod json_path;
+pub mod data_source;
+pub mod scenario_format;
+pub mod overrides;
+pub mod migration;
 
 use crate::protocol_error::ValidationErrors;
-pub use crate::protocol_error::{JsonError, ProtocolError, ValidationError};
+pub use crate::protocol_error::{JsonError, ProtocolError, SyntaxError, ValidationError};
+pub use crate::scenario_format::ScenarioFormat;
+pub use crate::overrides::{Override, Overrides};
+pub use crate::migration::CURRENT_VERSION;
+pub use crate::migration::Change;
 pub use crate::schema::Scenario;
 use crate::semantic_validator::Validator;
 use anyhow::Context;
-use schemars::schema_for;
 use serde_json::Value;
 use std::fs;
 use std::io::stderr;
@@ -16,27 +26,52 @@ use std::path::{Path, PathBuf};
 
 pub type Result<T> = std::result::Result<T, ProtocolError>;
 
+/// The format is taken from the extension, see `ScenarioFormat::from_path`.
+/// `$ref` and `include` are resolved, see `include`. Older versions are migrated, see `migration`.
 pub fn parse_scenario(path: impl AsRef<Path>) -> Scenario {
-    let path = path.as_ref();
-    let json_content = fs::read_to_string(path).unwrap();
+    read_scenario(&path, ScenarioFormat::from_path(&path), &Overrides::default()).unwrap()
+}
+
+/// `parse_scenario` in the given format, with a profile and overrides applied, see `overrides`
+pub fn read_scenario(path: impl AsRef<Path>, format: ScenarioFormat, overrides: &Overrides) -> Result<Scenario> {
+    let (composed, _) = compose(path.as_ref(), format, overrides)?;
 
-    serde_json::from_str(&json_content).unwrap()
+    serde_json::from_value(composed.value)
+        .map_err(|e| JsonError { line: 0, column: 0, message: e.to_string() }.into())
+}
+
+/// Also returns what was changed to bring the scenario to the current version
+fn compose(path: &Path, format: ScenarioFormat, overrides: &Overrides) -> Result<(include::Composed, Vec<Change>)> {
+    let mut composed = include::compose(path, format)?;
+    let changes = migration::upgrade(&mut composed.value);
+    let errors = overrides.apply(&mut composed.value);
+    if !errors.is_empty() {
+        let items = errors.into_iter()
+            .map(|error| ValidationError { path: composed.locate(&error.path), ..error })
+            .collect();
+        return Err(ValidationErrors { items }.into());
+    }
+    Ok((composed, changes))
 }
 
 
 pub fn validate(path: impl AsRef<Path>) -> Result<()> {
+    validate_with(&path, ScenarioFormat::from_path(&path), &Overrides::default())?;
+    Ok(())
+}
+
+/// Validates the scenario as it runs: with the profile and overrides applied. Returns what was
+/// changed in memory to bring it to the current version, see `migration`.
+pub fn validate_with(path: impl AsRef<Path>, format: ScenarioFormat, overrides: &Overrides) -> Result<Vec<Change>> {
     let path = path.as_ref();
-    eprintln!("scenario path: {}", path.display());
-    let json_content = fs::read_to_string(path)?;
-    let schema = schema_for!(Scenario);
-    let schema_json: Value = serde_json::to_value(&schema)
-        .map_err(|e| JsonError { line: 0, column: 0, message: e.to_string() })?;
-
-    let scenario_json: Value = serde_json::from_str(&json_content).map_err(|e| JsonError {
-        line: e.line(),
-        column: e.column(),
-        message: e.to_string(),
-    })?;
+    let (composed, changes) = match compose(path, format, overrides) {
+        Err(ProtocolError::Validation(e)) => return Err(invalid(e.items)),
+        composed => composed?,
+    };
+    // unknown fields are rejected in scenarios written for the current version only
+    let schema_json = migration::current_schema(changes.is_empty());
+
+    let scenario_json = &composed.value;
 
     let validator = jsonschema::validator_for(&schema_json).map_err(|e| {
         ProtocolError::Validation(ValidationErrors {
@@ -50,7 +85,7 @@ pub fn validate(path: impl AsRef<Path>) -> Result<()> {
 
     let mut errors: Vec<ValidationError>     = Vec::new();
 
-    for err in validator.iter_errors(&scenario_json) {
+    for err in validator.iter_errors(scenario_json) {
         errors.push(ValidationError {
             path: err.instance_path().to_string(), // типа "/workload/stages"
             code: "".to_string(),        // грубо, но ок для MVP
@@ -63,28 +98,54 @@ pub fn validate(path: impl AsRef<Path>) -> Result<()> {
         .with_rule(semantic_validator::NameRule::new())
         .with_rule(semantic_validator::WebProtocolRule::new())
         .with_rule(semantic_validator::StagesRule::new())
+        .with_rule(semantic_validator::ConstantVusRule::new())
         .with_rule(semantic_validator::DurationRule::new())
         .with_rule(semantic_validator::RpsRule::new())
         .with_rule(semantic_validator::VersionRule::new())
-        .with_rule(semantic_validator::JourneysRule::new());
-    business.validate(&scenario_json, &mut errors);
+        .with_rule(semantic_validator::JourneysRule::new())
+        .with_rule(semantic_validator::ExtractRule::new())
+        .with_rule(semantic_validator::ChecksRule::new())
+        .with_rule(semantic_validator::TemplatesRule::new())
+        .with_rule(semantic_validator::DataSourcesRule::new(path.parent().unwrap_or(Path::new(""))))
+        .with_rule(semantic_validator::TlsRule::new())
+        .with_rule(semantic_validator::ThresholdsRule::new())
+        .with_rule(semantic_validator::ReportRule::new())
+        .with_rule(semantic_validator::TracingRule::new());
+    business.validate(scenario_json, &mut errors);
 
     if !errors.is_empty() {
-        writeln!(stderr(), "Scenario is invalid ({} errors)", errors.len()).expect("Something went wrong while print stderr");
-        return Err(ValidationErrors { items: errors }.into());
+        for error in &mut errors {
+            error.path = composed.locate(&error.path);
+        }
+        return Err(invalid(errors));
     }
     println!("ok");
 
-    Ok(())
+    Ok(changes)
+}
+
+fn invalid(errors: Vec<ValidationError>) -> ProtocolError {
+    writeln!(stderr(), "Scenario is invalid ({} errors)", errors.len()).expect("Something went wrong while print stderr");
+    ValidationErrors { items: errors }.into()
 }
 
 pub fn export_schema(out_path: impl AsRef<Path>, version: Option<String>) -> anyhow::Result<()> {
     let path = out_path.as_ref();
     let final_path = with_version(path, version.as_deref())?;
-    let mut schema = schema_for!(Scenario);
-    let v = version.unwrap_or_else(|| "1".to_string());
-    schema.insert("$version".to_string(), serde_json::Value::String(v));
-    let _ = fs::write(final_path, serde_json::to_string_pretty(&schema).unwrap());
+    let v = match version.as_deref() {
+        None => CURRENT_VERSION,
+        Some(v) => v.parse().map_err(|_| anyhow::anyhow!("invalid scenario version '{}'", v))?,
+    };
+    let content = match migration::frozen_schema(v) {
+        Some(schema) => schema.to_string(),
+        None if v == CURRENT_VERSION => {
+            let mut schema = migration::current_schema(true);
+            schema["$version"] = Value::String(v.to_string());
+            serde_json::to_string_pretty(&schema)?
+        }
+        None => anyhow::bail!("unknown scenario version {}, supported: 1 to {}", v, CURRENT_VERSION),
+    };
+    let _ = fs::write(final_path, content);
     println!("Schema exported successfully !");
 
     Ok(())
@@ -111,13 +172,77 @@ fn with_version(path: &Path, version: Option<&str>) -> anyhow::Result<PathBuf> {
 
 
 pub fn generate_scenario(out_path: impl AsRef<Path>, version: &str) -> anyhow::Result<()> {
+    generate_scenario_as(&out_path, version, ScenarioFormat::from_path(&out_path))
+}
+
+pub fn generate_scenario_as(out_path: impl AsRef<Path>, version: &str, format: ScenarioFormat) -> anyhow::Result<()> {
     let path = out_path.as_ref();
-    let mut default_scenario: Scenario = Scenario::default();
-    default_scenario = default_scenario.set_version(version.parse().unwrap_or(1u16));
-    fs::write(path, serde_json::to_string_pretty(&default_scenario)?)
+    if version.parse() != Ok(CURRENT_VERSION) {
+        anyhow::bail!("cannot generate version {}, new scenarios are version {}", version, CURRENT_VERSION);
+    }
+    let default_scenario: Scenario = Scenario::default();
+    fs::write(path, format.to_string_pretty(&default_scenario)?)
         .context("Failed to write default scenario to file")
 }
 
+/// What `migrate_scenario` did
+#[derive(Debug)]
+pub struct Migrated {
+    pub from: u16,
+    pub to: u16,
+    pub changes: Vec<Change>,
+    pub out_path: PathBuf,
+    /// Files named by `$ref` and `include`, which are left as they are
+    pub references: Vec<String>,
+}
+
+/// Writes the scenario at version `to`, to `<name>-v<to>.<ext>` next to it unless `out_path` is
+/// given. The output format follows the output extension, the fields keep their order. `$ref` and
+/// `include` are kept: when version `to` rejects unknown fields, the files they name must not have any.
+pub fn migrate_scenario(path: impl AsRef<Path>, to: u16, out_path: Option<&Path>) -> anyhow::Result<Migrated> {
+    let path = path.as_ref();
+    let format = ScenarioFormat::from_path(path);
+    let content = fs::read_to_string(path)?;
+    let mut scenario = format.parse(&content)?;
+    let from = migration::version(&scenario)?;
+    let changes = migration::migrate(&mut scenario, to)?;
+    if migration::MIGRATIONS.iter().any(|m| m.from >= from && m.from < to && m.rejects_unknown_fields) {
+        let mut unknown = migration::unknown_fields(&scenario);
+        unknown.extend(referenced_unknown_fields(path, format));
+        if !unknown.is_empty() {
+            anyhow::bail!(migration::MigrationError {
+                message: format!("version {} rejects these unknown fields, remove them or prefix them with `{}`: {}",
+                                 to, migration::EXTENSION_PREFIX, unknown.join(", ")),
+            });
+        }
+    }
+
+    let out_path = match out_path {
+        Some(out_path) => out_path.to_path_buf(),
+        None => with_version(path, Some(&to.to_string()))?,
+    };
+    let out_format = ScenarioFormat::from_path(&out_path);
+    if out_format == ScenarioFormat::Toml {
+        scenario = scenario_format::without_nulls(scenario);
+    }
+    fs::write(&out_path, migration::to_string_in_order(&scenario, out_format, &content, format)?)
+        .with_context(|| format!("cannot write {}", out_path.display()))?;
+
+    Ok(Migrated { from, to, changes, out_path, references: include::references(&scenario) })
+}
+
+/// Unknown fields of the content taken from `$ref` and `include` files, located in those files,
+/// see `migration::unknown_fields`
+fn referenced_unknown_fields(path: &Path, format: ScenarioFormat) -> Vec<String> {
+    let Ok(composed) = include::compose(path, format) else {
+        return Vec::new();
+    };
+    migration::unknown_fields(&composed.value).iter()
+        .map(|pointer| composed.locate(pointer))
+        .filter(|at| at.contains('#'))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -129,7 +254,7 @@ mod tests {
         let output_file = tmp.path().join("result.txt");
         let result_file = tmp.path().join("result-v1.txt");
 
-        export_schema(output_file.to_str().unwrap().to_string(), Some(String::from("1")))?;
+        export_schema(output_file.to_str().unwrap(), Some(String::from("1")))?;
 
         let actual = fs::read_to_string(result_file)?;
         insta::assert_snapshot!(actual);
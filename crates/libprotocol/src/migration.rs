@@ -0,0 +1,374 @@
+//! Scenario format versions. Older scenarios are upgraded in memory when they are read, and
+//! `rload migrate` writes the upgraded file. Steps work on the JSON value, so fields they do not
+//! know about are kept.
+//!
+//! Version 2 rejects unknown fields, so that a misspelled option fails validation instead of being
+//! ignored. Fields starting with `x-` are extensions and stay allowed.
+
+use crate::{Scenario, ScenarioFormat};
+use schemars::schema_for;
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Serialize, Serializer};
+use serde_json::{json, Map, Value};
+
+pub const CURRENT_VERSION: u16 = 2;
+
+/// Prefix of extension fields, see above
+pub const EXTENSION_PREFIX: &str = "x-";
+
+/// Schemas of older versions, as they were last exported
+const SCHEMA_V1: &str = include_str!("schema-v1.json");
+
+/// Returns the JSON-pointer path and description of every change
+type Apply = fn(&mut Map<String, Value>) -> Vec<(String, String)>;
+
+/// Upgrades a scenario from version `from` to `from + 1`
+pub struct Migration {
+    pub from: u16,
+    pub summary: &'static str,
+    /// Version `from + 1` rejects unknown fields, in the files named by `$ref` and `include` too
+    pub rejects_unknown_fields: bool,
+    apply: Apply,
+}
+
+/// Registered steps, one per version: `MIGRATIONS[i]` upgrades version `i + 1`
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 1,
+        summary: "the unused `metadata` is dropped, unknown fields are renamed to `x-` extensions",
+        rejects_unknown_fields: true,
+        apply: v1_to_v2,
+    },
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    /// Version the change was made for
+    pub version: u16,
+    /// Where the change is in the migrated scenario
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{} {}: {}", self.version, self.path, self.message)
+    }
+}
+
+#[derive(Debug)]
+pub struct MigrationError {
+    pub message: String,
+}
+
+impl std::fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cannot migrate scenario: {}", self.message)
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+fn error(message: String) -> MigrationError {
+    MigrationError { message }
+}
+
+pub fn version(scenario: &Value) -> Result<u16, MigrationError> {
+    let version = scenario.get("version").ok_or_else(|| error("it has no version".to_string()))?;
+    version.as_u64()
+        .and_then(|v| u16::try_from(v).ok())
+        .filter(|v| (1..=CURRENT_VERSION).contains(v))
+        .ok_or_else(|| error(format!("unknown version {}, supported: 1 to {}", version, CURRENT_VERSION)))
+}
+
+/// Applies the registered steps from the scenario version up to `to`
+pub fn migrate(scenario: &mut Value, to: u16) -> Result<Vec<Change>, MigrationError> {
+    let from = version(scenario)?;
+    if !(1..=CURRENT_VERSION).contains(&to) {
+        return Err(error(format!("unknown version {}, supported: 1 to {}", to, CURRENT_VERSION)));
+    }
+    if to < from {
+        return Err(error(format!("version {} cannot be migrated down to {}", from, to)));
+    }
+    let Value::Object(object) = scenario else {
+        unreachable!("a scenario with a version is an object");
+    };
+    let mut changes = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= from && m.from < to) {
+        let version = migration.from + 1;
+        object.insert("version".to_string(), Value::from(version));
+        changes.push(Change { version, path: "/version".to_string(), message: format!("{} -> {}", migration.from, version) });
+        changes.extend((migration.apply)(object).into_iter().map(|(path, message)| Change { version, path, message }));
+    }
+    Ok(changes)
+}
+
+/// Brings a scenario being read to `CURRENT_VERSION`. Unknown versions are left to validation.
+pub(crate) fn upgrade(scenario: &mut Value) -> Vec<Change> {
+    match version(scenario) {
+        Ok(version) if version < CURRENT_VERSION => migrate(scenario, CURRENT_VERSION).unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// Schema of an older version. The current one is generated from `Scenario`
+pub(crate) fn frozen_schema(version: u16) -> Option<&'static str> {
+    match version {
+        1 => Some(SCHEMA_V1),
+        _ => None,
+    }
+}
+
+/// Schema of the current version, from `Scenario`. A `strict` one has `additionalProperties: false`
+/// on every object with properties, `x-` extensions aside.
+pub(crate) fn current_schema(strict: bool) -> Value {
+    let mut schema = serde_json::to_value(schema_for!(Scenario)).unwrap_or_default();
+    if strict {
+        reject_unknown_fields(&mut schema);
+    }
+    schema
+}
+
+fn reject_unknown_fields(schema: &mut Value) {
+    match schema {
+        Value::Object(object) => {
+            if object.contains_key("properties") && !object.contains_key("additionalProperties") {
+                object.insert("additionalProperties".to_string(), Value::Bool(false));
+                object.insert("patternProperties".to_string(), json!({ format!("^{}", EXTENSION_PREFIX): {} }));
+            }
+            object.values_mut().for_each(reject_unknown_fields);
+        }
+        Value::Array(items) => items.iter_mut().for_each(reject_unknown_fields),
+        _ => {}
+    }
+}
+
+/// JSON-pointer paths of the fields the current schema does not have, `x-` extensions and the
+/// root `include` aside. Objects with a `$ref` name another file and are not looked into.
+pub(crate) fn unknown_fields(scenario: &Value) -> Vec<String> {
+    let schema = current_schema(false);
+    let mut unknown = Vec::new();
+    walk(scenario, &schema, &schema, "", &mut unknown);
+    unknown.retain(|path| path != "/include");
+    unknown
+}
+
+fn walk(value: &Value, schema: &Value, root: &Value, path: &str, unknown: &mut Vec<String>) {
+    let schema = resolve(schema, root);
+    if let Some(variants) = schema.get("oneOf").or_else(|| schema.get("anyOf")).and_then(Value::as_array) {
+        if let Some(variant) = variants.iter().map(|variant| resolve(variant, root)).find(|variant| tagged(value, variant)) {
+            walk(value, variant, root, path, unknown);
+        }
+        return;
+    }
+    match value {
+        Value::Object(fields) if fields.contains_key("$ref") => {}
+        Value::Object(fields) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            let additional = schema.get("additionalProperties").filter(|additional| additional.is_object());
+            for (key, field) in fields {
+                let at = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+                match (properties.and_then(|properties| properties.get(key)), additional) {
+                    (Some(property), _) | (None, Some(property)) => walk(field, property, root, &at, unknown),
+                    (None, None) if properties.is_some() && !key.starts_with(EXTENSION_PREFIX) => unknown.push(at),
+                    (None, None) => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item) = schema.get("items") {
+                for (i, value) in items.iter().enumerate() {
+                    walk(value, item, root, &format!("{}/{}", path, i), unknown);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `#/$defs/<name>` references are followed, others are left as they are
+fn resolve<'a>(schema: &'a Value, root: &'a Value) -> &'a Value {
+    schema.get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix("#"))
+        .and_then(|pointer| root.pointer(pointer))
+        .unwrap_or(schema)
+}
+
+/// An object variant whose constant properties, like a step `type`, are the ones of `value`
+fn tagged(value: &Value, variant: &Value) -> bool {
+    let (Value::Object(fields), Some(properties)) = (value, variant.get("properties").and_then(Value::as_object)) else {
+        return false;
+    };
+    properties.iter()
+        .filter_map(|(key, property)| property.get("const").map(|constant| (key, constant)))
+        .all(|(key, constant)| fields.get(key) == Some(constant))
+}
+
+/// Renames the field at the JSON-pointer `path` to `x-<name>`. Returns the new path, or `None`
+/// when the extension is already there.
+fn rename_to_extension(scenario: &mut Value, path: &str) -> Option<String> {
+    let (parent, key) = path.rsplit_once('/')?;
+    let fields = scenario.pointer_mut(parent)?.as_object_mut()?;
+    let key = key.replace("~1", "/").replace("~0", "~");
+    let extension = format!("{}{}", EXTENSION_PREFIX, key);
+    if fields.contains_key(&extension) {
+        return None;
+    }
+    let value = fields.remove(&key)?;
+    fields.insert(extension.clone(), value);
+    Some(format!("{}/{}", parent, extension.replace('~', "~0").replace('/', "~1")))
+}
+
+fn v1_to_v2(scenario: &mut Map<String, Value>) -> Vec<(String, String)> {
+    let mut changes = Vec::new();
+    if scenario.get("metadata") == Some(&Value::Null) {
+        scenario.remove("metadata");
+        changes.push(("/metadata".to_string(), "removed, it could only be null".to_string()));
+    }
+    let mut value = Value::Object(std::mem::take(scenario));
+    for path in unknown_fields(&value) {
+        let message = match rename_to_extension(&mut value, &path) {
+            Some(renamed) => format!("unknown field, renamed to {}", renamed),
+            None => format!("unknown field, not renamed: its `{}` extension is already there", EXTENSION_PREFIX),
+        };
+        changes.push((path, message));
+    }
+    if let Value::Object(object) = value {
+        *scenario = object;
+    }
+    changes
+}
+
+/// Writes a migrated scenario with its fields in the order of `original`, the document it was
+/// read from. New fields come last, a field renamed to an extension stays where it was.
+pub(crate) fn to_string_in_order(scenario: &Value, format: ScenarioFormat, original: &str, original_format: ScenarioFormat) -> anyhow::Result<String> {
+    let order = match original_format {
+        ScenarioFormat::Json => serde_json::from_str(original).ok(),
+        ScenarioFormat::Yaml => serde_yaml::from_str(original).ok(),
+        ScenarioFormat::Toml => toml::from_str(original).ok(),
+    };
+    format.serialize_ordered(&InOrderOf { value: scenario, order: order.as_ref() })
+}
+
+/// `value` with the keys of its objects in the order of the ones in `order`
+struct InOrderOf<'a> {
+    value: &'a Value,
+    order: Option<&'a serde_yaml::Value>,
+}
+
+impl Serialize for InOrderOf<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.value {
+            Value::Object(fields) => {
+                let order = self.order.and_then(serde_yaml::Value::as_mapping);
+                let mut keys: Vec<&String> = Vec::with_capacity(fields.len());
+                for key in order.into_iter().flat_map(|order| order.keys()).filter_map(serde_yaml::Value::as_str) {
+                    let extension = format!("{}{}", EXTENSION_PREFIX, key);
+                    let found = fields.get_key_value(key).or_else(|| fields.get_key_value(&extension));
+                    if let Some((key, _)) = found.filter(|(key, _)| !keys.contains(key)) {
+                        keys.push(key);
+                    }
+                }
+                let rest: Vec<&String> = fields.keys().filter(|key| !keys.contains(key)).collect();
+                keys.extend(rest);
+
+                let mut map = serializer.serialize_map(Some(keys.len()))?;
+                for key in keys {
+                    let order = order.and_then(|order| {
+                        order.get(key.as_str()).or_else(|| key.strip_prefix(EXTENSION_PREFIX).and_then(|name| order.get(name)))
+                    });
+                    map.serialize_entry(key, &InOrderOf { value: &fields[key], order })?;
+                }
+                map.end()
+            }
+            Value::Array(items) => {
+                let order = self.order.and_then(serde_yaml::Value::as_sequence);
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for (i, item) in items.iter().enumerate() {
+                    seq.serialize_element(&InOrderOf { value: item, order: order.and_then(|order| order.get(i)) })?;
+                }
+                seq.end()
+            }
+            value => value.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::migration::{current_schema, migrate, unknown_fields, upgrade, CURRENT_VERSION, MIGRATIONS};
+    use crate::Scenario;
+    use serde_json::json;
+
+    #[test]
+    fn it_registers_one_step_per_version() {
+        let froms: Vec<u16> = MIGRATIONS.iter().map(|m| m.from).collect();
+        assert_eq!((1..CURRENT_VERSION).collect::<Vec<_>>(), froms);
+    }
+
+    #[test]
+    fn it_migrates_v1_to_v2() {
+        let mut scenario = json!({
+            "version": 1,
+            "name": "checkout",
+            "include": ["common.yaml"],
+            "target": {"base_url": "http://localhost:8080", "default_headers": {"Accept": "*/*"}, "proxy": "none"},
+            "journeys": [{"name": "default", "weight": 1, "steps": [
+                {"type": "sleep", "duration_ms": 10},
+                {"type": "request", "method": "GET", "path": "/", "retries": 3, "x-note": "kept"},
+                {"$ref": "steps.yaml#/login"}
+            ]}],
+            "profiles": {"staging": {"default_headers": {"X-Env": "staging"}, "region": "eu"}},
+            "metadata": null,
+            "x-owner": "payments"
+        });
+
+        let changes: Vec<String> = migrate(&mut scenario, 2).unwrap().iter().map(ToString::to_string).collect();
+
+        insta::assert_debug_snapshot!(changes);
+        assert_eq!(json!({"default_headers": {"X-Env": "staging"}, "x-region": "eu"}), scenario["profiles"]["staging"]);
+        assert!(unknown_fields(&scenario).is_empty());
+    }
+
+    #[test]
+    fn it_reports_metadata_that_is_not_null() {
+        let mut scenario = json!({"version": 1, "name": "checkout", "metadata": {"team": "payments"}});
+        let changes: Vec<String> = migrate(&mut scenario, 2).unwrap().iter().map(ToString::to_string).collect();
+        assert_eq!(vec!["v2 /version: 1 -> 2", "v2 /metadata: unknown field, renamed to /x-metadata"], changes);
+        assert_eq!(json!({"team": "payments"}), scenario["x-metadata"]);
+
+        let mut scenario = json!({"version": 1, "name": "checkout", "metadata": {"team": "payments"}, "x-metadata": "kept"});
+        let changes: Vec<String> = migrate(&mut scenario, 2).unwrap().iter().map(ToString::to_string).collect();
+        assert_eq!("v2 /metadata: unknown field, not renamed: its `x-` extension is already there", changes[1]);
+        assert_eq!(vec!["/metadata"], unknown_fields(&scenario));
+    }
+
+    #[test]
+    fn it_rejects_unknown_fields_in_the_current_schema() {
+        let validator = jsonschema::validator_for(&current_schema(true)).unwrap();
+        let mut scenario = serde_json::to_value(Scenario::default()).unwrap();
+        scenario["x-owner"] = json!("payments");
+        assert!(validator.is_valid(&scenario));
+
+        scenario["target"]["proxy"] = json!("none");
+        let paths: Vec<String> = validator.iter_errors(&scenario).map(|e| e.instance_path().to_string()).collect();
+        assert_eq!(vec!["/target"], paths);
+        assert!(jsonschema::validator_for(&current_schema(false)).unwrap().is_valid(&scenario));
+    }
+
+    #[test]
+    fn it_rejects_unknown_and_downward_versions() {
+        assert_eq!("cannot migrate scenario: unknown version 3, supported: 1 to 2",
+                   migrate(&mut json!({"version": 3}), 2).unwrap_err().to_string());
+        assert_eq!("cannot migrate scenario: version 2 cannot be migrated down to 1",
+                   migrate(&mut json!({"version": 2}), 1).unwrap_err().to_string());
+        assert_eq!("cannot migrate scenario: it has no version",
+                   migrate(&mut json!({}), 2).unwrap_err().to_string());
+        assert!(migrate(&mut json!({"version": 2}), 2).unwrap().is_empty());
+
+        let mut unknown = json!({"version": "one", "metadata": null});
+        assert!(upgrade(&mut unknown).is_empty());
+        assert_eq!(json!({"version": "one", "metadata": null}), unknown);
+    }
+}
@@ -0,0 +1,302 @@
+//! Profiles and `key=value` overrides, applied to the composed scenario before validation so
+//! `validate` checks what `run` executes. The selected profile goes first, then `RLOAD_*`
+//! environment variables, then `--set` pairs: later values win.
+
+use crate::{Scenario, ValidationError};
+use schemars::schema_for;
+use serde_json::{Map, Value};
+use std::str::FromStr;
+
+const ENV_PREFIX: &str = "RLOAD_";
+
+/// Maps of strings: new keys under them keep their value as a string
+const STRING_MAPS: [&str; 3] = ["variables", "default_headers", "headers"];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overrides {
+    /// One of the scenario `profiles`
+    pub profile: Option<String>,
+    /// Applied in order after the profile
+    pub values: Vec<Override>,
+}
+
+/// `key` is a dotted path into the scenario, e.g. `target.base_url` or `workload.stages.0.rps`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Override {
+    pub key: String,
+    pub value: String,
+    /// Where the user wrote it, for error messages, e.g. `--set target.base_url`
+    pub origin: String,
+}
+
+impl FromStr for Override {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s.split_once('=')
+            .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", s))?;
+        Ok(Override { key: key.to_string(), value: value.to_string(), origin: format!("--set {}", key) })
+    }
+}
+
+impl Overrides {
+    /// `RLOAD_PROFILE` selects the profile. Other `RLOAD_<KEY>` variables are overrides with `__`
+    /// between segments, e.g. `RLOAD_TARGET__BASE_URL`. Scenario segments are lowercased, the keys
+    /// of string maps are kept as written, e.g. `RLOAD_VARIABLES__tenant`. Variables that do not
+    /// start with a scenario key are ignored.
+    pub fn from_env(vars: impl IntoIterator<Item = (String, String)>) -> Overrides {
+        let keys = scenario_keys();
+        let mut vars: Vec<(String, String)> = vars.into_iter()
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+            .collect();
+        vars.sort();
+
+        let mut overrides = Overrides::default();
+        for (name, value) in vars {
+            let key = env_key(&name[ENV_PREFIX.len()..]);
+            if key == "profile" {
+                overrides.profile = Some(value);
+            } else if keys.iter().any(|k| Some(k.as_str()) == key.split('.').next()) {
+                overrides.values.push(Override { key, value, origin: name });
+            }
+        }
+        overrides
+    }
+
+    /// Errors are located at the JSON-pointer path of what could not be overridden
+    pub(crate) fn apply(&self, scenario: &mut Value) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        if let Some(name) = &self.profile {
+            match scenario.get("profiles").and_then(|profiles| profiles.get(name)).cloned() {
+                Some(profile) => apply_profile(scenario, profile),
+                None => errors.push(error("/profiles", unknown_profile(scenario, name))),
+            }
+        }
+        let keys = scenario_keys();
+        for o in &self.values {
+            let path: String = o.key.split('.').map(|t| format!("/{}", t)).collect();
+            let result = match o.key.split('.').next() {
+                Some(first) if keys.iter().any(|k| k == first) => set(scenario, &o.key, &o.value),
+                first => Err(format!("unknown scenario key '{}'", first.unwrap_or_default())),
+            };
+            if let Err(message) = result {
+                errors.push(error(&path, format!("{}: {}", o.origin, message)));
+            }
+        }
+        errors
+    }
+}
+
+/// Segments up to a string map are lowercased, the ones after it are map keys
+fn env_key(name: &str) -> String {
+    let mut in_map = false;
+    name.split("__")
+        .map(|segment| {
+            if in_map {
+                return segment.to_string();
+            }
+            let segment = segment.to_lowercase();
+            in_map = STRING_MAPS.contains(&segment.as_str());
+            segment
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn error(path: &str, message: String) -> ValidationError {
+    ValidationError { path: path.to_string(), code: "invalid_override".to_string(), message }
+}
+
+fn unknown_profile(scenario: &Value, name: &str) -> String {
+    let names: Vec<&str> = scenario.get("profiles")
+        .and_then(Value::as_object)
+        .map(|profiles| profiles.keys().map(String::as_str).collect())
+        .unwrap_or_default();
+    match names.as_slice() {
+        [] => format!("unknown profile '{}', the scenario has no profiles", name),
+        names => format!("unknown profile '{}', expected one of: {}", name, names.join(", ")),
+    }
+}
+
+/// Values of the wrong type are copied as they are, the schema reports them
+fn apply_profile(scenario: &mut Value, profile: Value) {
+    let Value::Object(profile) = profile else {
+        return;
+    };
+    for (key, field) in profile.into_iter().filter(|(_, field)| !field.is_null()) {
+        let (parent, key) = match key.as_str() {
+            "base_url" | "default_headers" => ("target", key),
+            "stages" => ("workload", key),
+            "variables" => ("", key),
+            _ => continue,
+        };
+        let into = match parent {
+            "" => scenario.as_object_mut(),
+            parent => object(scenario, parent),
+        };
+        let Some(into) = into else {
+            continue;
+        };
+        match (into.get_mut(&key), field) {
+            (Some(Value::Object(own)), Value::Object(fields)) => own.extend(fields),
+            (_, field) => {
+                into.insert(key, field);
+            }
+        }
+    }
+}
+
+/// The object under `key`, created when missing or null
+fn object<'a>(value: &'a mut Value, key: &str) -> Option<&'a mut Map<String, Value>> {
+    let child = value.as_object_mut()?.entry(key).or_insert(Value::Null);
+    if child.is_null() {
+        *child = Value::Object(Map::new());
+    }
+    child.as_object_mut()
+}
+
+/// Missing or null objects on the way are created, array items must exist
+fn set(scenario: &mut Value, key: &str, raw: &str) -> Result<(), String> {
+    let tokens: Vec<&str> = key.split('.').collect();
+    if tokens.iter().any(|t| t.is_empty()) {
+        return Err(format!("'{}' has an empty segment", key));
+    }
+    let (last, parents) = tokens.split_last().expect("split yields at least one token");
+    let mut node = scenario;
+    for (i, token) in parents.iter().enumerate() {
+        node = match node {
+            Value::Object(fields) => {
+                let child = fields.entry(token.to_string()).or_insert(Value::Null);
+                if child.is_null() {
+                    *child = Value::Object(Map::new());
+                }
+                child
+            }
+            Value::Array(items) => item(items, token)?,
+            _ => return Err(format!("{} is not an object or an array", tokens[..i].join("."))),
+        };
+    }
+    let string_map = parents.last().is_some_and(|parent| STRING_MAPS.contains(parent));
+    match node {
+        Value::Object(fields) => {
+            let value = typed(raw, fields.get(*last), string_map);
+            fields.insert(last.to_string(), value);
+        }
+        Value::Array(items) => {
+            let item = item(items, last)?;
+            *item = typed(raw, Some(item), string_map);
+        }
+        _ => return Err(format!("{} is not an object or an array", parents.join("."))),
+    }
+    Ok(())
+}
+
+fn item<'a>(items: &'a mut [Value], token: &str) -> Result<&'a mut Value, String> {
+    let len = items.len();
+    token.parse::<usize>().ok()
+        .and_then(|i| items.get_mut(i))
+        .ok_or_else(|| format!("'{}' is not an index of an array of {} items", token, len))
+}
+
+/// Strings stay strings, as do new keys of string maps. Other values are read as JSON when they
+/// parse, e.g. numbers and booleans.
+fn typed(raw: &str, replaced: Option<&Value>, string_map: bool) -> Value {
+    match replaced {
+        Some(Value::String(_)) => Value::String(raw.to_string()),
+        None | Some(Value::Null) if string_map => Value::String(raw.to_string()),
+        _ => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string())),
+    }
+}
+
+fn scenario_keys() -> Vec<String> {
+    let schema = serde_json::to_value(schema_for!(Scenario)).unwrap_or_default();
+    schema.get("properties")
+        .and_then(Value::as_object)
+        .map(|properties| properties.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::overrides::{Override, Overrides};
+    use serde_json::json;
+
+    fn scenario() -> serde_json::Value {
+        json!({
+            "target": {"base_url": "http://localhost:8080", "default_headers": {"Accept": "*/*"}},
+            "workload": {"stages": [{"duration_sec": 10, "rps": 100}]},
+            "variables": {"tenant": "acme"},
+            "profiles": {
+                "staging": {
+                    "base_url": "https://staging.example.com",
+                    "default_headers": {"X-Env": "staging"},
+                    "stages": [{"duration_sec": 5, "rps": 10}],
+                    "variables": null
+                }
+            }
+        })
+    }
+
+    fn set(pairs: &[&str]) -> Vec<Override> {
+        pairs.iter().map(|pair| pair.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn it_applies_the_profile_then_the_values() {
+        let mut scenario = scenario();
+        let overrides = Overrides {
+            profile: Some("staging".to_string()),
+            values: set(&["workload.stages.0.rps=20", "variables.port=8443", "target.insecure_tls=true"]),
+        };
+
+        assert!(overrides.apply(&mut scenario).is_empty());
+        assert_eq!("https://staging.example.com", scenario["target"]["base_url"]);
+        assert_eq!(json!({"Accept": "*/*", "X-Env": "staging"}), scenario["target"]["default_headers"]);
+        assert_eq!(json!([{"duration_sec": 5, "rps": 20}]), scenario["workload"]["stages"]);
+        assert_eq!(json!({"tenant": "acme", "port": "8443"}), scenario["variables"]);
+        assert_eq!(json!(true), scenario["target"]["insecure_tls"]);
+    }
+
+    #[test]
+    fn it_reads_overrides_from_env() {
+        let vars = [
+            ("RLOAD_VARIABLES__tenant", "globex"),
+            ("RLOAD_TARGET__DEFAULT_HEADERS__X-Env", "env"),
+            ("RLOAD_PROFILE", "staging"),
+            ("RLOAD_LOG", "debug"),
+            ("HOME", "/root"),
+        ].map(|(name, value)| (name.to_string(), value.to_string()));
+
+        let overrides = Overrides::from_env(vars);
+
+        assert_eq!(Some("staging".to_string()), overrides.profile);
+        assert_eq!(vec![
+            Override {
+                key: "target.default_headers.X-Env".to_string(),
+                value: "env".to_string(),
+                origin: "RLOAD_TARGET__DEFAULT_HEADERS__X-Env".to_string(),
+            },
+            Override {
+                key: "variables.tenant".to_string(),
+                value: "globex".to_string(),
+                origin: "RLOAD_VARIABLES__tenant".to_string(),
+            },
+        ], overrides.values);
+    }
+
+    #[test]
+    fn it_reports_what_cannot_be_overridden() {
+        let mut scenario = scenario();
+        let overrides = Overrides {
+            profile: Some("prod".to_string()),
+            values: set(&["workload.stages.3.rps=1", "target.base_url.host=x", "nope=1", "name..x=1"]),
+        };
+
+        let messages: Vec<String> = overrides.apply(&mut scenario).iter()
+            .map(|e| format!("{} {}", e.path, e.message))
+            .collect();
+
+        insta::assert_debug_snapshot!(messages);
+        assert!("missing-equals".parse::<Override>().is_err());
+    }
+}
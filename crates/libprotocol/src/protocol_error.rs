@@ -1,3 +1,4 @@
+use crate::scenario_format::ScenarioFormat;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -8,6 +9,9 @@ pub enum ProtocolError {
     #[error("Failed to parse scenario JSON: {0}")]
     Json(#[from] JsonError),
 
+    #[error("Failed to parse scenario: {0}")]
+    Syntax(#[from] SyntaxError),
+
     #[error(transparent)]
     Validation(#[from] ValidationErrors),
 }
@@ -20,6 +24,16 @@ pub struct JsonError {
     pub message: String,
 }
 
+/// A YAML or TOML scenario that does not parse, JSON ones report a `JsonError`
+#[derive(Debug, Error)]
+#[error("invalid {format} at line {line}, column {column}: {message}")]
+pub struct SyntaxError {
+    pub format: ScenarioFormat,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
 #[derive(Debug, Error)]
 #[error("validation error at [{path}] {message} ({code})")]
 pub struct ValidationError {
@@ -0,0 +1,115 @@
+//! Scenario files may be JSON, YAML or TOML. Every format is read into a JSON value first,
+//! so the schema, the semantic rules and their JSON-pointer paths are the same for all of them.
+
+use crate::protocol_error::{JsonError, ProtocolError, SyntaxError};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScenarioFormat {
+    #[default]
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl ScenarioFormat {
+    /// `.yaml`/`.yml` and `.toml`, anything else is read as JSON
+    pub fn from_path(path: impl AsRef<Path>) -> ScenarioFormat {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("yaml") | Some("yml") => ScenarioFormat::Yaml,
+            Some("toml") => ScenarioFormat::Toml,
+            _ => ScenarioFormat::Json,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ScenarioFormat::Json => "json",
+            ScenarioFormat::Yaml => "yaml",
+            ScenarioFormat::Toml => "toml",
+        }
+    }
+
+    /// Syntax errors carry the 1-based line and column of the problem
+    pub fn parse(&self, content: &str) -> Result<Value, ProtocolError> {
+        match self {
+            ScenarioFormat::Json => serde_json::from_str(content).map_err(|e| JsonError {
+                line: e.line(),
+                column: e.column(),
+                message: e.to_string(),
+            }.into()),
+            ScenarioFormat::Yaml => serde_yaml::from_str(content).map_err(|e| {
+                let (line, column) = e.location().map(|l| (l.line(), l.column())).unwrap_or((0, 0));
+                SyntaxError { format: *self, line, column, message: e.to_string() }.into()
+            }),
+            ScenarioFormat::Toml => toml::from_str(content).map_err(|e| {
+                let (line, column) = e.span().map(|span| line_column(content, span.start)).unwrap_or((0, 0));
+                SyntaxError { format: *self, line, column, message: e.message().to_string() }.into()
+            }),
+        }
+    }
+
+    /// A `Scenario`, or a scenario read as a JSON value
+    pub fn to_string_pretty(&self, scenario: &impl Serialize) -> anyhow::Result<String> {
+        match self {
+            // TOML has no null: unset options are left out
+            ScenarioFormat::Toml => self.serialize_ordered(&without_nulls(serde_json::to_value(scenario)?)),
+            _ => self.serialize_ordered(scenario),
+        }
+    }
+
+    /// `to_string_pretty` keeping the fields in the order `scenario` serializes them. Nulls are
+    /// written as they are, TOML fails on them.
+    pub(crate) fn serialize_ordered(&self, scenario: &impl Serialize) -> anyhow::Result<String> {
+        Ok(match self {
+            ScenarioFormat::Json => serde_json::to_string_pretty(scenario)?,
+            ScenarioFormat::Yaml => serde_yaml::to_string(scenario)?,
+            ScenarioFormat::Toml => toml::to_string_pretty(scenario)?,
+        })
+    }
+}
+
+impl FromStr for ScenarioFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ScenarioFormat::Json),
+            "yaml" | "yml" => Ok(ScenarioFormat::Yaml),
+            "toml" => Ok(ScenarioFormat::Toml),
+            other => Err(format!("unknown scenario format '{}', expected json, yaml or toml", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for ScenarioFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScenarioFormat::Json => write!(f, "json"),
+            ScenarioFormat::Yaml => write!(f, "yaml"),
+            ScenarioFormat::Toml => write!(f, "toml"),
+        }
+    }
+}
+
+pub(crate) fn without_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(object.into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| (key, without_nulls(value)))
+            .collect()),
+        Value::Array(items) => Value::Array(items.into_iter().map(without_nulls).collect()),
+        value => value,
+    }
+}
+
+/// 1-based line and column of the byte `offset`
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = content.get(..offset).unwrap_or(content);
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    (line, column)
+}
@@ -1,4 +1,5 @@
 use crate::schema::Step::Request;
+use crate::migration::CURRENT_VERSION;
 use crate::ValidationError;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -7,28 +8,42 @@ use std::string::ToString;
 
 #[derive(Serialize, Deserialize, JsonSchema, Debug)]
 pub struct Scenario {
+    /// Format version, see `migration`
     pub  version: u16,
     pub  name: String,
     pub  target: Target,
     pub  workload: Workload,
     pub  journeys: Vec<Journey>,
+    /// Values for `{{name}}` placeholders in request paths, headers and bodies
+    pub  variables: Option<BTreeMap<String, String>>,
+    /// Row files for `{{data.<source>.<column>}}` placeholders, one row per source and VU iteration
+    pub  data_sources: Option<Vec<DataSource>>,
+    /// Named environments selected with `--profile`, each overriding parts of the scenario
+    pub  profiles: Option<BTreeMap<String, Profile>>,
     pub  description: Option<String>,
     pub  tags: Option<Vec<String>>,
     pub  thresholds: Option<Vec<Threshold>>,
-    pub  metadata: Option<()>
+    /// What goes into the run report
+    pub  report: Option<ReportOptions>,
+    /// W3C trace context propagation and request spans
+    pub  tracing: Option<TracingOptions>,
 }
 impl Default for Scenario {
     fn default() -> Self {
         Self {
-            version: 1,
+            version: CURRENT_VERSION,
             name: "default_scenario".to_string(),
             target: Target::default(),
             workload: Workload::default(),
             journeys: Vec::from([Journey::default()]),
+            variables: None,
+            data_sources: None,
+            profiles: None,
             description: None,
             tags: None,
             thresholds: Option::from(vec![Threshold::default()]),
-            metadata: None,
+            report: None,
+            tracing: None,
         }
     }
 }
@@ -39,12 +54,151 @@ impl Scenario {
     }
 }
 
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default)]
+pub struct Profile {
+    /// Replaces `target.base_url`
+    pub  base_url: Option<String>,
+    /// Merged over `target.default_headers`
+    pub  default_headers: Option<BTreeMap<String, String>>,
+    /// Replaces `workload.stages`
+    pub  stages: Option<Vec<Stage>>,
+    /// Merged over `variables`
+    pub  variables: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct DataSource {
+    /// Referenced as `{{data.<name>.<column>}}`
+    pub  name: String,
+    /// CSV file with a header row, or JSON lines with one object per line. Relative to the scenario file
+    pub  path: String,
+    /// Defaults to the file extension: `.csv`, `.jsonl` or `.ndjson`
+    pub  format: Option<DataFormat>,
+    /// How VUs pick rows. Defaults to `sequential`
+    pub  distribution: Option<Distribution>,
+    /// Seed of the `random` distribution. Without it the rows differ on every run
+    pub  seed: Option<u64>,
+    /// What `unique_per_vu` and `shared` do once every row is taken. Defaults to `recycle`
+    pub  on_exhausted: Option<OnExhausted>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DataFormat {
+    Csv,
+    Jsonl,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Distribution {
+    /// Every VU walks the rows from the top, one row per iteration
+    #[default]
+    Sequential,
+    /// A row picked at random per iteration, reproducible with `seed`
+    Random,
+    /// Each VU keeps its own row for the whole run
+    UniquePerVu,
+    /// One cursor for all VUs, every iteration takes the next row
+    Shared,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OnExhausted {
+    /// Start over from the first row
+    #[default]
+    Recycle,
+    /// A VU left without a row stops iterating
+    StopVu,
+}
+
+impl DataSource {
+    pub fn format(&self) -> Option<DataFormat> {
+        if self.format.is_some() {
+            return self.format;
+        }
+        match std::path::Path::new(&self.path).extension().and_then(|e| e.to_str()) {
+            Some("csv") => Some(DataFormat::Csv),
+            Some("jsonl") | Some("ndjson") => Some(DataFormat::Jsonl),
+            _ => None,
+        }
+    }
+
+    pub fn distribution(&self) -> Distribution {
+        self.distribution.unwrap_or_default()
+    }
+
+    pub fn on_exhausted(&self) -> OnExhausted {
+        self.on_exhausted.unwrap_or_default()
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default)]
+pub struct ReportOptions {
+    /// Width of the `timeseries` buckets. Defaults to 1000
+    pub  timeseries_interval_ms: Option<u64>,
+}
+
+impl ReportOptions {
+    pub const DEFAULT_TIMESERIES_INTERVAL_MS: u64 = 1000;
+
+    pub fn timeseries_interval_ms(&self) -> u64 {
+        self.timeseries_interval_ms.unwrap_or(Self::DEFAULT_TIMESERIES_INTERVAL_MS)
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default)]
+pub struct TracingOptions {
+    /// Send a `traceparent` header with every request. Defaults to true when spans are exported
+    /// with `--out otlp=`, false otherwise
+    pub  traceparent: Option<bool>,
+    /// Share of requests marked sampled and exported as spans, 0.0 to 1.0. Defaults to 1.0
+    pub  sample_ratio: Option<f64>,
+}
+
+impl TracingOptions {
+    pub const DEFAULT_SAMPLE_RATIO: f64 = 1.0;
+
+    pub fn sample_ratio(&self) -> f64 {
+        self.sample_ratio.unwrap_or(Self::DEFAULT_SAMPLE_RATIO)
+    }
+}
+
 #[derive(Serialize, Deserialize, JsonSchema, Debug)]
 pub struct Target {
     pub  base_url: String,
+    /// Sent with every request, a step's own headers win
     pub  default_headers: Option<BTreeMap<String, String>>,
-    pub  insecure_tls: Option<bool>
+    /// Accept any server certificate. For test environments only
+    pub  insecure_tls: Option<bool>,
+    /// PEM file with extra CA certificates to trust, relative to the scenario file
+    pub  ca_bundle: Option<String>,
+    /// PEM client certificate for mTLS, requires `client_key`
+    pub  client_cert: Option<String>,
+    /// PEM private key of `client_cert`
+    pub  client_key: Option<String>,
+    pub  min_tls_version: Option<TlsVersion>,
+    pub  max_tls_version: Option<TlsVersion>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    #[serde(rename = "1.2")]
+    Tls12,
+    #[serde(rename = "1.3")]
+    Tls13,
 }
+
+impl std::fmt::Display for TlsVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TlsVersion::Tls12 => write!(f, "1.2"),
+            TlsVersion::Tls13 => write!(f, "1.3"),
+        }
+    }
+}
+
 impl Default for Target {
     fn default() -> Self {
         let mut headers: BTreeMap<String, String> = BTreeMap::new();
@@ -53,22 +207,46 @@ impl Default for Target {
             base_url: "http://localhost:8080".parse().unwrap(),
             default_headers: Some(headers),
             insecure_tls: None,
+            ca_bundle: None,
+            client_cert: None,
+            client_key: None,
+            min_tls_version: None,
+            max_tls_version: None,
         }
     }
 }
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct Workload {
     pub  stages: Vec<Stage>,
+    /// Load model. Defaults to `open` (arrival rate driven by `stages`)
+    pub  executor: Option<WorkloadExecutor>,
 }
 impl Default for Workload {
     fn default() -> Self {
         Self {
             stages: vec![Stage::default()],
+            executor: None,
         }
     }
 }
 
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WorkloadExecutor {
+    /// Open model: requests are started at the rate planned by `stages`
+    Open,
+    /// Closed model: `vus` virtual users loop their journeys back-to-back for `duration_sec`
+    ConstantVus {
+        vus: u32,
+        duration_sec: u32,
+    },
+}
+
 impl Workload {
+    pub fn is_closed_model(&self) -> bool {
+        matches!(self.executor, Some(WorkloadExecutor::ConstantVus { .. }))
+    }
+
     pub fn get_rps_avg(&self) -> f64 {
         if self.stages.is_empty() {
             return 0.0;
@@ -76,7 +254,7 @@ impl Workload {
 
         self.stages
             .iter()
-            .map(|stage| stage.rps.max(0) as f64)
+            .map(|stage| stage.avg_rps().max(0.0))
             .sum::<f64>()
             / (self.stages.len() as f64)
     }
@@ -85,17 +263,55 @@ impl Workload {
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct Stage {
     pub  duration_sec: i32,
+    /// Rate at the start of the stage
     pub  rps: i32,
+    /// Rate at the end of the stage. When set, the rate ramps linearly from `rps` to `target_rps`
+    pub  target_rps: Option<i32>,
+    /// How ticks are spread inside the stage. Defaults to `constant`
+    pub  arrival: Option<Arrival>,
 }
 impl Default for Stage {
     fn default() -> Self {
         Self {
             duration_sec: 10,
             rps: 100,
+            target_rps: None,
+            arrival: None,
         }
     }
 }
 
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Arrival {
+    /// Ticks are evenly spaced
+    #[default]
+    Constant,
+    /// Exponential inter-arrival times, as in a Poisson process with the stage rate
+    Poisson,
+    /// Each tick is shifted randomly inside its own slot
+    UniformJitter,
+}
+
+impl Stage {
+    pub fn arrival(&self) -> Arrival {
+        self.arrival.unwrap_or_default()
+    }
+
+    pub fn end_rps(&self) -> i32 {
+        self.target_rps.unwrap_or(self.rps)
+    }
+
+    pub fn avg_rps(&self) -> f64 {
+        (self.rps + self.end_rps()) as f64 / 2.0
+    }
+
+    /// Number of ticks planned for the stage (area under the rate line)
+    pub fn planned_ticks(&self) -> i32 {
+        self.duration_sec * (self.rps + self.end_rps()) / 2
+    }
+}
+
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct Journey {
     pub  name: String,
@@ -151,9 +367,58 @@ pub enum Step {
         headers: Option<HashMap<String, String>>,
         body: Option<String>,
         timeout_ms: Option<u32>,
+        /// Values taken from the response, available as `{{name}}` in later steps of the journey
+        extract: Option<Vec<Extract>>,
+        /// Assertions on the response. A failed check makes the request an error
+        checks: Option<Vec<Check>>,
     },
 }
 
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Check {
+    /// Status must be one of `values`. Replaces the default 2xx rule
+    Status { values: Vec<u16> },
+    BodyContains { value: String },
+    BodyRegex { pattern: String },
+    /// Value at the JSONPath `path` of the body must equal `value`
+    JsonEquals { path: String, value: serde_json::Value },
+    MaxBodySize { bytes: u64 },
+    HeaderPresent { header: String },
+}
+
+impl std::fmt::Display for Check {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Check::Status { values } => write!(f, "status in {:?}", values),
+            Check::BodyContains { value } => write!(f, "body contains {:?}", value),
+            Check::BodyRegex { pattern } => write!(f, "body matches /{}/", pattern),
+            Check::JsonEquals { path, value } => write!(f, "{} == {}", path, value),
+            Check::MaxBodySize { bytes } => write!(f, "body size <= {}", bytes),
+            Check::HeaderPresent { header } => write!(f, "header {} present", header),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(tag = "from", rename_all = "snake_case")]
+pub enum Extract {
+    /// JSONPath on the response body, e.g. `$.data.token`
+    Json { name: String, path: String },
+    /// Response header value
+    Header { name: String, header: String },
+    /// First capture group of `pattern` on the response body, the whole match when there is no group
+    Regex { name: String, pattern: String },
+}
+
+impl Extract {
+    pub fn name(&self) -> &str {
+        match self {
+            Extract::Json { name, .. } | Extract::Header { name, .. } | Extract::Regex { name, .. } => name,
+        }
+    }
+}
+
 impl Default for Step {
     fn default() -> Self {
         Step::Sleep { duration_ms: 10 }
@@ -168,10 +433,12 @@ impl Step {
             headers: None,
             body: None,
             timeout_ms: None,
+            extract: None,
+            checks: None,
         }
     }
 }
-#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub  enum ThresholdOperator {
     Lt,
@@ -181,7 +448,50 @@ pub  enum ThresholdOperator {
     Eq
 }
 
-#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+/// Metrics a threshold can be evaluated against.
+/// `latency.*` values are in milliseconds, `http.error_rate` is a percentage.
+pub const THRESHOLD_METRICS: &[&str] = &[
+    "http.error_rate",
+    "requests.total",
+    "requests.ok",
+    "requests.error",
+    "latency.min",
+    "latency.max",
+    "latency.mean",
+    "latency.p50",
+    "latency.p90",
+    "latency.p95",
+    "latency.p99",
+    "rps.planned_avg",
+    "rps.achieved_avg",
+];
+
+impl ThresholdOperator {
+    pub fn compare(&self, observed: f64, expected: f64) -> bool {
+        match self {
+            ThresholdOperator::Lt => observed < expected,
+            ThresholdOperator::Gt => observed > expected,
+            ThresholdOperator::Lte => observed <= expected,
+            ThresholdOperator::Gte => observed >= expected,
+            ThresholdOperator::Eq => (observed - expected).abs() < f64::EPSILON,
+        }
+    }
+}
+
+impl std::fmt::Display for ThresholdOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = match self {
+            ThresholdOperator::Lt => "<",
+            ThresholdOperator::Gt => ">",
+            ThresholdOperator::Lte => "<=",
+            ThresholdOperator::Gte => ">=",
+            ThresholdOperator::Eq => "==",
+        };
+        write!(f, "{}", sign)
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct Threshold {
     pub  metric: String,
     pub  op: ThresholdOperator,
@@ -193,7 +503,7 @@ impl Default for Threshold {
     fn default() -> Self {
         Self {
             metric: "http.error_rate".to_string(),
-            op: ThresholdOperator::Gt,
+            op: ThresholdOperator::Lt,
             value: 10.0,
             scope: None,
         }
@@ -201,8 +511,9 @@ impl Default for Threshold {
 }
 
 
-#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+/// Narrows a threshold to a single endpoint (`"GET /path"`) or journey (by name).
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct ThresholdScope {
-    pub  endpoint: String,
-    pub  journey: String,
+    pub  endpoint: Option<String>,
+    pub  journey: Option<String>,
 }
\ No newline at end of file
@@ -1,10 +1,15 @@
 use crate::schema::Step::{Request, Sleep};
-use crate::schema::{Journey, Scenario, Step};
+use crate::json_path::JsonPath;
+use crate::data_source::DataTable;
+use crate::schema::{Check, DataSource, Distribution, Extract, Journey, Scenario, Step, WorkloadExecutor, THRESHOLD_METRICS};
 use crate::ValidationError;
+use crate::template::{has_placeholders, Placeholder, Template};
 use predicates::Predicate;
+use std::path::{Path, PathBuf};
 
 enum ScenarioVersion {
     V1 = 1,
+    V2 = 2,
 }
 impl TryFrom<u16> for ScenarioVersion {
     type Error = ValidationError;
@@ -12,6 +17,7 @@ impl TryFrom<u16> for ScenarioVersion {
     fn try_from(value: u16) -> Result<Self, Self::Error> {
         match value {
             1 => Ok(ScenarioVersion::V1),
+            2 => Ok(ScenarioVersion::V2),
             _ => Err(ValidationError{ path: "/version".to_string(), code: "unsupported_version".to_string(), message: "Unsupported version".to_string()}),
         }
     }
@@ -74,7 +80,7 @@ impl StagesRule {
 
 impl Rule for StagesRule {
     fn validate(&self, scenario: &Scenario, errors: &mut Vec<ValidationError>) {
-        if scenario.workload.stages.is_empty(){
+        if scenario.workload.stages.is_empty() && !scenario.workload.is_closed_model() {
             errors.push(ValidationError {
                 path: "/workload/stages".to_string(),
                 code: "".to_string(),
@@ -108,6 +114,37 @@ impl Rule for DurationRule {
     }
 }
 
+pub(crate) struct ConstantVusRule {
+}
+
+impl ConstantVusRule {
+    pub(crate) fn new() -> Self {
+        ConstantVusRule {}
+    }
+}
+
+impl Rule for ConstantVusRule {
+    fn validate(&self, scenario: &Scenario, errors: &mut Vec<ValidationError>) {
+        let Some(WorkloadExecutor::ConstantVus { vus, duration_sec }) = scenario.workload.executor else {
+            return;
+        };
+        if vus == 0 || vus > 10000 {
+            errors.push(ValidationError {
+                path: "/workload/executor/vus".to_string(),
+                code: "invalid_value".to_string(),
+                message: "vus must be between 1 and 10000".to_string(),
+            })
+        }
+        if !(10..=86400).contains(&duration_sec) {
+            errors.push(ValidationError {
+                path: "/workload/executor/duration_sec".to_string(),
+                code: "invalid_value".to_string(),
+                message: "Duration must be >= 10 and < 86400 sec ".to_string(),
+            })
+        }
+    }
+}
+
 pub(crate) struct VersionRule {
 }
 
@@ -123,7 +160,7 @@ impl Rule for VersionRule {
             errors.push(ValidationError {
                 path: "/version".to_string(),
                 code: "unsupported_version".to_string(),
-                message: format!("Unsupported version: {}. Supported: [1]", scenario.version),
+                message: format!("Unsupported version: {}. Supported: [1, 2]", scenario.version),
             });
         }
     }
@@ -150,6 +187,14 @@ impl Rule for RpsRule {
                     message: self.message.clone(),
                 })
             }
+            if let Some(target_rps) = stage.target_rps
+                && !(0..=10000).contains(&target_rps) {
+                errors.push(ValidationError {
+                    path: std::format!("/workload/stages/{}/target_rps", i),
+                    code: "invalid_value".to_string(),
+                    message: "target_rps must be >= 0 and <= 10000".to_string(),
+                })
+            }
         }
     }
 }
@@ -222,7 +267,8 @@ impl JourneyStepRule {
                 body: _body,
                 headers: _headers,
                 method: _method,
-                timeout_ms
+                timeout_ms,
+                ..
             } => {
                 if let Some(timeout_ms) = timeout_ms && (timeout_ms == &0 || timeout_ms > &100000) {
                     errors.push(ValidationError {
@@ -257,6 +303,398 @@ impl JourneyStepRule {
         }
     }
 }
+pub(crate) struct ThresholdsRule {
+}
+
+impl ThresholdsRule {
+    pub(crate) fn new() -> Self {
+        ThresholdsRule {}
+    }
+}
+
+impl Rule for ThresholdsRule {
+    fn validate(&self, scenario: &Scenario, errors: &mut Vec<ValidationError>) {
+        let Some(thresholds) = &scenario.thresholds else {
+            return;
+        };
+        for (i, threshold) in thresholds.iter().enumerate() {
+            if !THRESHOLD_METRICS.contains(&threshold.metric.as_str()) {
+                errors.push(ValidationError {
+                    path: std::format!("/thresholds/{}/metric", i),
+                    code: "invalid_value".to_string(),
+                    message: format!("Unknown metric: {}. Supported: {:?}", threshold.metric, THRESHOLD_METRICS),
+                });
+            }
+            if let Some(scope) = &threshold.scope {
+                if scope.endpoint.is_some() && scope.journey.is_some() {
+                    errors.push(ValidationError {
+                        path: std::format!("/thresholds/{}/scope", i),
+                        code: "invalid_value".to_string(),
+                        message: "scope must have either endpoint or journey, not both".to_string(),
+                    });
+                }
+                if let Some(journey) = &scope.journey
+                    && !scenario.journeys.iter().any(|j| &j.name == journey) {
+                    errors.push(ValidationError {
+                        path: std::format!("/thresholds/{}/scope/journey", i),
+                        code: "invalid_value".to_string(),
+                        message: format!("Unknown journey: {}", journey),
+                    });
+                }
+            }
+        }
+    }
+}
+
+pub(crate) struct ReportRule {
+}
+
+impl ReportRule {
+    pub(crate) fn new() -> Self {
+        ReportRule {}
+    }
+}
+
+impl Rule for ReportRule {
+    fn validate(&self, scenario: &Scenario, errors: &mut Vec<ValidationError>) {
+        if let Some(interval_ms) = scenario.report.as_ref().and_then(|r| r.timeseries_interval_ms)
+            && interval_ms < 100 {
+            errors.push(ValidationError {
+                path: "/report/timeseries_interval_ms".to_string(),
+                code: "invalid_value".to_string(),
+                message: "timeseries_interval_ms must be >= 100".to_string(),
+            });
+        }
+    }
+}
+
+pub(crate) struct TracingRule {
+}
+
+impl TracingRule {
+    pub(crate) fn new() -> Self {
+        TracingRule {}
+    }
+}
+
+impl Rule for TracingRule {
+    fn validate(&self, scenario: &Scenario, errors: &mut Vec<ValidationError>) {
+        if let Some(ratio) = scenario.tracing.as_ref().and_then(|t| t.sample_ratio)
+            && !(0.0..=1.0).contains(&ratio) {
+            errors.push(ValidationError {
+                path: "/tracing/sample_ratio".to_string(),
+                code: "invalid_value".to_string(),
+                message: "sample_ratio must be between 0.0 and 1.0".to_string(),
+            });
+        }
+    }
+}
+
+pub(crate) struct ExtractRule {
+}
+
+impl ExtractRule {
+    pub(crate) fn new() -> Self {
+        ExtractRule {}
+    }
+}
+
+impl Rule for ExtractRule {
+    fn validate(&self, scenario: &Scenario, errors: &mut Vec<ValidationError>) {
+        for (i, journey) in scenario.journeys.iter().enumerate() {
+            for (j, step) in journey.steps.iter().enumerate() {
+                let Request { extract: Some(extract), .. } = step else {
+                    continue;
+                };
+                for (k, rule) in extract.iter().enumerate() {
+                    let path = std::format!("/journeys/{}/steps/{}/extract/{}", i, j, k);
+                    if !is_variable_name(rule.name()) {
+                        errors.push(ValidationError {
+                            path: format!("{}/name", path),
+                            code: "invalid_value".to_string(),
+                            message: "name must be an identifier and not a built-in placeholder (vu_id, iteration, uuid)".to_string(),
+                        });
+                    }
+                    let invalid = match rule {
+                        Extract::Json { path, .. } => JsonPath::parse(path).err().map(|e| ("path", e)),
+                        Extract::Regex { pattern, .. } => regex::Regex::new(pattern).err().map(|e| ("pattern", e.to_string())),
+                        Extract::Header { header, .. } if header.is_empty() => Some(("header", "header must be filled".to_string())),
+                        Extract::Header { .. } => None,
+                    };
+                    if let Some((field, message)) = invalid {
+                        errors.push(ValidationError {
+                            path: format!("{}/{}", path, field),
+                            code: "invalid_value".to_string(),
+                            message,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub(crate) struct ChecksRule {
+}
+
+impl ChecksRule {
+    pub(crate) fn new() -> Self {
+        ChecksRule {}
+    }
+}
+
+impl Rule for ChecksRule {
+    fn validate(&self, scenario: &Scenario, errors: &mut Vec<ValidationError>) {
+        for (i, journey) in scenario.journeys.iter().enumerate() {
+            for (j, step) in journey.steps.iter().enumerate() {
+                let Request { checks: Some(checks), .. } = step else {
+                    continue;
+                };
+                for (k, check) in checks.iter().enumerate() {
+                    let invalid = match check {
+                        Check::Status { values } if values.is_empty() => Some(("values", "values must not be empty".to_string())),
+                        Check::Status { values } => values.iter()
+                            .find(|status| !(100..=599).contains(*status))
+                            .map(|status| ("values", format!("status {} must be between 100 and 599", status))),
+                        Check::BodyContains { value } if value.is_empty() => Some(("value", "value must be filled".to_string())),
+                        Check::BodyRegex { pattern } => regex::Regex::new(pattern).err().map(|e| ("pattern", e.to_string())),
+                        Check::JsonEquals { path, .. } => JsonPath::parse(path).err().map(|e| ("path", e)),
+                        Check::MaxBodySize { bytes: 0 } => Some(("bytes", "bytes must be > 0".to_string())),
+                        Check::HeaderPresent { header } if header.is_empty() => Some(("header", "header must be filled".to_string())),
+                        _ => None,
+                    };
+                    if let Some((field, message)) = invalid {
+                        errors.push(ValidationError {
+                            path: std::format!("/journeys/{}/steps/{}/checks/{}/{}", i, j, k, field),
+                            code: "invalid_value".to_string(),
+                            message,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub(crate) struct TlsRule {
+}
+
+impl TlsRule {
+    pub(crate) fn new() -> Self {
+        TlsRule {}
+    }
+}
+
+impl Rule for TlsRule {
+    fn validate(&self, scenario: &Scenario, errors: &mut Vec<ValidationError>) {
+        let target = &scenario.target;
+        let mut push = |field: &str, message: String| errors.push(ValidationError {
+            path: std::format!("/target/{}", field),
+            code: "invalid_tls".to_string(),
+            message,
+        });
+
+        for (field, value) in [("ca_bundle", &target.ca_bundle), ("client_cert", &target.client_cert), ("client_key", &target.client_key)] {
+            if value.as_ref().is_some_and(|path| path.is_empty()) {
+                push(field, std::format!("{} must be a file path", field));
+            }
+        }
+        match (&target.client_cert, &target.client_key) {
+            (Some(_), None) => push("client_key", "client_key is required with client_cert".to_string()),
+            (None, Some(_)) => push("client_cert", "client_cert is required with client_key".to_string()),
+            _ => {}
+        }
+        if let (Some(min), Some(max)) = (target.min_tls_version, target.max_tls_version) && min > max {
+            push("min_tls_version", std::format!("min_tls_version {} must be <= max_tls_version {}", min, max));
+        }
+        let plain_http = scenario.target.base_url.starts_with("http://");
+        let tls_options = target.ca_bundle.is_some() || target.client_cert.is_some() || target.min_tls_version.is_some() || target.max_tls_version.is_some();
+        if plain_http && tls_options {
+            push("base_url", "TLS options require an https base_url".to_string());
+        }
+    }
+}
+
+fn is_variable_name(name: &str) -> bool {
+    matches!(Template::parse(&format!("{{{{{}}}}}", name)), Ok(t) if matches!(t.placeholders().next(), Some(Placeholder::Var(_))))
+}
+
+pub(crate) struct TemplatesRule {
+}
+
+impl TemplatesRule {
+    pub(crate) fn new() -> Self {
+        TemplatesRule {}
+    }
+
+    fn check(&self, input: &str, path: String, variables: &[&str], errors: &mut Vec<ValidationError>) {
+        if !has_placeholders(input) {
+            return;
+        }
+        let template = match Template::parse(input) {
+            Ok(template) => template,
+            Err(message) => {
+                errors.push(ValidationError { path, code: "invalid_template".to_string(), message });
+                return;
+            }
+        };
+        for placeholder in template.placeholders() {
+            if let Placeholder::Var(name) = placeholder
+                && !variables.contains(&name.as_str()) {
+                errors.push(ValidationError {
+                    path: path.clone(),
+                    code: "unknown_variable".to_string(),
+                    message: format!("Unknown variable: {}. Declare it in `variables` or extract it in an earlier step", name),
+                });
+            }
+        }
+    }
+}
+
+impl Rule for TemplatesRule {
+    fn validate(&self, scenario: &Scenario, errors: &mut Vec<ValidationError>) {
+        let declared: Vec<&str> = scenario.variables.iter().flat_map(|v| v.keys()).map(String::as_str).collect();
+
+        for (i, journey) in scenario.journeys.iter().enumerate() {
+            // values extracted by earlier steps of the same journey are known too
+            let mut variables = declared.clone();
+            for (j, step) in journey.steps.iter().enumerate() {
+                let Request { extract, .. } = step else {
+                    continue;
+                };
+                for (field, input) in request_templates(step) {
+                    self.check(input, format!("/journeys/{}/steps/{}/{}", i, j, field), &variables, errors);
+                }
+                variables.extend(extract.iter().flatten().map(Extract::name));
+            }
+        }
+    }
+}
+
+/// Templated fields of a request step as `(field path, value)`: path, headers by name, body
+fn request_templates(step: &Step) -> Vec<(String, &str)> {
+    let Request { path, headers, body, .. } = step else {
+        return Vec::new();
+    };
+    let mut templates = vec![("path".to_string(), path.as_str())];
+    if let Some(headers) = headers {
+        let mut names: Vec<&String> = headers.keys().collect();
+        names.sort();
+        templates.extend(names.into_iter().map(|name| (format!("headers/{}", name), headers[name].as_str())));
+    }
+    if let Some(body) = body {
+        templates.push(("body".to_string(), body.as_str()));
+    }
+    templates
+}
+
+/// Data files are read relative to `dir`, the directory of the scenario file
+pub(crate) struct DataSourcesRule {
+    dir: PathBuf,
+}
+
+impl DataSourcesRule {
+    pub(crate) fn new(dir: &Path) -> Self {
+        DataSourcesRule { dir: dir.to_path_buf() }
+    }
+
+    fn load(&self, source: &DataSource, path: &str, errors: &mut Vec<ValidationError>) -> Option<DataTable> {
+        let mut push = |field: &str, message: String| errors.push(ValidationError {
+            path: format!("{}/{}", path, field),
+            code: "invalid_data_source".to_string(),
+            message,
+        });
+        if source.path.is_empty() {
+            push("path", "path must be a file path".to_string());
+            return None;
+        }
+        let Some(format) = source.format() else {
+            push("format", format!("cannot tell the format of {} from its extension, set format", source.path));
+            return None;
+        };
+        match DataTable::load(&self.dir.join(&source.path), format) {
+            Ok(table) if table.rows.is_empty() => {
+                push("path", format!("{} has no rows", source.path));
+                None
+            }
+            Ok(table) => Some(table),
+            Err(message) => {
+                push("path", format!("{}: {}", source.path, message));
+                None
+            }
+        }
+    }
+}
+
+impl Rule for DataSourcesRule {
+    fn validate(&self, scenario: &Scenario, errors: &mut Vec<ValidationError>) {
+        let mut tables: Vec<(&str, Option<DataTable>)> = Vec::new();
+        for (i, source) in scenario.data_sources.iter().flatten().enumerate() {
+            let path = format!("/data_sources/{}", i);
+            if !is_variable_name(&source.name) {
+                errors.push(ValidationError {
+                    path: format!("{}/name", path),
+                    code: "invalid_value".to_string(),
+                    message: "name must be an identifier".to_string(),
+                });
+            } else if tables.iter().any(|(name, _)| *name == source.name) {
+                errors.push(ValidationError {
+                    path: format!("{}/name", path),
+                    code: "invalid_value".to_string(),
+                    message: format!("duplicate data source name: {}", source.name),
+                });
+            }
+            let exhaustible = matches!(source.distribution(), Distribution::UniquePerVu | Distribution::Shared);
+            if source.on_exhausted.is_some() && !exhaustible {
+                errors.push(ValidationError {
+                    path: format!("{}/on_exhausted", path),
+                    code: "invalid_value".to_string(),
+                    message: "on_exhausted applies to the unique_per_vu and shared distributions only".to_string(),
+                });
+            }
+            if source.seed.is_some() && source.distribution() != Distribution::Random {
+                errors.push(ValidationError {
+                    path: format!("{}/seed", path),
+                    code: "invalid_value".to_string(),
+                    message: "seed applies to the random distribution only".to_string(),
+                });
+            }
+            tables.push((&source.name, self.load(source, &path, errors)));
+        }
+
+        for (i, journey) in scenario.journeys.iter().enumerate() {
+            for (j, step) in journey.steps.iter().enumerate() {
+                for (field, input) in request_templates(step) {
+                    // broken templates are reported by TemplatesRule
+                    let Ok(template) = Template::parse(input) else {
+                        continue;
+                    };
+                    for placeholder in template.placeholders() {
+                        let Placeholder::Data { source, column } = placeholder else {
+                            continue;
+                        };
+                        let path = format!("/journeys/{}/steps/{}/{}", i, j, field);
+                        match tables.iter().find(|(name, _)| name == source) {
+                            None => errors.push(ValidationError {
+                                path,
+                                code: "unknown_data_source".to_string(),
+                                message: format!("Unknown data source: {}. Declare it in `data_sources`", source),
+                            }),
+                            Some((_, Some(table))) if table.column(column).is_none() => errors.push(ValidationError {
+                                path,
+                                code: "unknown_column".to_string(),
+                                message: format!("Data source {} has no column {}, found: {}", source, column, table.columns.join(", ")),
+                            }),
+                            // an unreadable file is already reported
+                            Some(_) => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
 pub(crate) struct WebProtocolRule {
     message: String
 }
@@ -0,0 +1,128 @@
+//! `{{...}}` placeholders in request paths, headers and bodies.
+//! Parsing lives here so the validator and the runtime agree on the syntax,
+//! rendering is done per request by `libruntime`.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Placeholder {
+    /// `{{vu_id}}`
+    VuId,
+    /// `{{iteration}}`: how many times the VU completed its journey
+    Iteration,
+    /// `{{random_int(min,max)}}`, both bounds inclusive
+    RandomInt { min: i64, max: i64 },
+    /// `{{uuid}}`: random v4 uuid
+    Uuid,
+    /// `{{env.NAME}}`: environment variable of the rload process
+    Env(String),
+    /// `{{data.source.column}}`: column of the row the VU drew from a data source
+    Data { source: String, column: String },
+    /// `{{name}}`: scenario variable
+    Var(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    pub segments: Vec<Segment>,
+}
+
+impl Template {
+    pub fn parse(input: &str) -> Result<Template, String> {
+        let mut segments = Vec::new();
+        let mut rest = input;
+
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                segments.push(Segment::Text(rest[..start].to_string()));
+            }
+            let after_open = &rest[start + 2..];
+            let end = after_open.find("}}")
+                .ok_or_else(|| format!("unclosed placeholder in '{}'", input))?;
+            segments.push(Segment::Placeholder(parse_placeholder(after_open[..end].trim())?));
+            rest = &after_open[end + 2..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Text(rest.to_string()));
+        }
+
+        Ok(Template { segments })
+    }
+
+    /// `true` when there is nothing to render
+    pub fn is_static(&self) -> bool {
+        self.segments.iter().all(|s| matches!(s, Segment::Text(_)))
+    }
+
+    pub fn placeholders(&self) -> impl Iterator<Item = &Placeholder> {
+        self.segments.iter().filter_map(|s| match s {
+            Segment::Placeholder(p) => Some(p),
+            Segment::Text(_) => None,
+        })
+    }
+
+    pub fn render(&self, mut resolve: impl FnMut(&Placeholder) -> String) -> String {
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Placeholder(p) => out.push_str(&resolve(p)),
+            }
+        }
+        out
+    }
+}
+
+/// Only strings containing `{{` need to be parsed
+pub fn has_placeholders(input: &str) -> bool {
+    input.contains("{{")
+}
+
+fn parse_placeholder(expr: &str) -> Result<Placeholder, String> {
+    match expr {
+        "" => return Err("empty placeholder '{{}}'".to_string()),
+        "vu_id" => return Ok(Placeholder::VuId),
+        "iteration" => return Ok(Placeholder::Iteration),
+        "uuid" => return Ok(Placeholder::Uuid),
+        _ => {}
+    }
+    if let Some(name) = expr.strip_prefix("env.") {
+        if !is_identifier(name) {
+            return Err(format!("invalid environment variable name in '{{{{{}}}}}'", expr));
+        }
+        return Ok(Placeholder::Env(name.to_string()));
+    }
+    if let Some(field) = expr.strip_prefix("data.") {
+        let Some((source, column)) = field.split_once('.').filter(|(source, column)| is_identifier(source) && !column.is_empty()) else {
+            return Err(format!("expected '{{{{data.<source>.<column>}}}}' in '{{{{{}}}}}'", expr));
+        };
+        return Ok(Placeholder::Data { source: source.to_string(), column: column.to_string() });
+    }
+    if let Some(args) = expr.strip_prefix("random_int(").and_then(|a| a.strip_suffix(')')) {
+        let bounds: Vec<&str> = args.split(',').map(str::trim).collect();
+        let [min, max] = bounds.as_slice() else {
+            return Err(format!("random_int expects 2 arguments in '{{{{{}}}}}'", expr));
+        };
+        let (Ok(min), Ok(max)) = (min.parse::<i64>(), max.parse::<i64>()) else {
+            return Err(format!("random_int arguments must be integers in '{{{{{}}}}}'", expr));
+        };
+        if min > max {
+            return Err(format!("random_int min must be <= max in '{{{{{}}}}}'", expr));
+        }
+        return Ok(Placeholder::RandomInt { min, max });
+    }
+    if is_identifier(expr) {
+        return Ok(Placeholder::Var(expr.to_string()));
+    }
+    Err(format!("unknown placeholder '{{{{{}}}}}'", expr))
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -1,4 +1,7 @@
-use libprotocol::validate;
+use libprotocol::data_source::DataTable;
+use libprotocol::json_path::{JsonPath, PathSegment};
+use libprotocol::template::{Placeholder, Template};
+use libprotocol::{parse_scenario, read_scenario, validate, validate_with, Overrides, ScenarioFormat};
 use std::fs;
 use std::path::PathBuf;
 use tempfile::tempdir;
@@ -8,7 +11,7 @@ fn it_check_generate_scenario() -> anyhow::Result<()> {
     let tmp = tempdir()?;
     let output_file = tmp.path().join("result.txt");
 
-    let _ = libprotocol::generate_scenario(output_file.to_str().unwrap(), "1");
+    let _ = libprotocol::generate_scenario(output_file.to_str().unwrap(), "2");
 
     insta::assert_snapshot!(std::fs::read_to_string(&output_file)?);
 
@@ -51,6 +54,74 @@ fn it_check_validate_with_invalid_scenario_broken_json()
     }
 }
 
+#[test]
+fn it_check_validate_with_valid_yaml_and_toml_scenarios()
+{
+    validate(fixture_path("valid-scenario.yaml")).unwrap();
+    validate(fixture_path("valid-scenario.toml")).unwrap();
+    assert_eq!(
+        serde_json::to_value(parse_scenario(fixture_path("valid-scenario.yaml"))).unwrap(),
+        serde_json::to_value(parse_scenario(fixture_path("valid-scenario.toml"))).unwrap(),
+    );
+}
+#[test]
+fn it_check_validate_with_invalid_scenario_broken_yaml()
+{
+    let err = validate(fixture_path("broken-yaml-scenario.yaml")).unwrap_err();
+    match err {
+        libprotocol::ProtocolError::Syntax(e) => {
+            assert_eq!(ScenarioFormat::Yaml, e.format);
+            assert_eq!((6, 1), (e.line, e.column));
+        }
+        other => panic!("Expected Syntax error, got: {other:?}"),
+    }
+}
+#[test]
+fn it_check_validate_with_invalid_scenario_broken_toml()
+{
+    let err = validate(fixture_path("broken-toml-scenario.toml")).unwrap_err();
+    match err {
+        libprotocol::ProtocolError::Syntax(e) => {
+            assert_eq!(ScenarioFormat::Toml, e.format);
+            assert_eq!((5, 12), (e.line, e.column));
+            assert_eq!("string values must be quoted, expected literal string", e.message);
+        }
+        other => panic!("Expected Syntax error, got: {other:?}"),
+    }
+}
+#[test]
+fn it_check_validate_yaml_reports_the_same_paths_as_json()
+{
+    let errors = |name: &str| match validate(fixture_path(name)).unwrap_err() {
+        libprotocol::ProtocolError::Validation(e) => e.items.into_iter().map(|e| (e.path, e.message)).collect::<Vec<_>>(),
+        other => panic!("Expected Validation error, got: {other:?}"),
+    };
+    assert_eq!(errors("invalid-semantic-rules-scenario.json"), errors("invalid-semantic-rules-scenario.yaml"));
+}
+#[test]
+fn it_check_validate_with_explicit_format()
+{
+    let tmp = tempdir().unwrap();
+    let scenario_file = tmp.path().join("scenario.txt");
+    fs::copy(fixture_path("valid-scenario.yaml"), &scenario_file).unwrap();
+
+    assert!(matches!(validate(&scenario_file), Err(libprotocol::ProtocolError::Json(_))));
+    validate_with(&scenario_file, ScenarioFormat::Yaml, &Overrides::default()).unwrap();
+}
+#[test]
+fn it_check_generate_scenario_in_every_format() -> anyhow::Result<()> {
+    let tmp = tempdir()?;
+    for format in [ScenarioFormat::Json, ScenarioFormat::Yaml, ScenarioFormat::Toml] {
+        let output_file = tmp.path().join(format!("scenario.{}", format.extension()));
+        libprotocol::generate_scenario(&output_file, "2")?;
+        validate(&output_file).unwrap();
+        assert_eq!(format, ScenarioFormat::from_path(&output_file));
+    }
+    assert!("xml".parse::<ScenarioFormat>().is_err());
+
+    Ok(())
+}
+
 #[test]
 fn it_check_validate_with_invalid_scenario_validation_schema_error()
 {
@@ -99,11 +170,276 @@ fn it_check_validate_with_invalid_extended_scenario_validation_semantic_rules_wr
     }
 }
 #[test]
+fn it_check_validate_with_invalid_thresholds()
+{
+    let scenario_file = fixture_path("invalid-thresholds-scenario.json");
+    let err = validate(scenario_file).unwrap_err();
+    match err {
+        libprotocol::ProtocolError::Validation(e) => {
+            insta::assert_debug_snapshot!(e);
+        }
+        other => panic!("Expected Validation error, got: {other:?}"),
+    }
+}
+#[test]
 fn it_check_validate_with_valid_extended_scenario()
 {
     let scenario_file = fixture_path("valid-extended-scenario.json");
     validate(scenario_file).unwrap();
 }
+#[test]
+fn it_check_validate_with_valid_constant_vus_scenario()
+{
+    let scenario_file = fixture_path("valid-constant-vus-scenario.json");
+    validate(scenario_file).unwrap();
+}
+#[test]
+fn it_check_validate_with_valid_templates_scenario()
+{
+    let scenario_file = fixture_path("valid-templates-scenario.json");
+    validate(scenario_file).unwrap();
+}
+#[test]
+fn it_check_validate_with_invalid_templates()
+{
+    let scenario_file = fixture_path("invalid-templates-scenario.json");
+    let err = validate(scenario_file).unwrap_err();
+    match err {
+        libprotocol::ProtocolError::Validation(e) => {
+            insta::assert_debug_snapshot!(e);
+        }
+        other => panic!("Expected Validation error, got: {other:?}"),
+    }
+}
+#[test]
+fn it_check_validate_with_invalid_extract()
+{
+    let scenario_file = fixture_path("invalid-extract-scenario.json");
+    let err = validate(scenario_file).unwrap_err();
+    match err {
+        libprotocol::ProtocolError::Validation(e) => {
+            insta::assert_debug_snapshot!(e);
+        }
+        other => panic!("Expected Validation error, got: {other:?}"),
+    }
+}
+#[test]
+fn it_check_validate_with_invalid_checks()
+{
+    let scenario_file = fixture_path("invalid-checks-scenario.json");
+    let err = validate(scenario_file).unwrap_err();
+    match err {
+        libprotocol::ProtocolError::Validation(e) => {
+            insta::assert_debug_snapshot!(e);
+        }
+        other => panic!("Expected Validation error, got: {other:?}"),
+    }
+}
+#[test]
+fn it_check_validate_with_invalid_tls()
+{
+    let scenario_file = fixture_path("invalid-tls-scenario.json");
+    let err = validate(scenario_file).unwrap_err();
+    match err {
+        libprotocol::ProtocolError::Validation(e) => {
+            insta::assert_debug_snapshot!(e);
+        }
+        other => panic!("Expected Validation error, got: {other:?}"),
+    }
+}
+#[test]
+fn it_check_validate_with_valid_data_sources_scenario()
+{
+    let scenario_file = fixture_path("valid-data-sources-scenario.json");
+    validate(scenario_file).unwrap();
+}
+#[test]
+fn it_check_validate_with_invalid_data_sources()
+{
+    let scenario_file = fixture_path("invalid-data-sources-scenario.json");
+    let err = validate(scenario_file).unwrap_err();
+    match err {
+        libprotocol::ProtocolError::Validation(e) => {
+            insta::assert_debug_snapshot!(e);
+        }
+        other => panic!("Expected Validation error, got: {other:?}"),
+    }
+}
+#[test]
+fn it_check_validate_with_composed_scenario()
+{
+    let scenario_file = fixture_path("composed/scenario.yaml");
+    validate(&scenario_file).unwrap();
+
+    let scenario = parse_scenario(&scenario_file);
+    let journeys: Vec<(&str, usize)> = scenario.journeys.iter().map(|j| (j.name.as_str(), j.steps.len())).collect();
+    assert_eq!(vec![("login", 2), ("checkout", 3), ("browse", 2)], journeys);
+    assert_eq!("http://localhost:8080", scenario.target.base_url);
+    assert_eq!(Some("acme"), scenario.variables.as_ref().and_then(|v| v.get("tenant")).map(String::as_str));
+    assert_eq!(50, scenario.workload.stages[0].rps);
+}
+#[test]
+fn it_check_validate_with_invalid_composed_scenario()
+{
+    for name in ["invalid-scenario", "broken-refs-scenario", "cycle-a"] {
+        let scenario_file = fixture_path(&format!("composed/{}.yaml", name));
+        match validate(scenario_file).unwrap_err() {
+            libprotocol::ProtocolError::Validation(e) => {
+                insta::assert_debug_snapshot!(format!("composed_{}", name), e);
+            }
+            other => panic!("Expected Validation error, got: {other:?}"),
+        }
+    }
+}
+#[test]
+fn it_check_validate_with_profile_and_overrides()
+{
+    let scenario_file = fixture_path("profiles-scenario.yaml");
+    let staging = |values: &[&str]| Overrides {
+        profile: Some("staging".to_string()),
+        values: values.iter().map(|v| v.parse().unwrap()).collect(),
+    };
+    validate(&scenario_file).unwrap();
+    validate_with(&scenario_file, ScenarioFormat::Yaml, &staging(&[])).unwrap();
+
+    let scenario = read_scenario(&scenario_file, ScenarioFormat::Yaml, &staging(&["variables.tenant=globex", "workload.stages.0.rps=5"])).unwrap();
+    assert_eq!("https://staging.example.com", scenario.target.base_url);
+    let headers = scenario.target.default_headers.unwrap();
+    assert_eq!(vec!["Content-Type", "X-Env"], headers.keys().collect::<Vec<_>>());
+    let variables = scenario.variables.unwrap();
+    assert_eq!((Some("globex"), Some("eu-west-1")), (variables.get("tenant").map(String::as_str), variables.get("region").map(String::as_str)));
+    assert_eq!((60, 5), (scenario.workload.stages[0].duration_sec, scenario.workload.stages[0].rps));
+}
+#[test]
+fn it_check_validate_with_invalid_profile_and_overrides()
+{
+    let scenario_file = fixture_path("profiles-scenario.yaml");
+    let cases = [
+        ("broken", Overrides { profile: Some("broken".to_string()), values: Vec::new() }),
+        ("unknown", Overrides {
+            profile: Some("prod".to_string()),
+            values: ["workload.stages.0.rps=fast", "journeys.2.name=x"].iter().map(|v| v.parse().unwrap()).collect(),
+        }),
+        ("schema", Overrides { profile: None, values: vec!["workload.stages.0.rps=fast".parse().unwrap()] }),
+    ];
+    for (name, overrides) in cases {
+        match validate_with(&scenario_file, ScenarioFormat::Yaml, &overrides).unwrap_err() {
+            libprotocol::ProtocolError::Validation(e) => {
+                insta::assert_debug_snapshot!(format!("profiles_{}", name), e);
+            }
+            other => panic!("Expected Validation error, got: {other:?}"),
+        }
+    }
+}
+#[test]
+fn it_check_migrate_v1_scenario() -> anyhow::Result<()>
+{
+    let tmp = tempdir()?;
+    let scenario_file = fixture_path("v1-scenario.yaml");
+    let migrated = libprotocol::migrate_scenario(&scenario_file, 2, Some(&tmp.path().join("migrated.yaml")))?;
+
+    assert_eq!((1, 2, 3), (migrated.from, migrated.to, migrated.changes.len()));
+    insta::assert_snapshot!(fs::read_to_string(&migrated.out_path)?);
+    validate(&migrated.out_path)?;
+    // read in memory, a v1 scenario runs as its migrated file
+    assert_eq!(
+        serde_json::to_value(parse_scenario(&scenario_file))?,
+        serde_json::to_value(parse_scenario(&migrated.out_path))?,
+    );
+
+    let copy = tmp.path().join("scenario.json");
+    fs::copy(fixture_path("valid-scenario.json"), &copy)?;
+    let migrated = libprotocol::migrate_scenario(&copy, 2, None)?;
+    assert_eq!(tmp.path().join("scenario-v2.json"), migrated.out_path);
+    assert!(libprotocol::migrate_scenario(&migrated.out_path, 1, None).is_err());
+
+    let composed = libprotocol::migrate_scenario(fixture_path("composed/scenario.yaml"), 2, Some(&tmp.path().join("composed.yaml")))?;
+    assert_eq!(vec!["common.yaml", "journeys.yaml", "steps.yaml", "target.yaml"], composed.references);
+
+    // referenced files are left as they are, version 2 does not take unknown fields in them
+    fs::write(tmp.path().join("main.yaml"), "version: 1\nname: main\ninclude: [common.yaml]\nretries: 1\n")?;
+    fs::write(tmp.path().join("common.yaml"), "target: { base_url: http://localhost:8080, proxy: none }\n")?;
+    let error = libprotocol::migrate_scenario(tmp.path().join("main.yaml"), 2, None).unwrap_err();
+    assert_eq!("cannot migrate scenario: version 2 rejects these unknown fields, remove them or prefix them with `x-`: common.yaml#/target/proxy",
+               error.to_string());
+    assert!(!tmp.path().join("main-v2.yaml").exists());
+
+    fs::write(tmp.path().join("common.yaml"), "target: { base_url: http://localhost:8080, x-proxy: none }\n")?;
+    let migrated = libprotocol::migrate_scenario(tmp.path().join("main.yaml"), 2, None)?;
+    let changes: Vec<String> = migrated.changes.iter().map(ToString::to_string).collect();
+    assert_eq!(vec!["v2 /version: 1 -> 2", "v2 /retries: unknown field, renamed to /x-retries"], changes);
+    assert_eq!("version: 2\nname: main\ninclude:\n- common.yaml\nx-retries: 1\n", fs::read_to_string(&migrated.out_path)?);
+    Ok(())
+}
+#[test]
+fn it_check_export_schema_of_every_version() -> anyhow::Result<()>
+{
+    let tmp = tempdir()?;
+    let output_file = tmp.path().join("schema.json");
+    libprotocol::export_schema(&output_file, None)?;
+    libprotocol::export_schema(&output_file, Some("1".to_string()))?;
+
+    let current: serde_json::Value = serde_json::from_str(&fs::read_to_string(&output_file)?)?;
+    let v1: serde_json::Value = serde_json::from_str(&fs::read_to_string(tmp.path().join("schema-v1.json"))?)?;
+    assert_eq!(("2", "1"), (current["$version"].as_str().unwrap(), v1["$version"].as_str().unwrap()));
+    assert_eq!(serde_json::json!(false), current["$defs"]["Target"]["additionalProperties"]);
+    assert!(current["$defs"]["Target"]["patternProperties"].get("^x-").is_some());
+    assert!(v1["$defs"]["Target"].get("additionalProperties").is_none());
+    assert!(libprotocol::export_schema(&output_file, Some("3".to_string())).is_err());
+    Ok(())
+}
+#[test]
+fn it_check_parse_json_path()
+{
+    let path = JsonPath::parse("$.data.items[0]['display name']").unwrap();
+    assert_eq!(vec![
+        PathSegment::Key("data".to_string()),
+        PathSegment::Key("items".to_string()),
+        PathSegment::Index(0),
+        PathSegment::Key("display name".to_string()),
+    ], path.segments);
+    let value = serde_json::json!({"data": {"items": [{"display name": "x"}]}});
+    assert_eq!(Some(&serde_json::json!("x")), path.select(&value));
+    assert!(JsonPath::parse("data.token").is_err());
+    assert!(JsonPath::parse("$.items[x]").is_err());
+    assert!(JsonPath::parse("$..items").is_err());
+}
+#[test]
+fn it_check_parse_template()
+{
+    let template = Template::parse("/users/{{vu_id}}/{{ env.HOME }}?n={{random_int(-5, 5)}}&t={{tenant}}").unwrap();
+    assert_eq!(vec![
+        &Placeholder::VuId,
+        &Placeholder::Env("HOME".to_string()),
+        &Placeholder::RandomInt { min: -5, max: 5 },
+        &Placeholder::Var("tenant".to_string()),
+    ], template.placeholders().collect::<Vec<_>>());
+    assert!(Template::parse("/static").unwrap().is_static());
+    assert!(Template::parse("/{{vu_id").is_err());
+    assert!(Template::parse("/{{}}").is_err());
+    assert!(Template::parse("/{{random_int(1)}}").is_err());
+    assert!(Template::parse("/{{1abc}}").is_err());
+    assert_eq!(
+        Some(&Placeholder::Data { source: "users".to_string(), column: "first name".to_string() }),
+        Template::parse("{{ data.users.first name }}").unwrap().placeholders().next(),
+    );
+    assert!(Template::parse("/{{data.users}}").is_err());
+}
+#[test]
+fn it_check_parse_data_table()
+{
+    let table = DataTable::parse_csv("email,password\na@example.com,\"se,cret\"\nb@example.com,pass\n").unwrap();
+    assert_eq!(vec!["email", "password"], table.columns);
+    assert_eq!(2, table.rows.len());
+    assert_eq!(Some("se,cret"), table.value(0, "password"));
+    assert_eq!(None, table.value(0, "token"));
+    assert!(DataTable::parse_csv("a,b\n1,2,3\n").is_err());
+
+    let table = DataTable::parse_jsonl("{\"id\": 1, \"name\": \"a\"}\n\n{\"name\": \"b\", \"tags\": [\"x\"]}\n").unwrap();
+    assert_eq!(vec!["id", "name", "tags"], table.columns);
+    assert_eq!(vec![vec!["1", "a", ""], vec!["", "b", "[\"x\"]"]], table.rows);
+    assert_eq!("line 1: expected a JSON object", DataTable::parse_jsonl("[1]").unwrap_err());
+}
 
 fn fixture_path(name: &str) -> PathBuf {
     PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -0,0 +1,289 @@
+use crate::run_engine::{LatencySummary, Requests, RunReport};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// How much worse a metric may get before it counts as a regression
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerances {
+    /// Allowed latency increase, in percent of the baseline
+    pub latency_pct: f64,
+    /// Allowed error rate increase, in percentage points
+    pub error_rate_points: f64,
+    /// Allowed achieved RPS decrease, in percent of the baseline
+    pub rps_pct: f64,
+}
+
+impl Default for Tolerances {
+    fn default() -> Self {
+        Tolerances { latency_pct: 10.0, error_rate_points: 1.0, rps_pct: 10.0 }
+    }
+}
+
+/// One metric of one scope in both reports. A scope missing from a report has no value there
+/// and is never a regression.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MetricDiff {
+    /// `overall`, `endpoint <METHOD /path>`, `stage <index>` or `journey <name>`
+    pub scope: String,
+    pub metric: &'static str,
+    pub baseline: Option<f64>,
+    pub current: Option<f64>,
+    /// Relative change in percent, `None` when either side is missing or the baseline is 0
+    pub change_pct: Option<f64>,
+    pub regressed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    pub regressions: usize,
+    pub metrics: Vec<MetricDiff>,
+}
+
+/// Returned by the CLI when the current report regressed, so `bin/rload` can map it to an exit code.
+#[derive(Debug)]
+pub struct RegressionsFound {
+    pub regressions: usize,
+}
+
+impl std::fmt::Display for RegressionsFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "regressions found: {}", self.regressions)
+    }
+}
+
+impl std::error::Error for RegressionsFound {}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    /// latency, error rate
+    LowerIsBetter,
+    /// throughput
+    HigherIsBetter,
+}
+
+/// The values a scope has in one report
+#[derive(Default)]
+struct ScopeMetrics {
+    latency: Option<LatencySummary>,
+    requests: Option<Requests>,
+    achieved_rps: Option<f64>,
+}
+
+/// Diffs latency percentiles, error rate and achieved RPS overall and per endpoint, stage and journey
+pub fn compare(baseline: &RunReport, current: &RunReport, tolerances: &Tolerances) -> Comparison {
+    let mut metrics = Vec::new();
+    let mut push = |scope: String, baseline: ScopeMetrics, current: ScopeMetrics| {
+        for (metric, pick) in [("latency.p50", 0), ("latency.p95", 1), ("latency.p99", 2)] {
+            let value = |m: &ScopeMetrics| m.latency.map(|l| [l.p50, l.p95, l.p99][pick] as f64);
+            if let Some(diff) = diff(&scope, metric, value(&baseline), value(&current), Direction::LowerIsBetter, |change, _| change > tolerances.latency_pct) {
+                metrics.push(diff);
+            }
+        }
+        let error_rate = |m: &ScopeMetrics| m.requests.filter(|r| r.total > 0).map(|r| r.error as f64 * 100.0 / r.total as f64);
+        if let Some(diff) = diff(&scope, "http.error_rate", error_rate(&baseline), error_rate(&current), Direction::LowerIsBetter, |_, points| points > tolerances.error_rate_points) {
+            metrics.push(diff);
+        }
+        if let Some(diff) = diff(&scope, "rps.achieved", baseline.achieved_rps, current.achieved_rps, Direction::HigherIsBetter, |change, _| change > tolerances.rps_pct) {
+            metrics.push(diff);
+        }
+    };
+
+    push(
+        "overall".to_string(),
+        overall(baseline),
+        overall(current),
+    );
+    for endpoint in keys(baseline.by_endpoint.keys(), current.by_endpoint.keys()) {
+        push(format!("endpoint {}", endpoint), endpoint_metrics(baseline, &endpoint), endpoint_metrics(current, &endpoint));
+    }
+    for stage in keys(baseline.latency_by_stage.keys(), current.latency_by_stage.keys()) {
+        push(format!("stage {}", stage), stage_metrics(baseline, stage), stage_metrics(current, stage));
+    }
+    let journey_names = |report: &RunReport| report.by_journey.iter().map(|j| j.key.clone()).collect::<Vec<_>>();
+    for journey in keys(journey_names(baseline).iter(), journey_names(current).iter()) {
+        push(format!("journey {}", journey), journey_metrics(baseline, &journey), journey_metrics(current, &journey));
+    }
+
+    let regressions = metrics.iter().filter(|m| m.regressed).count();
+    Comparison { regressions, metrics }
+}
+
+fn keys<'a, K: Ord + Clone + 'a>(a: impl Iterator<Item = &'a K>, b: impl Iterator<Item = &'a K>) -> BTreeSet<K> {
+    a.chain(b).cloned().collect()
+}
+
+fn overall(report: &RunReport) -> ScopeMetrics {
+    ScopeMetrics {
+        latency: Some(report.latency_overall_summary),
+        requests: Some(report.requests),
+        achieved_rps: Some(report.rps.achieved_avg as f64),
+    }
+}
+
+fn endpoint_metrics(report: &RunReport, endpoint: &str) -> ScopeMetrics {
+    report.by_endpoint.get(endpoint)
+        .map(|stats| ScopeMetrics {
+            latency: Some(stats.latency_summary),
+            requests: Some(stats.request),
+            achieved_rps: Some(stats.achieved_rps),
+        })
+        .unwrap_or_default()
+}
+
+/// Stages have no error counts in the report
+fn stage_metrics(report: &RunReport, stage: u64) -> ScopeMetrics {
+    ScopeMetrics {
+        latency: report.latency_by_stage.get(&stage).copied(),
+        requests: None,
+        achieved_rps: report.rps.by_stage.get(&stage).map(|s| s.achieved_rps as f64),
+    }
+}
+
+/// Journeys have no throughput in the report
+fn journey_metrics(report: &RunReport, journey: &str) -> ScopeMetrics {
+    report.by_journey.iter().find(|j| j.key == journey)
+        .map(|stats| ScopeMetrics {
+            latency: Some(stats.latency_summary),
+            requests: Some(stats.request),
+            achieved_rps: None,
+        })
+        .unwrap_or_default()
+}
+
+/// `exceeds(change_pct, change_abs)` gets how much worse the current value is, both 0 when it is not worse
+fn diff(scope: &str, metric: &'static str, baseline: Option<f64>, current: Option<f64>, direction: Direction, exceeds: impl Fn(f64, f64) -> bool) -> Option<MetricDiff> {
+    if baseline.is_none() && current.is_none() {
+        return None;
+    }
+    let change_pct = match (baseline, current) {
+        (Some(b), Some(c)) if b != 0.0 => Some((c - b) * 100.0 / b),
+        _ => None,
+    };
+    let regressed = match (baseline, current) {
+        (Some(b), Some(c)) => {
+            let worse = match direction {
+                Direction::LowerIsBetter => c - b,
+                Direction::HigherIsBetter => b - c,
+            }.max(0.0);
+            // from a zero baseline any increase is an infinite relative change
+            let worse_pct = match b {
+                0.0 if worse > 0.0 => f64::INFINITY,
+                0.0 => 0.0,
+                b => worse * 100.0 / b,
+            };
+            exceeds(worse_pct, worse)
+        }
+        _ => false,
+    };
+    Some(MetricDiff { scope: scope.to_string(), metric, baseline, current, change_pct, regressed })
+}
+
+impl Comparison {
+    /// Aligned text table, regressed rows are marked with `!`
+    pub fn table(&self) -> String {
+        let rows: Vec<[String; 6]> = self.metrics.iter().map(|m| [
+            if m.regressed { "!".to_string() } else { String::new() },
+            m.scope.clone(),
+            m.metric.to_string(),
+            value(m.baseline),
+            value(m.current),
+            m.change_pct.map(|c| format!("{:+.1}%", c)).unwrap_or_else(|| "-".to_string()),
+        ]).collect();
+        let header = ["", "scope", "metric", "baseline", "current", "change"].map(String::from);
+
+        let mut widths = [0usize; 6];
+        for row in std::iter::once(&header).chain(&rows) {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let mut out = String::new();
+        for row in std::iter::once(&header).chain(&rows) {
+            let line = row.iter().zip(widths).enumerate()
+                .map(|(i, (cell, width))| match i {
+                    // numbers right-aligned
+                    3..=5 => format!("{:>width$}", cell, width = width),
+                    _ => format!("{:<width$}", cell, width = width),
+                })
+                .collect::<Vec<_>>()
+                .join("  ");
+            let _ = writeln!(out, "{}", line.trim_end());
+        }
+        let _ = writeln!(out, "\n{} regression(s) in {} metrics", self.regressions, self.metrics.len());
+        out
+    }
+}
+
+fn value(value: Option<f64>) -> String {
+    match value {
+        Some(v) if v.fract() == 0.0 => format!("{}", v),
+        Some(v) => format!("{:.2}", v),
+        None => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compare::{compare, Comparison, Tolerances};
+    use crate::run_engine::{LatencySummary, RunReport};
+    use crate::test_runs::{mock_report, RUN_ENGINE_FIXTURE};
+
+    async fn report() -> RunReport {
+        let mut report = mock_report(RUN_ENGINE_FIXTURE).await;
+        report.time.real_time_duration_sec = 3; // flaky test
+        report
+    }
+
+    #[tokio::test]
+    async fn it_finds_no_regression_against_itself() {
+        let baseline = report().await;
+
+        let comparison = compare(&baseline, &baseline, &Tolerances::default());
+
+        assert_eq!(0, comparison.regressions);
+        assert!(comparison.metrics.iter().any(|m| m.scope.starts_with("endpoint ")));
+        assert!(comparison.metrics.iter().any(|m| m.scope == "stage 1"));
+        assert!(comparison.metrics.iter().any(|m| m.scope.starts_with("journey ")));
+    }
+
+    #[tokio::test]
+    async fn it_flags_regressions_beyond_tolerances() {
+        let baseline = report().await;
+        let mut current = report().await;
+        let endpoint = current.by_endpoint.keys().next().unwrap().clone();
+        let stats = current.by_endpoint.get_mut(&endpoint).unwrap();
+        stats.latency_summary = LatencySummary { p95: 100, ..stats.latency_summary };
+        stats.request.error = stats.request.total / 50;
+        current.rps.by_stage.get_mut(&0).unwrap().achieved_rps = 950;
+
+        let comparison = compare(&baseline, &current, &Tolerances::default());
+
+        let regressed: Vec<(String, &str)> = comparison.metrics.iter()
+            .filter(|m| m.regressed)
+            .map(|m| (m.scope.clone(), m.metric))
+            .collect();
+        assert_eq!(vec![
+            (format!("endpoint {}", endpoint), "latency.p95"),
+            (format!("endpoint {}", endpoint), "http.error_rate"),
+        ], regressed);
+
+        // 5% slower stage 0 is within the default 10%
+        let strict = Tolerances { rps_pct: 1.0, ..Tolerances::default() };
+        assert!(compare(&baseline, &current, &strict).metrics.iter().any(|m| m.scope == "stage 0" && m.metric == "rps.achieved" && m.regressed));
+    }
+
+    #[tokio::test]
+    async fn it_prints_a_table() {
+        let baseline = report().await;
+        let mut current = report().await;
+        current.requests.error = current.requests.total / 10;
+        current.rps.achieved_avg /= 2;
+
+        let comparison = compare(&baseline, &current, &Tolerances::default());
+        let overall: Vec<_> = comparison.metrics.iter().filter(|m| m.scope == "overall").cloned().collect();
+        let regressions = overall.iter().filter(|m| m.regressed).count();
+
+        insta::assert_snapshot!(Comparison { regressions, metrics: overall }.table());
+    }
+}
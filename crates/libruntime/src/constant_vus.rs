@@ -0,0 +1,276 @@
+use crate::events::{Event, EventSink, StopSignal};
+use crate::execution_plan::ExecutionPlan;
+use crate::metrics::MetricsAggregator;
+use crate::run_engine::{build_pool, finish_report, Completed, RunEngine, RunReport};
+use crate::scheduler::Scheduler;
+use crate::trace_context::unix_ns;
+use crate::vu_runner::{ErrorType, ExecutorAbstract, NextAction, RequestSpec, ResponseResult, VUState, VuPool, VuRuntime};
+use libprotocol::schema::Scenario;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+impl RunEngine {
+    /// Closed model: `vus` virtual users loop their journeys back-to-back for `duration_sec`.
+    /// Throughput is a consequence of latency and think time, there is no scheduler.
+    pub(crate) async fn run_constant_vus(&self, plan: &ExecutionPlan, scenario: &Scenario, vus: u32, duration_sec: u32, sink: EventSink<Event>) -> RunReport {
+        let start_time = Instant::now();
+        let duration_ms = duration_sec as u64 * 1000;
+
+        let mut run_report = RunReport::new(&Scheduler::new(&scenario.workload));
+        run_report.time.planned_duration_ms = duration_ms;
+        run_report.time.planned_duration_sec = duration_sec as f64;
+
+        let executor = self.executor(plan);
+        let plan = Arc::new(plan.clone());
+        let (pool, journey_per_vu) = build_pool(&plan, 0..vus);
+        let mut metrics = MetricsAggregator::with_timeseries_interval_ms(plan.timeseries_interval_ms);
+
+        let pool = match self.is_real_time {
+            true => self.run_real_time(executor, plan, pool, duration_ms, &mut metrics, &sink).await,
+            false => self.run_virtual_time(executor, plan, pool, duration_ms, &mut metrics, &sink).await,
+        };
+
+        sink.send(Event::RunFinished);
+
+        // Time
+        run_report.time.real_time_duration_sec = start_time.elapsed().as_secs();
+        let duration_sec_measured = match self.is_real_time {
+            true => start_time.elapsed().as_secs_f64().max(0.001),
+            false => duration_sec as f64,
+        };
+
+        // RPS: nothing is planned in the closed model
+        run_report.rps.planned_avg = 0;
+        run_report.rps.achieved_avg = (metrics.total_requests as f64 / duration_sec_measured) as u64;
+        run_report.rps.achieved_avg_including_drain = run_report.rps.achieved_avg;
+
+        // Vu Utilization
+        run_report.vus.count = vus as u64;
+        run_report.vus.iterations = pool.get_total_iterations();
+
+        run_report.run.mode = "constant_vus".to_string();
+        run_report.run.duration_sec_planned = duration_sec as u64;
+
+        run_report.sleep = pool.get_total_sleep_ms();
+
+        finish_report(run_report, metrics, scenario, &journey_per_vu)
+    }
+
+    /// Deterministic mode: always advances the VU that becomes ready first, time is virtual.
+    async fn run_virtual_time(
+        &self,
+        executor: Arc<Box<dyn ExecutorAbstract>>,
+        plan: Arc<ExecutionPlan>,
+        mut pool: VuPool,
+        duration_ms: u64,
+        metrics: &mut MetricsAggregator,
+        sink: &EventSink<Event>,
+    ) -> VuPool {
+        let raw = self.raw_results.as_ref();
+        let runtime = VuRuntime{};
+        let mut executed: u64 = 0;
+        let origin_unix_ms = unix_ns(SystemTime::now()) / 1_000_000;
+
+        while let Some(vu_idx) = pool.next_ready_vu() {
+            let vu = pool.get_mut(vu_idx).unwrap();
+            let now = vu.next_ready_at_ms;
+            if now >= duration_ms || self.stop.is_stopped() {
+                break;
+            }
+
+            match runtime.next_action(&plan, vu, now).await {
+                NextAction::Ready(req) => {
+                    executed += 1;
+                    let res = executor.execute(&plan, &req, executed).await
+                        .unwrap_or_else(|e| executor_error(&req, e, sink));
+                    // a request always takes time, otherwise the virtual clock could stall
+                    let finished_ms = now + res.latency_ms.max(1);
+
+                    sink.send(Event::request_finished(&res));
+                    let vu = pool.get_mut(vu_idx).unwrap();
+                    if let Some(raw) = raw {
+                        raw.record(origin_unix_ms + finished_ms, vu.vu_id, &res);
+                    }
+                    runtime.on_request_executed(&plan, vu, finished_ms, &res);
+                    metrics.consume(res, now);
+                }
+                NextAction::NotReady(_next_ready_at) => { /* entered think time */ }
+                NextAction::CompletedIteration => {
+                    // journey without requests
+                    vu.next_ready_at_ms = now + 1;
+                }
+            }
+        }
+        pool
+    }
+
+    /// Real mode: one task per VU, completed requests are collected through a channel.
+    async fn run_real_time(
+        &self,
+        executor: Arc<Box<dyn ExecutorAbstract>>,
+        plan: Arc<ExecutionPlan>,
+        pool: VuPool,
+        duration_ms: u64,
+        metrics: &mut MetricsAggregator,
+        sink: &EventSink<Event>,
+    ) -> VuPool {
+        let raw = self.raw_results.as_ref();
+        let (tx, mut rx) = mpsc::unbounded_channel::<Completed>();
+        let window = RunWindow { origin: Instant::now(), duration_ms, stop: self.stop.clone() };
+        let origin_unix_ms = unix_ns(SystemTime::now()) / 1_000_000;
+
+        let handles: Vec<_> = pool.into_vus().into_iter().enumerate().map(|(vu_idx, vu)| {
+            let tx = tx.clone();
+            let executor = executor.clone();
+            let plan = plan.clone();
+            tokio::spawn(vu_loop(vu_idx, vu, executor, plan, window.clone(), tx, sink.clone()))
+        }).collect();
+        drop(tx);
+
+        // closes once every VU task has dropped its sender
+        while let Some(done) = rx.recv().await {
+            sink.send(Event::request_finished(&done.res));
+            if let Some(raw) = raw {
+                raw.record(origin_unix_ms + done.now_ms, done.vu_id, &done.res);
+            }
+            metrics.consume(done.res, done.last_request_started_ms);
+        }
+
+        let mut vus = Vec::with_capacity(handles.len());
+        for handle in handles {
+            if let Ok(vu) = handle.await {
+                vus.push(vu);
+            }
+        }
+        VuPool::new(vus)
+    }
+}
+
+/// VU tasks of a real-time run start requests until `duration_ms` after `origin` or a stop
+#[derive(Clone)]
+struct RunWindow {
+    origin: Instant,
+    duration_ms: u64,
+    stop: StopSignal,
+}
+
+async fn vu_loop(
+    vu_idx: usize,
+    mut vu: VUState,
+    executor: Arc<Box<dyn ExecutorAbstract>>,
+    plan: Arc<ExecutionPlan>,
+    window: RunWindow,
+    tx: mpsc::UnboundedSender<Completed>,
+    sink: EventSink<Event>,
+) -> VUState {
+    let runtime = VuRuntime{};
+    let mut executed: u64 = 0;
+    let RunWindow { origin, duration_ms, stop } = window;
+
+    loop {
+        let now = origin.elapsed().as_millis() as u64;
+        if now >= duration_ms || stop.is_stopped() {
+            break;
+        }
+
+        match runtime.next_action(&plan, &mut vu, now).await {
+            NextAction::NotReady(next_ready_at) => {
+                tokio::time::sleep(Duration::from_millis(next_ready_at.min(duration_ms).saturating_sub(now))).await;
+            }
+            NextAction::CompletedIteration => {
+                // journey without requests
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+            NextAction::Ready(req) => {
+                executed += 1;
+                let res = executor.execute(&plan, &req, executed).await
+                    .unwrap_or_else(|e| executor_error(&req, e, &sink));
+
+                // the VU stays busy for the reported latency (matters for the mock executor)
+                let busy_until = now + res.latency_ms.max(1);
+                let elapsed = origin.elapsed().as_millis() as u64;
+                if busy_until > elapsed {
+                    tokio::time::sleep(Duration::from_millis(busy_until - elapsed)).await;
+                }
+
+                let finished_ms = origin.elapsed().as_millis() as u64;
+                runtime.on_request_executed(&plan, &mut vu, finished_ms, &res);
+                let _ = tx.send(Completed {
+                    vu_idx,
+                    vu_id: vu.vu_id,
+                    now_ms: finished_ms,
+                    res,
+                    last_request_started_ms: now,
+                });
+            }
+        }
+    }
+    vu
+}
+
+fn executor_error(req: &RequestSpec, e: String, sink: &EventSink<Event>) -> ResponseResult {
+    sink.send(Event::Warning { message: format!("executor error: {e}") });
+    ResponseResult::error(req, ErrorType::ConnectionError)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::{Event, EventSink, StopSignal};
+    use crate::execution_plan::ExecutionPlan;
+    use crate::run_engine::RunEngine;
+    use libprotocol::schema::WorkloadExecutor;
+    use crate::test_runs::{fixture_scenario, RUN_ENGINE_FIXTURE};
+    use libprotocol::Scenario;
+
+    fn scenario(vus: u32, duration_sec: u32) -> Scenario {
+        let mut scenario = fixture_scenario(RUN_ENGINE_FIXTURE);
+        scenario.workload.executor = Some(WorkloadExecutor::ConstantVus { vus, duration_sec });
+        scenario
+    }
+
+    #[tokio::test]
+    async fn it_run_constant_vus_mock_and_check_run_report() {
+        let scenario = scenario(10, 2);
+        let execution_plan = ExecutionPlan::from(&scenario);
+
+        let mut report = RunEngine::new(Some(true), Some(false))
+            .run(&execution_plan, &scenario, EventSink::<Event>::noop()).await;
+        report.time.real_time_duration_sec = 2; // flaky test
+        insta::assert_debug_snapshot!(report);
+    }
+
+    #[tokio::test]
+    async fn it_run_constant_vus_respects_think_time() {
+        let scenario = scenario(1, 1);
+        let execution_plan = ExecutionPlan::from(&scenario);
+
+        let report = RunEngine::new(Some(true), Some(false))
+            .run(&execution_plan, &scenario, EventSink::<Event>::noop()).await;
+
+        // a single VU can not issue more than one request per ms, sleeps slow it down further
+        assert!(report.requests.total > 0);
+        assert!(report.requests.total < 1000);
+        assert!(report.sleep > 0);
+        assert_eq!(1, report.vus.count);
+    }
+
+    #[tokio::test]
+    async fn it_stops_early_and_reports_what_ran() {
+        let scenario = scenario(2, 60);
+        let execution_plan = ExecutionPlan::from(&scenario);
+        let stop = StopSignal::default();
+        let stopper = stop.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            stopper.stop();
+        });
+
+        let report = RunEngine::new(Some(true), Some(true)).with_stop(stop)
+            .run(&execution_plan, &scenario, EventSink::<Event>::noop()).await;
+
+        assert!(report.requests.total > 0);
+        assert!(report.time.real_time_duration_sec < 10);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::vu_runner::VUState;
+use libprotocol::data_source::DataTable;
+use libprotocol::schema::{Distribution, OnExhausted, Scenario, Step};
+use libprotocol::template::{Placeholder, Template};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// A data source file could not be loaded.
+#[derive(Debug)]
+pub struct DataSourceError {
+    pub message: String,
+}
+
+impl std::fmt::Display for DataSourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid data source: {}", self.message)
+    }
+}
+
+impl std::error::Error for DataSourceError {}
+
+/// Rows of the scenario `data_sources`, loaded once before the run and shared by all VUs.
+#[derive(Debug, Clone, Default)]
+pub struct DataFeeds {
+    feeds: BTreeMap<String, DataFeed>,
+    /// Sources referenced by each journey, by journey index: only those take rows
+    by_journey: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone)]
+struct DataFeed {
+    table: Arc<DataTable>,
+    distribution: Distribution,
+    seed: Option<u64>,
+    on_exhausted: OnExhausted,
+    /// Next row of the `shared` distribution
+    cursor: Arc<AtomicU64>,
+}
+
+impl DataFeeds {
+    /// Relative paths are taken from `dir`, the directory of the scenario file
+    pub fn load(scenario: &Scenario, dir: &Path) -> Result<DataFeeds, DataSourceError> {
+        let mut feeds = BTreeMap::new();
+        for source in scenario.data_sources.iter().flatten() {
+            let format = source.format().ok_or_else(|| DataSourceError {
+                message: format!("{}: cannot tell the format of {}", source.name, source.path),
+            })?;
+            let path = dir.join(&source.path);
+            let table = DataTable::load(&path, format)
+                .map_err(|e| DataSourceError { message: format!("{}: {}", path.display(), e) })?;
+            if table.rows.is_empty() {
+                return Err(DataSourceError { message: format!("{}: {} has no rows", source.name, source.path) });
+            }
+            feeds.insert(source.name.clone(), DataFeed {
+                table: Arc::new(table),
+                distribution: source.distribution(),
+                seed: source.seed,
+                on_exhausted: source.on_exhausted(),
+                cursor: Arc::new(AtomicU64::new(0)),
+            });
+        }
+        let by_journey = scenario.journeys.iter()
+            .map(|journey| referenced_sources(&journey.steps))
+            .collect();
+
+        Ok(DataFeeds { feeds, by_journey })
+    }
+
+    /// Draws the rows of the VU's current iteration, once per iteration. `run_seed` is
+    /// `ExecutionPlan::random_seed`, used by `random` sources without a `seed`.
+    /// Returns `false` when a source is exhausted and the VU has to stop.
+    pub(crate) fn draw(&self, vu: &mut VUState, run_seed: &str) -> bool {
+        if vu.data_iteration == Some(vu.iteration_count) {
+            return true;
+        }
+        vu.data_iteration = Some(vu.iteration_count);
+        for name in self.by_journey.get(vu.journey_id as usize).into_iter().flatten() {
+            let Some(feed) = self.feeds.get(name) else {
+                continue;
+            };
+            match feed.row(name, vu, run_seed) {
+                Some(row) => vu.data_rows.insert(name.clone(), row),
+                None => return false,
+            };
+        }
+        true
+    }
+
+    pub(crate) fn value(&self, vu: &VUState, source: &str, column: &str) -> Option<&str> {
+        let row = *vu.data_rows.get(source)?;
+        self.feeds.get(source)?.table.value(row, column)
+    }
+}
+
+impl DataFeed {
+    fn row(&self, name: &str, vu: &VUState, run_seed: &str) -> Option<usize> {
+        let rows = self.table.rows.len() as u64;
+        let index = match self.distribution {
+            Distribution::Sequential => vu.iteration_count,
+            Distribution::Random => {
+                let seed = self.seed.map(|seed| seed.to_string()).unwrap_or_else(|| run_seed.to_string());
+                let key = format!("{}-vu={}-iteration={}:seed={}", name, vu.vu_id, vu.iteration_count, seed);
+                u64::from_be_bytes(Sha256::digest(key.as_bytes())[0..8].try_into().unwrap())
+            }
+            Distribution::UniquePerVu => vu.vu_id as u64,
+            Distribution::Shared => self.cursor.fetch_add(1, Ordering::Relaxed),
+        };
+        let exhaustible = matches!(self.distribution, Distribution::UniquePerVu | Distribution::Shared);
+        if exhaustible && index >= rows && self.on_exhausted == OnExhausted::StopVu {
+            return None;
+        }
+        Some((index % rows) as usize)
+    }
+}
+
+fn referenced_sources(steps: &[Step]) -> Vec<String> {
+    let mut sources: Vec<String> = Vec::new();
+    for step in steps {
+        let Step::Request { path, headers, body, .. } = step else {
+            continue;
+        };
+        let templates = std::iter::once(path).chain(headers.iter().flat_map(|h| h.values())).chain(body);
+        for template in templates.filter_map(|t| Template::parse(t).ok()) {
+            for placeholder in template.placeholders() {
+                if let Placeholder::Data { source, .. } = placeholder && !sources.contains(source) {
+                    sources.push(source.clone());
+                }
+            }
+        }
+    }
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_feeds::DataFeeds;
+    use crate::vu_runner::VUState;
+    use libprotocol::schema::{DataSource, Distribution, Journey, OnExhausted, Scenario, Step, StepMethod};
+    use test_support::fixture_path;
+
+    fn load(distribution: Distribution, on_exhausted: Option<OnExhausted>) -> DataFeeds {
+        let scenario = Scenario {
+            data_sources: Some(vec![DataSource {
+                name: "users".to_string(),
+                path: "users.csv".to_string(),
+                format: None,
+                distribution: Some(distribution),
+                seed: None,
+                on_exhausted,
+            }]),
+            journeys: vec![Journey {
+                steps: vec![Step::Request {
+                    method: StepMethod::GET,
+                    path: "/users/{{data.users.email}}".to_string(),
+                    headers: None,
+                    body: None,
+                    timeout_ms: None,
+                    extract: None,
+                    checks: None,
+                }],
+                ..Journey::default()
+            }],
+            ..Scenario::default()
+        };
+        DataFeeds::load(&scenario, &fixture_path("crates/libruntime/tests/fixtures")).unwrap()
+    }
+
+    /// Emails of the rows the VU draws in its first `iterations` iterations
+    fn emails(feeds: &DataFeeds, vu: &mut VUState, iterations: u64) -> Vec<String> {
+        seeded_emails(feeds, vu, iterations, "1")
+    }
+
+    fn seeded_emails(feeds: &DataFeeds, vu: &mut VUState, iterations: u64, run_seed: &str) -> Vec<String> {
+        (0..iterations).map_while(|iteration| {
+            vu.iteration_count = iteration;
+            feeds.draw(vu, run_seed).then(|| feeds.value(vu, "users", "email").unwrap().to_string())
+        }).collect()
+    }
+
+    #[test]
+    fn it_walks_rows_sequentially_per_vu() {
+        let feeds = load(Distribution::Sequential, None);
+
+        let first = emails(&feeds, &mut VUState::new(0, 0), 4);
+        assert_eq!(vec!["a@example.com", "b@example.com", "c@example.com", "a@example.com"], first);
+        assert_eq!(first, emails(&feeds, &mut VUState::new(1, 0), 4));
+    }
+
+    #[test]
+    fn it_draws_reproducible_random_rows() {
+        let feeds = load(Distribution::Random, None);
+
+        let drawn = emails(&feeds, &mut VUState::new(0, 0), 30);
+        assert_eq!(drawn, emails(&feeds, &mut VUState::new(0, 0), 30));
+        assert!(drawn.contains(&"a@example.com".to_string()) && drawn.contains(&"c@example.com".to_string()));
+    }
+
+    #[test]
+    fn it_draws_other_random_rows_for_another_run_seed() {
+        let feeds = load(Distribution::Random, None);
+
+        let drawn = seeded_emails(&feeds, &mut VUState::new(0, 0), 30, "1");
+        assert_ne!(drawn, seeded_emails(&feeds, &mut VUState::new(0, 0), 30, "2"));
+    }
+
+    #[test]
+    fn it_keeps_one_row_per_vu() {
+        let feeds = load(Distribution::UniquePerVu, Some(OnExhausted::StopVu));
+
+        assert_eq!(vec!["b@example.com"; 3], emails(&feeds, &mut VUState::new(1, 0), 3));
+        assert!(emails(&feeds, &mut VUState::new(3, 0), 3).is_empty());
+    }
+
+    #[test]
+    fn it_shares_one_cursor_between_vus() {
+        let feeds = load(Distribution::Shared, Some(OnExhausted::StopVu));
+        let (mut first, mut second) = (VUState::new(0, 0), VUState::new(1, 0));
+
+        assert_eq!(vec!["a@example.com"], emails(&feeds, &mut first, 1));
+        assert_eq!(vec!["b@example.com", "c@example.com"], emails(&feeds, &mut second, 3));
+        // rows are drawn once per iteration
+        assert!(feeds.draw(&mut first, "1"));
+        assert_eq!(Some("a@example.com"), feeds.value(&first, "users", "email"));
+
+        let recycled = load(Distribution::Shared, None);
+        assert_eq!(4, emails(&recycled, &mut VUState::new(0, 0), 4).len());
+    }
+}
@@ -5,6 +5,7 @@ use std::collections::BTreeMap;
 use libprotocol::Scenario;
 use crate::events::{Event, EventSink};
 use crate::run_engine::{RunEngine, RunReport};
+use crate::scheduler::{Scheduler, StagePlan};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StepsCounting {
@@ -29,8 +30,8 @@ pub struct DryRunReport {
     /// journeys picked count total
     journeys: i32,
     steps: StepsCounting,
-    endpoints: BTreeMap<String, i32>
-
+    endpoints: BTreeMap<String, i32>,
+    stages: Vec<StagePlan>,
 }
 
 pub async fn dry_run(plan: ExecutionPlan, iterations: u32, seed: u32, mode: DryRunMode<'_>, sink: EventSink<Event>) -> DryRunResult {
@@ -52,7 +53,8 @@ pub fn dry_run_plan_only(mut plan: ExecutionPlan, iterations: u32, seed: u32) ->
         seed,
         journeys: 0,
         steps: StepsCounting{ request_count: 0, sleep_count: 0 },
-        endpoints: BTreeMap::new()
+        endpoints: BTreeMap::new(),
+        stages: Scheduler::new(&plan.workload).stage_plans(),
     };
 
     let sampler = &mut plan.weight_sampler;
@@ -1,40 +1,99 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use tokio::sync::mpsc::UnboundedSender;
 use crate::scheduler::Tick;
+use crate::trace_context::RequestSpan;
+use crate::vu_runner::ResponseResult;
 
 #[derive(Clone)]
 pub enum Event {
     TickExecuted { tick: Tick },
-    RequestFinished { ok: bool, latency_ms: u32 },
+    /// `error` is `None` for successful requests, see `ResponseResult::error_label`.
+    /// `status` is `None` when no response arrived
+    RequestFinished {
+        ok: bool,
+        latency_ms: u32,
+        error: Option<String>,
+        endpoint: String,
+        status: Option<u16>,
+        journey: String,
+        stage_index: u64,
+        /// Sampled requests of a scenario with `tracing.traceparent`
+        span: Option<RequestSpan>,
+    },
     InFlight { value: i32 },
+    /// Problems that do not stop the run, e.g. executor errors. Shown by the UI, which owns the
+    /// terminal while the run lasts
+    Warning { message: String },
     RunFinished,
 }
 
+impl Event {
+    pub(crate) fn request_finished(res: &ResponseResult) -> Self {
+        Event::RequestFinished {
+            ok: res.ok,
+            latency_ms: res.latency_ms as u32,
+            error: res.error_label(),
+            endpoint: res.endpoint_key.clone(),
+            status: res.status,
+            journey: res.journey_name.clone(),
+            stage_index: res.stage_index,
+            span: res.span,
+        }
+    }
+}
+
+/// Ends a run early, e.g. on Ctrl-C in the dashboard: no new requests start, the ones in flight
+/// are drained and the report covers what ran
+#[derive(Clone, Default)]
+pub struct StopSignal {
+    stopped: Arc<AtomicBool>,
+}
+
+impl StopSignal {
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+}
+
 #[derive(Clone)]
 pub struct EventSink<E> {
-    tx: Option<UnboundedSender<E>>,
+    txs: Vec<UnboundedSender<E>>,
 }
 
-impl<E> EventSink<E> {
+impl<E: Clone> EventSink<E> {
     /// No-op sink
     pub fn noop() -> Self {
-        Self { tx: None }
+        Self { txs: Vec::new() }
     }
 
     /// Real sink
     pub fn new(tx: UnboundedSender<E>) -> Self {
-        Self { tx: Some(tx) }
+        Self { txs: vec![tx] }
+    }
+
+    /// Every event goes to each of `txs`
+    pub fn fanout(txs: Vec<UnboundedSender<E>>) -> Self {
+        Self { txs }
     }
 
     /// Best-effort send
     #[inline]
     pub fn send(&self, ev: E) {
-        if let Some(tx) = &self.tx {
-            let _ = tx.send(ev);
+        if let Some((last, rest)) = self.txs.split_last() {
+            for tx in rest {
+                let _ = tx.send(ev.clone());
+            }
+            let _ = last.send(ev);
         }
     }
 
     #[inline]
     pub fn is_enabled(&self) -> bool {
-        self.tx.is_some()
+        !self.txs.is_empty()
     }
 }
\ No newline at end of file
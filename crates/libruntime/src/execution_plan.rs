@@ -1,14 +1,39 @@
+use crate::data_feeds::DataFeeds;
+use crate::tls::TlsOptions;
+use crate::trace_context::TracingSettings;
 use crate::weight_sampler::WeightSampler;
-use libprotocol::schema::{Journey, Scenario};
+use libprotocol::schema::{Check, Extract, Journey, Scenario, Step, Workload};
+use libprotocol::template::{has_placeholders, Template};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone)]
 pub struct ExecutionPlan {
     pub scenario_name: String,
     pub version: String,
     pub base_url: String,
+    /// `Target.default_headers`, sent with every request unless a step overrides them
+    pub default_headers: BTreeMap<String, String>,
+    pub tls: TlsOptions,
+    pub workload: Workload,
     pub journeys: Vec<libprotocol::schema::Journey>,
+    pub variables: BTreeMap<String, String>,
+    /// Rows of `data_sources`, empty until `DataFeeds::load`
+    pub data: DataFeeds,
+    /// Patterns used by the journeys, compiled once
+    pub regexes: BTreeMap<String, Regex>,
+    /// Request paths, header values and bodies with placeholders, parsed once
+    pub templates: BTreeMap<String, Template>,
     pub weight_sampler: WeightSampler,
+    /// Seed of `{{uuid}}`, `{{random_int}}` and random data rows. Differs on every run unless
+    /// it is set from an explicit seed, see `libruntime::plan`
+    pub random_seed: String,
     pub limits: Option<String>,
+    /// Width of the report time series buckets
+    pub timeseries_interval_ms: u64,
+    pub tracing: TracingSettings,
 }
 
 impl From<&Scenario> for ExecutionPlan {
@@ -18,9 +43,19 @@ impl From<&Scenario> for ExecutionPlan {
             scenario_name: scenario.name.to_string(),
             version: scenario.version.to_string(),
             base_url: scenario.target.base_url.to_string(),
+            default_headers: scenario.target.default_headers.clone().unwrap_or_default(),
+            tls: TlsOptions::from(&scenario.target),
+            workload: scenario.workload.clone(),
             journeys: scenario.journeys.clone(),
+            variables: scenario.variables.clone().unwrap_or_default(),
+            data: DataFeeds::default(),
+            regexes: compile_regexes(&scenario.journeys),
+            templates: parse_templates(scenario),
             weight_sampler: WeightSampler::from(scenario),
+            random_seed: run_seed(),
             limits: None,
+            timeseries_interval_ms: scenario.report.clone().unwrap_or_default().timeseries_interval_ms(),
+            tracing: TracingSettings::from(scenario.tracing.as_ref()),
         }
     }
 }
@@ -29,6 +64,58 @@ impl ExecutionPlan {
     pub fn get_journey(&self, id: i32) -> &Journey {
         self.journeys.get(id as usize).unwrap()
     }
+
+    pub fn regex(&self, pattern: &str) -> Option<Regex> {
+        match self.regexes.get(pattern) {
+            Some(re) => Some(re.clone()),
+            None => Regex::new(pattern).ok(),
+        }
+    }
+}
+
+fn compile_regexes(journeys: &[Journey]) -> BTreeMap<String, Regex> {
+    journeys.iter()
+        .flat_map(|journey| journey.steps.iter())
+        .flat_map(|step| match step {
+            Step::Request { extract, checks, .. } => {
+                let extract = extract.iter().flatten().filter_map(|rule| match rule {
+                    Extract::Regex { pattern, .. } => Some(pattern),
+                    _ => None,
+                });
+                let checks = checks.iter().flatten().filter_map(|check| match check {
+                    Check::BodyRegex { pattern } => Some(pattern),
+                    _ => None,
+                });
+                extract.chain(checks).collect()
+            }
+            _ => Vec::new(),
+        })
+        .filter_map(|pattern| Regex::new(pattern).ok().map(|re| (pattern.clone(), re)))
+        .collect()
+}
+
+fn parse_templates(scenario: &Scenario) -> BTreeMap<String, Template> {
+    let steps = scenario.journeys.iter()
+        .flat_map(|journey| journey.steps.iter())
+        .flat_map(|step| match step {
+            Step::Request { path, headers, body, .. } => std::iter::once(path)
+                .chain(headers.iter().flat_map(|h| h.values()))
+                .chain(body)
+                .collect(),
+            _ => Vec::new(),
+        });
+    scenario.target.default_headers.iter()
+        .flat_map(|headers| headers.values())
+        .chain(steps)
+        .filter(|input| has_placeholders(input))
+        .filter_map(|input| Template::parse(input).ok().map(|template| (input.clone(), template)))
+        .collect()
+}
+
+fn run_seed() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let key = format!("{}-{}", now, std::process::id());
+    Sha256::digest(key.as_bytes())[0..8].iter().map(|b| format!("{:02x}", b)).collect()
 }
 
 pub(crate) fn calculate_cumulative_ends(weights: &mut [u16]) -> Vec<u32> {
@@ -44,7 +131,7 @@ pub(crate) fn calculate_cumulative_ends(weights: &mut [u16]) -> Vec<u32> {
 
 #[cfg(test)]
 mod tests {
-    use crate::execution_plan::{calculate_cumulative_ends, ExecutionPlan};
+    use crate::execution_plan::calculate_cumulative_ends;
     use libprotocol::Scenario;
     use std::path::PathBuf;
 
@@ -57,10 +144,8 @@ mod tests {
 
     #[test]
     fn it_create_execution_plan_for_scenario() {
-        let path = fixture_path("weight-sampler-test.json");
-        let content = std::fs::read_to_string(&path).unwrap();
-        let scenario: Scenario = serde_json::from_str(&content).unwrap();
-        let execution_plan = ExecutionPlan::from(&scenario);
+        let scenario: Scenario = libprotocol::parse_scenario(fixture_path("weight-sampler-test.json"));
+        let execution_plan = crate::plan(&scenario, 0);
         insta::assert_debug_snapshot!(execution_plan);
     }
 
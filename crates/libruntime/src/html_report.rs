@@ -0,0 +1,299 @@
+use crate::run_engine::{LatencySummary, Requests, RunReport};
+use std::fmt::Write;
+
+/// One color per series of a chart, in order
+const PALETTE: [&str; 4] = ["#4e79a7", "#f28e2b", "#e15759", "#76b7b2"];
+
+const CHART_WIDTH: u32 = 760;
+const LABEL_WIDTH: u32 = 240;
+const BAR_HEIGHT: u32 = 14;
+const GROUP_GAP: u32 = 10;
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2rem auto;max-width:880px;color:#222}\
+h1{margin-bottom:.2rem}h2{margin-top:2.5rem;border-bottom:1px solid #ddd;padding-bottom:.3rem}\
+.meta{color:#666}.cards{display:flex;flex-wrap:wrap;gap:.8rem;margin-top:1.5rem}\
+.card{border:1px solid #ddd;border-radius:6px;padding:.6rem 1rem;min-width:120px}\
+.card b{display:block;font-size:1.4rem}table{border-collapse:collapse;width:100%;margin-top:1rem}\
+th,td{text-align:left;padding:.3rem .6rem;border-bottom:1px solid #eee}td.num,th.num{text-align:right}\
+.fail{color:#c0392b}.pass{color:#27ae60}svg text{font-size:12px}";
+
+/// Renders a saved run report as a self-contained HTML page: inline CSS and SVG charts, no scripts.
+pub fn render(report: &RunReport) -> String {
+    let mut html = String::new();
+    let title = format!("{} v{}", report.scenario.name, report.scenario.version);
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>rload report: {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(&title),
+        STYLE
+    );
+    let _ = writeln!(html, "<h1>{}</h1>", escape(&title));
+    let _ = writeln!(
+        html,
+        "<p class=\"meta\">{} model, seed {}, planned {} s, ran {} s</p>",
+        escape(&report.run.mode),
+        escape(&report.run.seed),
+        report.run.duration_sec_planned,
+        report.time.real_time_duration_sec
+    );
+
+    summary(&mut html, report);
+    thresholds(&mut html, report);
+    throughput(&mut html, report);
+    latency(&mut html, report);
+    errors(&mut html, report);
+    journeys(&mut html, report);
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn summary(html: &mut String, report: &RunReport) {
+    let latency = &report.latency_overall_summary;
+    let cards = [
+        ("requests", report.requests.total.to_string()),
+        ("errors", report.requests.error.to_string()),
+        ("error rate", format!("{}%", report.error_and_quality.http_error_rate)),
+        ("planned rps", report.rps.planned_avg.to_string()),
+        ("achieved rps", report.rps.achieved_avg.to_string()),
+        ("p50", format!("{} ms", latency.p50)),
+        ("p95", format!("{} ms", latency.p95)),
+        ("p99", format!("{} ms", latency.p99)),
+        ("corrected p95", format!("{} ms", latency.corrected.p95)),
+        ("corrected p99", format!("{} ms", latency.corrected.p99)),
+    ];
+    html.push_str("<div class=\"cards\">\n");
+    for (label, value) in cards {
+        let _ = writeln!(html, "<div class=\"card\">{}<b>{}</b></div>", label, escape(&value));
+    }
+    html.push_str("</div>\n");
+}
+
+fn thresholds(html: &mut String, report: &RunReport) {
+    let thresholds = report.thresholds();
+    if thresholds.results.is_empty() {
+        return;
+    }
+    let _ = writeln!(html, "<h2>Thresholds: {} of {} failed</h2>", thresholds.failed, thresholds.total);
+    html.push_str("<table>\n<tr><th>rule</th><th class=\"num\">observed</th><th>result</th></tr>\n");
+    for result in &thresholds.results {
+        let (class, verdict) = match result.passed {
+            true => ("pass", "passed"),
+            false => ("fail", "failed"),
+        };
+        let observed = result.observed.map(|v| v.to_string()).unwrap_or_else(|| "no data".to_string());
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"{}\">{}</td></tr>",
+            escape(&result.message),
+            escape(&observed),
+            class,
+            verdict
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+fn throughput(html: &mut String, report: &RunReport) {
+    html.push_str("<h2>Throughput by stage</h2>\n");
+    let groups: Vec<(String, Vec<u64>)> = report.rps.by_stage.values()
+        .map(|stage| (format!("stage {}", stage.stage_index), vec![stage.planned_rps, stage.achieved_rps]))
+        .collect();
+    html.push_str(&bar_chart("rps", &["planned", "achieved"], &groups));
+}
+
+fn latency(html: &mut String, report: &RunReport) {
+    html.push_str("<h2>Latency by stage</h2>\n");
+    let by_stage: Vec<(String, Vec<u64>)> = report.latency_by_stage.iter()
+        .map(|(stage_index, summary)| (format!("stage {}", stage_index), percentiles(summary)))
+        .collect();
+    html.push_str(&bar_chart("ms", &["p50", "p90", "p95", "p99"], &by_stage));
+
+    html.push_str("<h2>Latency by endpoint</h2>\n");
+    let by_endpoint: Vec<(String, Vec<u64>)> = report.by_endpoint.iter()
+        .map(|(endpoint, stats)| (endpoint.clone(), percentiles(&stats.latency_summary)))
+        .collect();
+    html.push_str(&bar_chart("ms", &["p50", "p90", "p95", "p99"], &by_endpoint));
+}
+
+fn percentiles(summary: &LatencySummary) -> Vec<u64> {
+    vec![summary.p50, summary.p90, summary.p95, summary.p99]
+}
+
+fn errors(html: &mut String, report: &RunReport) {
+    let _ = writeln!(html, "<h2>Errors: {} of {} requests</h2>", report.requests.error, report.requests.total);
+    html.push_str("<table>\n<tr><th>endpoint</th><th class=\"num\">requests</th><th class=\"num\">errors</th><th class=\"num\">error rate</th><th>failed checks</th></tr>\n");
+    for (endpoint, stats) in &report.by_endpoint {
+        let failed_checks: Vec<String> = stats.checks.iter()
+            .filter(|(_, counts)| counts.failed > 0)
+            .map(|(check, counts)| format!("{} ({})", check, counts.failed))
+            .collect();
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td>{}</td></tr>",
+            escape(endpoint),
+            stats.request.total,
+            stats.request.error,
+            error_rate(&stats.request),
+            escape(&failed_checks.join(", "))
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+fn journeys(html: &mut String, report: &RunReport) {
+    html.push_str("<h2>Journeys</h2>\n");
+    let groups: Vec<(String, Vec<u64>)> = report.by_journey.iter()
+        .map(|journey| (journey.key.clone(), vec![journey.request.ok, journey.request.error]))
+        .collect();
+    html.push_str(&bar_chart("requests", &["ok", "error"], &groups));
+
+    html.push_str("<table>\n<tr><th>journey</th><th class=\"num\">VUs</th><th class=\"num\">requests</th><th class=\"num\">share</th><th class=\"num\">error rate</th><th class=\"num\">p95</th></tr>\n");
+    for journey in &report.by_journey {
+        let share = match report.requests.total {
+            0 => 0.0,
+            total => journey.request.total as f64 * 100.0 / total as f64,
+        };
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{:.1}%</td><td class=\"num\">{}</td><td class=\"num\">{} ms</td></tr>",
+            escape(&journey.key),
+            journey.per_vu,
+            journey.request.total,
+            share,
+            error_rate(&journey.request),
+            journey.latency_summary.p95
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+fn error_rate(requests: &Requests) -> String {
+    match requests.total {
+        0 => "-".to_string(),
+        total => format!("{:.2}%", requests.error as f64 * 100.0 / total as f64),
+    }
+}
+
+/// Horizontal grouped bars: one group per label, one bar per series, scaled to the largest value
+fn bar_chart(unit: &str, series: &[&str], groups: &[(String, Vec<u64>)]) -> String {
+    if groups.is_empty() {
+        return "<p class=\"meta\">no data</p>\n".to_string();
+    }
+    let max = groups.iter().flat_map(|(_, values)| values).copied().max().unwrap_or(0).max(1);
+    let legend_height = 24;
+    let group_height = BAR_HEIGHT * series.len() as u32 + GROUP_GAP;
+    let height = legend_height + group_height * groups.len() as u32;
+    // leave room for the value after the longest bar
+    let scale = (CHART_WIDTH - LABEL_WIDTH - 70) as f64 / max as f64;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" role=\"img\">",
+        w = CHART_WIDTH,
+        h = height
+    );
+    for (i, name) in series.iter().enumerate() {
+        let x = LABEL_WIDTH + i as u32 * 100;
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{}\" y=\"4\" width=\"12\" height=\"12\" fill=\"{}\"/><text x=\"{}\" y=\"14\">{}</text>",
+            x,
+            PALETTE[i % PALETTE.len()],
+            x + 16,
+            escape(name)
+        );
+    }
+    for (g, (label, values)) in groups.iter().enumerate() {
+        let top = legend_height + g as u32 * group_height;
+        let _ = writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+            LABEL_WIDTH - 8,
+            top + group_height / 2,
+            escape(&truncate(label, 36))
+        );
+        for (i, value) in values.iter().enumerate() {
+            let y = top + i as u32 * BAR_HEIGHT;
+            let width = (*value as f64 * scale).round() as u32;
+            let _ = writeln!(
+                svg,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"><title>{} {}: {} {}</title></rect><text x=\"{}\" y=\"{}\">{}</text>",
+                LABEL_WIDTH,
+                y,
+                width,
+                BAR_HEIGHT - 2,
+                PALETTE[i % PALETTE.len()],
+                escape(label),
+                escape(series.get(i).unwrap_or(&"")),
+                value,
+                unit,
+                LABEL_WIDTH + width + 4,
+                y + BAR_HEIGHT - 3,
+                value
+            );
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn truncate(label: &str, max_chars: usize) -> String {
+    match label.chars().count() > max_chars {
+        true => format!("{}…", label.chars().take(max_chars - 1).collect::<String>()),
+        false => label.to_string(),
+    }
+}
+
+/// Also valid for XML text and attributes
+pub(crate) fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::html_report::{bar_chart, escape, render};
+    use crate::run_engine::RunReport;
+    use crate::test_runs::{mock_report, RUN_ENGINE_FIXTURE};
+
+    #[test]
+    fn it_draws_grouped_bars() {
+        let groups = vec![
+            ("stage 0".to_string(), vec![100, 50]),
+            ("GET /a?b=<c>".to_string(), vec![0, 200]),
+        ];
+        insta::assert_snapshot!(bar_chart("rps", &["planned", "achieved"], &groups));
+    }
+
+    #[test]
+    fn it_escapes_html() {
+        assert_eq!("GET /q?a=1&amp;b=&lt;x&gt; &quot;y&quot;", escape("GET /q?a=1&b=<x> \"y\""));
+    }
+
+    #[tokio::test]
+    async fn it_renders_a_saved_report() {
+        let report = mock_report(RUN_ENGINE_FIXTURE).await;
+
+        let saved: RunReport = serde_json::from_str(&serde_json::to_string(&report).unwrap()).unwrap();
+        let html = render(&saved);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        for section in ["Throughput by stage", "Latency by stage", "Latency by endpoint", "Errors: 0 of", "Journeys"] {
+            assert!(html.contains(section), "missing {}", section);
+        }
+        assert!(html.contains("default A"), "journey missing");
+        assert!(!html.contains("<script"), "page must be self-contained");
+    }
+}
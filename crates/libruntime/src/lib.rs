@@ -3,17 +3,39 @@ mod weight_sampler;
 mod dry_runner;
 pub mod scheduler;
 mod vu_runner;
+mod templating;
+pub mod data_feeds;
+mod response;
+pub mod tls;
 mod run_engine;
+mod constant_vus;
 mod metrics;
+mod raw_results;
+mod timeseries;
+#[cfg(test)]
+mod test_runs;
+pub mod trace_context;
 pub mod events;
+pub mod outputs;
+pub mod thresholds;
+pub mod html_report;
+pub mod compare;
+pub mod report;
 
 use crate::execution_plan::ExecutionPlan;
 pub use libprotocol::schema::Journey;
-use libprotocol::Scenario;
+use libprotocol::{Overrides, ProtocolError, Scenario, ScenarioFormat};
 use std::path::Path;
 use std::sync::Arc;
 use crate::dry_runner::DryRunMode;
-use crate::events::{Event, EventSink};
+use crate::events::{Event, EventSink, StopSignal};
+use crate::scheduler::Scheduler;
+pub use crate::run_engine::RunReport;
+use crate::outputs::{OutputError, OutputTarget};
+use crate::raw_results::RawResults;
+use crate::tls::ClientConfigError;
+use crate::trace_context::TracingSettings;
+use crate::data_feeds::{DataFeeds, DataSourceError};
 
 pub struct AppContext {
     pub scenario: Arc<Scenario>
@@ -22,30 +44,118 @@ pub struct AppContext {
 pub fn plan(scenario: &Scenario, seed: u32) -> ExecutionPlan {
     let mut plan = ExecutionPlan::from(scenario);
     plan.weight_sampler.seed = seed.to_string();
+    plan.random_seed = seed.to_string();
 
     plan
 }
-pub async fn dry_run(scenario_path: impl AsRef<Path>, seed: u32, iterations: u32, is_simulated: bool, sink: EventSink<Event>) {
-
-    let scenario: &Scenario = &libprotocol::parse_scenario(&scenario_path);
+pub async fn dry_run(scenario_path: impl AsRef<Path>, format: ScenarioFormat, overrides: &Overrides, seed: u32, iterations: u32, is_simulated: bool, print_plan: Option<u32>) -> Result<(), RunError> {
+
+    libprotocol::validate_with(&scenario_path, format, overrides)?;
+    let scenario: &Scenario = &libprotocol::read_scenario(&scenario_path, format, overrides)?;
+    if let Some(limit_steps) = print_plan {
+        let scheduler = &mut Scheduler::with_seed(&scenario.workload, &seed.to_string());
+        for tick in scheduler.take(limit_steps as usize) {
+            println!("{}", serde_json::to_string(&tick).unwrap());
+        }
+    }
     let mode = match is_simulated {
         true => DryRunMode::Simulated(scenario),
         false => DryRunMode::PlanOnly
     };
-    libprotocol::validate(&scenario_path).expect("scenario must be valid");
-    let report = dry_runner::dry_run(ExecutionPlan::from(scenario), iterations, seed, mode, sink).await;
+    let mut execution_plan = plan(scenario, seed);
+    let dir = scenario_path.as_ref().parent().unwrap_or(Path::new(""));
+    execution_plan.data = DataFeeds::load(scenario, dir)?;
+    let report = dry_runner::dry_run(execution_plan, iterations, seed, mode, EventSink::noop()).await;
+
+    println!("{:?}", report);
+    Ok(())
+}
+
+/// The run could not start, or its raw results could not be written.
+#[derive(Debug)]
+pub enum RunError {
+    Scenario(ProtocolError),
+    Client(ClientConfigError),
+    Data(DataSourceError),
+    Output(OutputError),
+}
 
-    println!("{:?}", report)
+impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunError::Scenario(e) => e.fmt(f),
+            RunError::Client(e) => e.fmt(f),
+            RunError::Data(e) => e.fmt(f),
+            RunError::Output(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for RunError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RunError::Scenario(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
-pub async fn run(scenario_path: impl AsRef<Path>, is_mock: Option<bool>, sink: EventSink<Event>) {
-    let scenario = &libprotocol::parse_scenario(&scenario_path);
-    let execution_plan: ExecutionPlan = ExecutionPlan::from(scenario);
+impl From<ProtocolError> for RunError {
+    fn from(e: ProtocolError) -> Self {
+        RunError::Scenario(e)
+    }
+}
 
-    let report = run_engine::RunEngine::new(Some(is_mock.unwrap()), Some(true))
-        .run(&execution_plan, scenario, sink).await;
+impl From<ClientConfigError> for RunError {
+    fn from(e: ClientConfigError) -> Self {
+        RunError::Client(e)
+    }
+}
+
+impl From<DataSourceError> for RunError {
+    fn from(e: DataSourceError) -> Self {
+        RunError::Data(e)
+    }
+}
+
+impl From<OutputError> for RunError {
+    fn from(e: OutputError) -> Self {
+        RunError::Output(e)
+    }
+}
+
+/// `outputs` are the `--out` targets: `OutputTarget::Raw` is written by the run itself, the others
+/// are fed from `sink`. A `stop` ends the run early and still returns the report of what ran
+pub async fn run(scenario_path: impl AsRef<Path>, format: ScenarioFormat, overrides: &Overrides, is_mock: Option<bool>, sink: EventSink<Event>, outputs: &[OutputTarget], stop: StopSignal) -> Result<RunReport, RunError> {
+    let scenario = &libprotocol::read_scenario(&scenario_path, format, overrides)?;
+    let mut execution_plan: ExecutionPlan = ExecutionPlan::from(scenario);
+    let exports_spans = outputs.iter().any(|output| matches!(output, OutputTarget::Otlp(_)));
+    execution_plan.tracing = TracingSettings::resolve(scenario.tracing.as_ref(), exports_spans);
+    let dir = scenario_path.as_ref().parent().unwrap_or(Path::new(""));
+    execution_plan.tls.resolve_paths(dir);
+    execution_plan.data = DataFeeds::load(scenario, dir)?;
+    // fail before the first tick instead of inside the executor
+    if is_mock != Some(true) {
+        tls::build_client(&execution_plan.tls)?;
+    }
+
+    let mut engine = run_engine::RunEngine::new(Some(is_mock.unwrap()), Some(true)).with_stop(stop);
+    let mut raw_writer = None;
+    if let Some(path) = outputs::raw_results(outputs)? {
+        let (raw, writer) = RawResults::create(path).await?;
+        engine = engine.with_raw_results(raw);
+        raw_writer = Some(writer);
+    }
+
+    let report = engine.run(&execution_plan, scenario, sink).await;
+
+    // the writer finishes once the engine has dropped its sender
+    drop(engine);
+    if let Some(writer) = raw_writer {
+        writer.await.map_err(|e| OutputError::new(format!("raw results writer: {}", e)))??;
+    }
 
-    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    Ok(report)
 }
 
 #[cfg(test)]
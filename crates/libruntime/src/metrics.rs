@@ -1,17 +1,47 @@
-use crate::run_engine::{ByStage, EndpointStats, HIGHEST_US, LOWEST_US, SIGFIG};
+use crate::run_engine::{ByStage, EndpointStats, Requests, HIGHEST_US, LOWEST_US, SIGFIG};
+use crate::timeseries::TimeSeries;
 use crate::vu_runner::ResponseResult;
 use std::collections::BTreeMap;
 use hdrhistogram::Histogram;
 
+/// Latency of the same requests measured two ways: service time from the actual send, and from the
+/// intended start (the tick's `planned_at_ms`). The second one is corrected for coordinated omission.
+#[derive(Debug, Clone)]
+pub struct LatencyHistograms {
+    pub service: Histogram<u64>,
+    pub corrected: Histogram<u64>,
+}
+
+impl LatencyHistograms {
+    pub fn new() -> Self {
+        let histogram = || Histogram::new_with_bounds(LOWEST_US, HIGHEST_US, SIGFIG).expect("latency histogram creation failed");
+        LatencyHistograms { service: histogram(), corrected: histogram() }
+    }
+
+    /// One sample per request in each histogram, latencies above `HIGHEST_US` are recorded as `HIGHEST_US`
+    pub fn record(&mut self, res: &ResponseResult) {
+        self.service.saturating_record(res.latency_us);
+        // measured from the tick, a request sent late behind a stalled one carries the stall in its delay
+        self.corrected.saturating_record(res.latency_us.saturating_add(res.start_delay_us));
+    }
+}
+
+impl Default for LatencyHistograms {
+    fn default() -> Self {
+        LatencyHistograms::new()
+    }
+}
+
 #[derive(Debug)]
 pub struct MetricsAggregator {
     pub total_requests: u64,
     pub ok_requests: u64,
     pub error_requests: u64,
 
-    pub overall_latency: Histogram<u64>,
-    pub latency_by_stage: BTreeMap<u64, Histogram<u64>>,
-    pub latency_by_endpoint: BTreeMap<String, Histogram<u64>>,
+    pub overall_latency: LatencyHistograms,
+    pub latency_by_stage: BTreeMap<u64, LatencyHistograms>,
+    pub latency_by_endpoint: BTreeMap<String, LatencyHistograms>,
+    pub latency_by_journey: BTreeMap<String, LatencyHistograms>,
     pub latency: Vec<u64>,
     pub latency_min: u64,
     pub latency_max:u64,
@@ -19,14 +49,15 @@ pub struct MetricsAggregator {
 
     ///  map endpoint_key → count, ok, err, latency_sum
     pub by_endpoint: BTreeMap<String, EndpointStats>,
-    /// map journey_name → count
-    pub by_journey: BTreeMap<String, (usize, u64)>,
+    /// map journey_name → journey_id, requests
+    pub by_journey: BTreeMap<String, (usize, Requests)>,
 
-    pub by_stage: BTreeMap<u64, ByStage>
+    pub by_stage: BTreeMap<u64, ByStage>,
+    pub(crate) timeseries: TimeSeries,
 }
 
 impl MetricsAggregator {
-    pub fn new() -> Self{
+    pub fn with_timeseries_interval_ms(interval_ms: u64) -> Self {
         Self {
             total_requests: 0,
             ok_requests: 0,
@@ -38,16 +69,14 @@ impl MetricsAggregator {
             by_endpoint: BTreeMap::new(),
             by_stage: BTreeMap::new(),
             by_journey: BTreeMap::new(),
-            overall_latency: Histogram::new_with_bounds(LOWEST_US, HIGHEST_US, SIGFIG).expect("histogram over_all creation failed"),
+            overall_latency: LatencyHistograms::new(),
             latency_by_stage: BTreeMap::new(),
             latency_by_endpoint: Default::default(),
+            latency_by_journey: Default::default(),
+            timeseries: TimeSeries::new(interval_ms),
         }
     }
 
-    pub fn record_overall_latency(&mut self, latency_us: u64) {
-        self.overall_latency.record(latency_us).expect("failed to record latency");
-    }
-
     pub fn consume(&mut self, request_event: ResponseResult, now_ms: u64) {
         self.total_requests += 1;
 
@@ -67,36 +96,38 @@ impl MetricsAggregator {
 
         self.latency.push(request_event.latency_ms);
 
-        self.record_overall_latency(request_event.latency_us);
+        self.overall_latency.record(&request_event);
+        self.timeseries.record(now_ms, request_event.latency_ms, request_event.ok);
 
-        self.by_endpoint.entry(request_event.endpoint_key.clone()).and_modify(|endpoint_metrics| {
-            endpoint_metrics.request.total +=1;
-            if endpoint_metrics.request.total == 1 {
-                endpoint_metrics.first_at_ms = now_ms;
-            }
-            endpoint_metrics.last_at_ms = now_ms;
-            let window_ms = endpoint_metrics
-                .last_at_ms
-                .saturating_sub(endpoint_metrics.first_at_ms)
-                .max(1);
-            endpoint_metrics.achieved_rps =
-                (endpoint_metrics.request.total as f64 / (window_ms as f64 / 1000.0)).round();
-
-            endpoint_metrics.count += 1;
-            if request_event.ok {
-                endpoint_metrics.request.ok +=1;
-            } else {
-                endpoint_metrics.request.error +=1;
+        let endpoint_metrics = self.by_endpoint.entry(request_event.endpoint_key.clone())
+            .or_insert_with(EndpointStats::default);
+        endpoint_metrics.request.total +=1;
+        if endpoint_metrics.request.total == 1 {
+            endpoint_metrics.first_at_ms = now_ms;
+        }
+        endpoint_metrics.last_at_ms = now_ms;
+        let window_ms = endpoint_metrics
+            .last_at_ms
+            .saturating_sub(endpoint_metrics.first_at_ms)
+            .max(1);
+        endpoint_metrics.achieved_rps =
+            (endpoint_metrics.request.total as f64 / (window_ms as f64 / 1000.0)).round();
+
+        endpoint_metrics.count += 1;
+        if request_event.ok {
+            endpoint_metrics.request.ok +=1;
+        } else {
+            endpoint_metrics.request.error +=1;
+        }
+        for (label, passed) in &request_event.checks {
+            let counts = endpoint_metrics.checks.entry(label.clone()).or_default();
+            match passed {
+                true => counts.passed += 1,
+                false => counts.failed += 1,
             }
-        }).or_insert(EndpointStats::default());
+        }
 
-        self.latency_by_endpoint.entry(request_event.endpoint_key).and_modify(|hist| {
-            hist.record(request_event.latency_us).expect("cant crate record in hist for by endpoint");
-        }).or_insert({
-            let mut hist = Histogram::new_with_bounds(LOWEST_US, HIGHEST_US, SIGFIG).expect("histogram by endpoint creation failed");
-            hist.record(request_event.latency_us).expect("cant crate record in hist for by endpoint");
-            hist
-        });
+        self.latency_by_endpoint.entry(request_event.endpoint_key.clone()).or_default().record(&request_event);
 
         self.by_stage.entry(request_event.stage_index).and_modify(|stage_rps| {
             stage_rps.request_count += 1;
@@ -106,21 +137,95 @@ impl MetricsAggregator {
         }).or_insert(
             ByStage {
                 stage_index: request_event.stage_index,
+                planned_rps: 0,
                 achieved_rps: 0,
                 request_count: 1,
                 stage_started_ms: request_event.stage_start_ms,
                 stage_duration_ms: 0,
             }
         );
-        self.latency_by_stage.entry(request_event.stage_index).and_modify(|hist| {
-            hist.record(request_event.latency_us).expect("cant crate record in hist for by stage");
-        }).or_insert({
-            let mut hist = Histogram::new_with_bounds(LOWEST_US, HIGHEST_US, SIGFIG).expect("histogram by stage creation failed");
-            hist.record(request_event.latency_us).expect("cant crate record in hist for by endpoint");
-            hist
-        });
-        
-        self.by_journey.entry(request_event.journey_name).and_modify(|(_journey_id, journey_count)| *journey_count += 1)
-            .or_insert((request_event.journey_id as usize, 1));
+        self.latency_by_stage.entry(request_event.stage_index).or_default().record(&request_event);
+        self.latency_by_journey.entry(request_event.journey_name.clone()).or_default().record(&request_event);
+
+        let (_journey_id, journey_requests) = self.by_journey.entry(request_event.journey_name)
+            .or_insert((request_event.journey_id as usize, Requests::default()));
+        journey_requests.total += 1;
+        if request_event.ok {
+            journey_requests.ok += 1;
+        } else {
+            journey_requests.error += 1;
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use crate::metrics::LatencyHistograms;
+    use crate::run_engine::{LatencySummary, HIGHEST_US};
+    use crate::vu_runner::{ErrorType, RequestSpec, ResponseResult};
+    use libprotocol::schema::StepMethod;
+
+    fn response(latency_ms: u64, planned_at_ms: u64, started_ms: u64) -> ResponseResult {
+        let request = RequestSpec {
+            method: StepMethod::GET,
+            path: "/".to_string(),
+            headers: vec![],
+            body: None,
+            extract: vec![],
+            checks: vec![],
+            endpoint_key: "GET /".to_string(),
+            timeout_ms: 0,
+            journey_id: 0,
+            journey_name: "default".to_string(),
+            step_index: 0,
+            stage_start_ms: 0,
+            stage_index: 0,
+        };
+        let mut res = ResponseResult::error(&request, ErrorType::Other);
+        res.latency_us = latency_ms * 1000;
+        res.schedule(planned_at_ms, started_ms);
+        res
+    }
+
+    #[test]
+    fn it_corrects_for_coordinated_omission() {
+        let mut histograms = LatencyHistograms::new();
+        // the target stalls for 1s on the first request, the next ones are sent late
+        histograms.record(&response(1000, 0, 0));
+        for i in 1..100 {
+            histograms.record(&response(10, i * 10, 1000));
+        }
+
+        let summary = LatencySummary::summarize(&histograms);
+
+        assert_eq!(100, summary.count);
+        assert_eq!(10, summary.p90);
+        assert_eq!(100, summary.corrected.count);
+        assert!(summary.corrected.p50 >= 500, "{:?}", summary);
+        assert_eq!(summary.max, summary.corrected.max);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn it_does_not_correct_requests_sent_on_time() {
+        let mut histograms = LatencyHistograms::new();
+        histograms.record(&response(1000, 0, 0));
+
+        let summary = LatencySummary::summarize(&histograms);
+
+        assert_eq!(1, summary.corrected.count);
+        assert_eq!(summary.p99, summary.corrected.p99);
+    }
+
+    #[test]
+    fn it_records_latencies_above_the_highest_trackable_value() {
+        let mut histograms = LatencyHistograms::new();
+        let mut res = response(0, 0, 0);
+        res.latency_us = HIGHEST_US * 2;
+        histograms.record(&res);
+
+        let summary = LatencySummary::summarize(&histograms);
+
+        assert_eq!(1, summary.count);
+        assert_eq!(1, summary.corrected.count);
+        assert!(summary.max >= HIGHEST_US / 1000, "{:?}", summary);
+    }
+}
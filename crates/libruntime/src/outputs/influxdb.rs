@@ -0,0 +1,176 @@
+use crate::outputs::{http_client, Batch, OutputError, OutputSink, Window};
+use async_trait::async_trait;
+use std::fmt::Write;
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+
+const MEASUREMENT: &str = "rload_requests";
+
+/// Sent as `Authorization: Token <value>` when set
+pub const TOKEN_ENV: &str = "INFLUXDB_TOKEN";
+
+/// Writes line protocol to an InfluxDB write endpoint, v1 `/write?db=` or v2 `/api/v2/write?org=&bucket=`.
+pub struct InfluxDbHttpSink {
+    client: reqwest::Client,
+    url: String,
+    token: Option<String>,
+}
+
+impl InfluxDbHttpSink {
+    pub fn new(url: &str) -> Result<Self, OutputError> {
+        reqwest::Url::parse(url).map_err(|e| OutputError::new(format!("influxdb url '{}': {}", url, e)))?;
+        Ok(InfluxDbHttpSink {
+            client: http_client()?,
+            url: url.to_string(),
+            token: std::env::var(TOKEN_ENV).ok(),
+        })
+    }
+}
+
+#[async_trait]
+impl OutputSink for InfluxDbHttpSink {
+    async fn flush(&mut self, scenario: &str, batch: &Batch) -> Result<(), OutputError> {
+        let mut request = self.client.post(&self.url).body(lines(scenario, &batch.series, batch.end_unix_ns));
+        if let Some(token) = &self.token {
+            request = request.header(reqwest::header::AUTHORIZATION, format!("Token {}", token));
+        }
+        let response = request.send().await
+            .map_err(|e| OutputError::new(format!("influxdb write to {}: {}", self.url, e)))?;
+        if !response.status().is_success() {
+            return Err(OutputError::new(format!("influxdb write to {}: status {}", self.url, response.status())));
+        }
+        Ok(())
+    }
+}
+
+/// Appends line protocol to a file, truncated when the run starts
+pub struct InfluxDbFileSink {
+    file: File,
+}
+
+impl InfluxDbFileSink {
+    pub async fn create(path: &Path) -> Result<Self, OutputError> {
+        let file = File::create(path).await
+            .map_err(|e| OutputError::new(format!("influxdb file {}: {}", path.display(), e)))?;
+        Ok(InfluxDbFileSink { file })
+    }
+}
+
+#[async_trait]
+impl OutputSink for InfluxDbFileSink {
+    async fn flush(&mut self, scenario: &str, batch: &Batch) -> Result<(), OutputError> {
+        self.file.write_all(lines(scenario, &batch.series, batch.end_unix_ns).as_bytes()).await
+            .and(self.file.flush().await)
+            .map_err(|e| OutputError::new(format!("influxdb file: {}", e)))
+    }
+}
+
+/// One point per series, every point of a flush is stamped with the flush time
+pub(crate) fn lines(scenario: &str, window: &Window, timestamp_ns: u64) -> String {
+    let mut out = String::new();
+    for (key, series) in window {
+        let _ = writeln!(
+            out,
+            "{},scenario={},journey={},endpoint={},stage={} count={}i,errors={}i,latency_mean_ms={},latency_p95_ms={}i,latency_max_ms={}i {}",
+            MEASUREMENT,
+            escape_tag(scenario),
+            escape_tag(&key.journey),
+            escape_tag(&key.endpoint),
+            key.stage_index,
+            series.count(),
+            series.error,
+            series.mean_ms(),
+            series.percentile_ms(0.95),
+            series.max_ms(),
+            timestamp_ns,
+        );
+    }
+    out
+}
+
+/// Commas, equal signs and spaces are escaped in tag values; an empty value would drop the point
+fn escape_tag(value: &str) -> String {
+    if value.is_empty() {
+        return "none".to_string();
+    }
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, ',' | '=' | ' ' | '\\') {
+            out.push('\\');
+        }
+        match c {
+            '\n' => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::outputs::influxdb::{lines, InfluxDbFileSink, InfluxDbHttpSink};
+    use crate::outputs::{Batch, OutputSink, SeriesKey, SeriesWindow, Window};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn batch(series: Window) -> Batch {
+        Batch { series, ..Batch::default() }
+    }
+
+    fn window() -> Window {
+        let mut window = Window::new();
+        window.insert(
+            SeriesKey { journey: "browse, buy".to_string(), endpoint: "GET /users/{{id}}".to_string(), stage_index: 1 },
+            SeriesWindow { ok: 2, error: 1, latencies_ms: vec![10, 20, 60] },
+        );
+        window
+    }
+
+    #[test]
+    fn it_writes_line_protocol() {
+        assert_eq!(
+            "rload_requests,scenario=demo\\ run,journey=browse\\,\\ buy,endpoint=GET\\ /users/{{id}},stage=1 count=3i,errors=1i,latency_mean_ms=30,latency_p95_ms=60i,latency_max_ms=60i 1700000000000000000\n",
+            lines("demo run", &window(), 1_700_000_000_000_000_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn it_appends_to_file() {
+        let path = std::env::temp_dir().join(format!("rload-influx-{}.lp", std::process::id()));
+        let mut sink = InfluxDbFileSink::create(&path).await.unwrap();
+
+        sink.flush("demo", &batch(window())).await.unwrap();
+        sink.flush("demo", &batch(window())).await.unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(2, content.lines().count());
+        assert!(content.lines().all(|line| line.starts_with("rload_requests,scenario=demo,")), "{}", content);
+    }
+
+    #[tokio::test]
+    async fn it_posts_to_write_endpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/api/v2/write?org=o&bucket=b", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            // the body is the last part of the request
+            while !String::from_utf8_lossy(&request).contains("latency_max_ms") {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            socket.write_all(b"HTTP/1.1 204 No Content\r\ncontent-length: 0\r\n\r\n").await.unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        let mut sink = InfluxDbHttpSink::new(&url).unwrap();
+
+        sink.flush("demo", &batch(window())).await.unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /api/v2/write?org=o&bucket=b HTTP/1.1"), "{}", request);
+        assert!(request.contains("rload_requests,scenario=demo,journey=browse\\,\\ buy"), "{}", request);
+    }
+}
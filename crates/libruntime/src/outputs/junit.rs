@@ -0,0 +1,106 @@
+use crate::html_report::escape;
+use crate::outputs::OutputError;
+use crate::run_engine::RunReport;
+use crate::thresholds::ThresholdResult;
+use std::fmt::Write;
+use std::path::Path;
+
+/// One `<testcase>`, `failure` holds (message, details)
+struct TestCase {
+    classname: String,
+    name: String,
+    failure: Option<(String, String)>,
+}
+
+/// Writes the thresholds and response checks of a finished run as JUnit XML
+pub fn write(path: &Path, report: &RunReport) -> Result<(), OutputError> {
+    std::fs::write(path, render(report))
+        .map_err(|e| OutputError::new(format!("junit file {}: {}", path.display(), e)))
+}
+
+/// One suite for thresholds, one for checks: a testcase per threshold and per endpoint check
+pub(crate) fn render(report: &RunReport) -> String {
+    let scenario = &report.scenario.name;
+    let thresholds: Vec<TestCase> = report.thresholds().results.iter()
+        .map(|result| threshold_case(scenario, result))
+        .collect();
+    let checks: Vec<TestCase> = report.by_endpoint.iter()
+        .flat_map(|(endpoint, stats)| stats.checks.iter().map(move |(check, counts)| {
+            let total = counts.passed + counts.failed;
+            TestCase {
+                classname: format!("{}.checks.{}", scenario, endpoint),
+                name: check.clone(),
+                failure: (counts.failed > 0).then(|| (
+                    format!("{} of {} responses failed {}", counts.failed, total, check),
+                    format!("expected every response of {} to pass {}, observed {} failed, {} passed", endpoint, check, counts.failed, counts.passed),
+                )),
+            }
+        }))
+        .collect();
+
+    let time = report.time.real_time_duration_sec;
+    let tests = thresholds.len() + checks.len();
+    let failures = thresholds.iter().chain(&checks).filter(|case| case.failure.is_some()).count();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(xml, "<testsuites name=\"rload\" tests=\"{}\" failures=\"{}\" time=\"{}\">", tests, failures, time);
+    suite(&mut xml, &format!("{}.thresholds", scenario), &thresholds, time);
+    suite(&mut xml, &format!("{}.checks", scenario), &checks, time);
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+fn threshold_case(scenario: &str, result: &ThresholdResult) -> TestCase {
+    let mut name = format!("{} {} {}", result.metric, result.op, result.value);
+    if let Some(scope) = &result.scope {
+        if let Some(endpoint) = &scope.endpoint {
+            let _ = write!(name, " [endpoint {}]", endpoint);
+        }
+        if let Some(journey) = &scope.journey {
+            let _ = write!(name, " [journey {}]", journey);
+        }
+    }
+    let observed = result.observed.map(|v| v.to_string()).unwrap_or_else(|| "no data".to_string());
+    TestCase {
+        classname: format!("{}.thresholds", scenario),
+        failure: (!result.passed).then(|| (
+            result.message.clone(),
+            format!("expected {} {} {}, observed {}", result.metric, result.op, result.value, observed),
+        )),
+        name,
+    }
+}
+
+fn suite(xml: &mut String, name: &str, cases: &[TestCase], time: u64) {
+    let failures = cases.iter().filter(|case| case.failure.is_some()).count();
+    let _ = writeln!(xml, "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{}\">", escape(name), cases.len(), failures, time);
+    for case in cases {
+        let _ = write!(xml, "    <testcase classname=\"{}\" name=\"{}\"", escape(&case.classname), escape(&case.name));
+        match &case.failure {
+            Some((message, details)) => {
+                let _ = writeln!(xml, ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>", escape(message), escape(details));
+            }
+            None => xml.push_str("/>\n"),
+        }
+    }
+    xml.push_str("  </testsuite>\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::outputs::junit::render;
+    use crate::run_engine::CheckCounts;
+    use crate::test_runs::mock_report;
+
+    #[tokio::test]
+    async fn it_reports_thresholds_and_checks() {
+        let mut report = mock_report("failing-thresholds-scenario.json").await;
+        report.time.real_time_duration_sec = 1; // flaky test
+        // the mock executor does not run checks
+        let checks = &mut report.by_endpoint.get_mut("GET /demo").unwrap().checks;
+        checks.insert("status in [200]".to_string(), CheckCounts { passed: 10, failed: 0 });
+        checks.insert("body_contains \"ok\"".to_string(), CheckCounts { passed: 8, failed: 2 });
+
+        insta::assert_snapshot!(render(&report));
+    }
+}
@@ -0,0 +1,388 @@
+mod influxdb;
+pub mod junit;
+mod otlp;
+mod proto;
+mod statsd;
+
+use crate::events::Event;
+use crate::trace_context::RequestSpan;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use crate::trace_context::unix_ns;
+
+pub use influxdb::{InfluxDbFileSink, InfluxDbHttpSink};
+pub use otlp::OtlpSink;
+pub use statsd::StatsdSink;
+
+/// How often the sinks receive the requests finished since the previous flush
+pub const FLUSH_INTERVAL_MS: u64 = 1000;
+
+/// A destination that does not answer delays the next flushes, not the run
+const HTTP_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const HTTP_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where an output sink writes, parsed from `<kind>=<target>`:
+/// `influxdb=http://host:8086/api/v2/write?org=o&bucket=b`, `influxdb=./run.lp`, `statsd=127.0.0.1:8125`
+/// `otlp=http://collector:4318`, `raw=results.ndjson` or `junit=results.xml`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputTarget {
+    InfluxDbHttp(String),
+    InfluxDbFile(PathBuf),
+    Statsd(String),
+    Otlp(String),
+    /// One record per completed request, CSV for a `.csv` file and NDJSON otherwise.
+    /// Written by the run engine rather than from events, see `libruntime::run`
+    Raw(PathBuf),
+    /// Thresholds and checks as JUnit XML, written from the final report, see `junit::write`
+    Junit(PathBuf),
+}
+
+impl FromStr for OutputTarget {
+    type Err = OutputError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((kind, target)) = s.split_once('=') else {
+            return Err(OutputError::new(format!("expected <kind>=<target>, got '{}'", s)));
+        };
+        if target.is_empty() {
+            return Err(OutputError::new(format!("missing target for '{}'", kind)));
+        }
+        match kind {
+            "influxdb" if target.starts_with("http://") || target.starts_with("https://") => Ok(OutputTarget::InfluxDbHttp(target.to_string())),
+            "influxdb" => Ok(OutputTarget::InfluxDbFile(PathBuf::from(target))),
+            "statsd" => Ok(OutputTarget::Statsd(target.to_string())),
+            "otlp" => Ok(OutputTarget::Otlp(target.to_string())),
+            "raw" => Ok(OutputTarget::Raw(PathBuf::from(target))),
+            "junit" => Ok(OutputTarget::Junit(PathBuf::from(target))),
+            kind => Err(OutputError::new(format!("unknown output '{}', expected influxdb, statsd, otlp, raw or junit", kind))),
+        }
+    }
+}
+
+/// An output could not be set up or written to.
+#[derive(Debug)]
+pub struct OutputError {
+    pub message: String,
+}
+
+impl OutputError {
+    pub(crate) fn new(message: impl Into<String>) -> Self {
+        OutputError { message: message.into() }
+    }
+}
+
+impl std::fmt::Display for OutputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "output: {}", self.message)
+    }
+}
+
+impl std::error::Error for OutputError {}
+
+/// Tags of a series, besides the scenario name
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SeriesKey {
+    pub journey: String,
+    pub endpoint: String,
+    pub stage_index: u64,
+}
+
+/// Requests of a series finished since the previous flush
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SeriesWindow {
+    pub ok: u64,
+    pub error: u64,
+    pub latencies_ms: Vec<u32>,
+}
+
+impl SeriesWindow {
+    pub fn count(&self) -> u64 {
+        self.ok + self.error
+    }
+
+    pub fn mean_ms(&self) -> f64 {
+        match self.latencies_ms.len() {
+            0 => 0.0,
+            n => self.latencies_ms.iter().map(|&l| l as f64).sum::<f64>() / n as f64,
+        }
+    }
+
+    pub fn max_ms(&self) -> u32 {
+        self.latencies_ms.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Nearest-rank percentile, `q` in 0.0..=1.0
+    pub fn percentile_ms(&self, q: f64) -> u32 {
+        let mut sorted = self.latencies_ms.clone();
+        sorted.sort_unstable();
+        match sorted.len() {
+            0 => 0,
+            n => sorted[((q * n as f64).ceil() as usize).clamp(1, n) - 1],
+        }
+    }
+}
+
+pub type Window = BTreeMap<SeriesKey, SeriesWindow>;
+
+/// A sampled request with the tags of its series
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanRecord {
+    pub span: RequestSpan,
+    pub key: SeriesKey,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
+
+/// What happened since the previous flush
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Batch {
+    /// Unix time of the previous flush, or of the start of the run
+    pub start_unix_ns: u64,
+    pub end_unix_ns: u64,
+    pub series: Window,
+    pub spans: Vec<SpanRecord>,
+}
+
+/// A destination for the metrics of a run. Sinks only see aggregated batches,
+/// so a slow destination never holds back the requests.
+#[async_trait]
+pub trait OutputSink: Send {
+    async fn flush(&mut self, scenario: &str, batch: &Batch) -> Result<(), OutputError>;
+}
+
+/// Opens every target before the run starts, so a bad address or path fails early
+pub async fn connect(targets: &[OutputTarget]) -> Result<Vec<Box<dyn OutputSink>>, OutputError> {
+    raw_results(targets)?;
+    let mut sinks: Vec<Box<dyn OutputSink>> = Vec::new();
+    for target in targets {
+        match target {
+            OutputTarget::InfluxDbHttp(url) => sinks.push(Box::new(InfluxDbHttpSink::new(url)?)),
+            OutputTarget::InfluxDbFile(path) => sinks.push(Box::new(InfluxDbFileSink::create(path).await?)),
+            OutputTarget::Statsd(addr) => sinks.push(Box::new(StatsdSink::connect(addr).await?)),
+            OutputTarget::Otlp(endpoint) => sinks.push(Box::new(OtlpSink::new(endpoint)?)),
+            OutputTarget::Raw(_) | OutputTarget::Junit(_) => {}
+        }
+    }
+    Ok(sinks)
+}
+
+/// File of the `raw=` target. The engine streams to a single file, so a second one is an error
+pub fn raw_results(targets: &[OutputTarget]) -> Result<Option<&Path>, OutputError> {
+    let mut paths = targets.iter().filter_map(|target| match target {
+        OutputTarget::Raw(path) => Some(path.as_path()),
+        _ => None,
+    });
+    match (paths.next(), paths.next()) {
+        (Some(first), Some(second)) => Err(OutputError::new(format!(
+            "only one raw= output is supported, got {} and {}", first.display(), second.display()))),
+        (path, _) => Ok(path),
+    }
+}
+
+/// Client of the HTTP sinks
+pub(crate) fn http_client() -> Result<reqwest::Client, OutputError> {
+    reqwest::Client::builder()
+        .connect_timeout(HTTP_CONNECT_TIMEOUT)
+        .timeout(HTTP_REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| OutputError::new(format!("http client: {}", e)))
+}
+
+/// Feeds the sinks from the event stream of a run.
+pub struct Outputs {
+    scenario: String,
+    sinks: Vec<Box<dyn OutputSink>>,
+    batch: Batch,
+    /// Failed flushes per error message
+    failures: BTreeMap<String, u64>,
+}
+
+impl Outputs {
+    pub fn new(scenario: impl Into<String>, sinks: Vec<Box<dyn OutputSink>>) -> Self {
+        let batch = Batch { start_unix_ns: unix_ns(SystemTime::now()), ..Batch::default() };
+        Outputs { scenario: scenario.into(), sinks, batch, failures: BTreeMap::new() }
+    }
+
+    pub fn on_event(&mut self, event: Event) {
+        if let Event::RequestFinished { ok, latency_ms, error, endpoint, status, journey, stage_index, span } = event {
+            let key = SeriesKey { journey, endpoint, stage_index };
+            if let Some(span) = span {
+                self.batch.spans.push(SpanRecord { span, key: key.clone(), status, error });
+            }
+            let series = self.batch.series.entry(key).or_default();
+            match ok {
+                true => series.ok += 1,
+                false => series.error += 1,
+            }
+            series.latencies_ms.push(latency_ms);
+        }
+    }
+
+    /// A failing sink is counted and skipped, the run goes on
+    pub async fn flush(&mut self) {
+        if self.batch.series.is_empty() {
+            return;
+        }
+        let now = unix_ns(SystemTime::now());
+        let mut batch = std::mem::replace(&mut self.batch, Batch { start_unix_ns: now, ..Batch::default() });
+        batch.end_unix_ns = now;
+        for sink in self.sinks.iter_mut() {
+            if let Err(e) = sink.flush(&self.scenario, &batch).await {
+                *self.failures.entry(e.to_string()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Failed flushes, one line per distinct error. Reported after the run rather than over the
+    /// live view
+    pub fn failures(&self) -> Vec<String> {
+        self.failures.iter()
+            .map(|(message, count)| match count {
+                1 => message.clone(),
+                count => format!("{} ({} flushes)", message, count),
+            })
+            .collect()
+    }
+
+    /// Flushes every `interval` and once more when the run finishes, then returns the `failures`
+    pub fn spawn(mut self, mut rx: mpsc::UnboundedReceiver<Event>, interval: Duration) -> JoinHandle<Vec<String>> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => self.flush().await,
+                    ev = rx.recv() => match ev {
+                        Some(Event::RunFinished) | None => break,
+                        Some(ev) => self.on_event(ev),
+                    }
+                }
+            }
+            self.flush().await;
+            self.failures()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::Event;
+    use crate::outputs::{raw_results, Batch, OutputError, OutputSink, OutputTarget, Outputs, SeriesKey, SeriesWindow};
+    use crate::trace_context::RequestSpan;
+    use async_trait::async_trait;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    struct Recorder(Arc<Mutex<Vec<Batch>>>);
+
+    #[async_trait]
+    impl OutputSink for Recorder {
+        async fn flush(&mut self, _scenario: &str, batch: &Batch) -> Result<(), OutputError> {
+            self.0.lock().unwrap().push(batch.clone());
+            Ok(())
+        }
+    }
+
+    struct Unreachable;
+
+    #[async_trait]
+    impl OutputSink for Unreachable {
+        async fn flush(&mut self, _scenario: &str, _batch: &Batch) -> Result<(), OutputError> {
+            Err(OutputError::new("influxdb write to http://db: connection refused"))
+        }
+    }
+
+    fn request(journey: &str, endpoint: &str, stage_index: u64, latency_ms: u32, ok: bool) -> Event {
+        Event::RequestFinished {
+            ok,
+            latency_ms,
+            error: (!ok).then(|| "http_500".to_string()),
+            endpoint: endpoint.to_string(),
+            status: Some(if ok { 200 } else { 500 }),
+            journey: journey.to_string(),
+            stage_index,
+            span: None,
+        }
+    }
+
+    #[test]
+    fn it_parses_output_targets() {
+        assert_eq!(OutputTarget::InfluxDbHttp("http://localhost:8086/write?db=rload".to_string()), "influxdb=http://localhost:8086/write?db=rload".parse().unwrap());
+        assert_eq!(OutputTarget::InfluxDbFile(PathBuf::from("out/run.lp")), "influxdb=out/run.lp".parse().unwrap());
+        assert_eq!(OutputTarget::Statsd("127.0.0.1:8125".to_string()), "statsd=127.0.0.1:8125".parse().unwrap());
+        assert_eq!(OutputTarget::Otlp("http://localhost:4318".to_string()), "otlp=http://localhost:4318".parse().unwrap());
+        assert_eq!(OutputTarget::Raw(PathBuf::from("results.csv")), "raw=results.csv".parse().unwrap());
+        assert_eq!(OutputTarget::Junit(PathBuf::from("results.xml")), "junit=results.xml".parse().unwrap());
+        for invalid in ["influxdb", "statsd=", "graphite=localhost:2003"] {
+            assert!(invalid.parse::<OutputTarget>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn it_accepts_a_single_raw_output() {
+        let targets = |specs: &[&str]| specs.iter().map(|s| s.parse().unwrap()).collect::<Vec<OutputTarget>>();
+
+        assert_eq!(None, raw_results(&targets(&["otlp=http://localhost:4318"])).unwrap());
+        assert_eq!(Some(Path::new("a.csv")), raw_results(&targets(&["raw=a.csv", "statsd=127.0.0.1:8125"])).unwrap());
+        assert_eq!("output: only one raw= output is supported, got a.csv and b.ndjson",
+                   raw_results(&targets(&["raw=a.csv", "raw=b.ndjson"])).unwrap_err().to_string());
+    }
+
+    #[tokio::test]
+    async fn it_flushes_series_per_window() {
+        let flushed = Arc::new(Mutex::new(Vec::new()));
+        let (tx, rx) = mpsc::unbounded_channel();
+        let outputs = Outputs::new("demo", vec![Box::new(Recorder(flushed.clone()))]);
+        let handle = outputs.spawn(rx, Duration::from_secs(3600));
+
+        tx.send(request("browse", "GET /ok", 0, 10, true)).unwrap();
+        tx.send(request("browse", "GET /ok", 0, 30, false)).unwrap();
+        tx.send(request("browse", "GET /ok", 1, 20, true)).unwrap();
+        let span = RequestSpan { trace_id: [1; 16], span_id: [2; 8], start_unix_ns: 10, end_unix_ns: 20, step_index: 3 };
+        let Event::RequestFinished { ok, latency_ms, error, endpoint, status, journey, stage_index, .. } = request("browse", "GET /ok", 1, 40, true) else {
+            unreachable!()
+        };
+        tx.send(Event::RequestFinished { ok, latency_ms, error, endpoint, status, journey, stage_index, span: Some(span) }).unwrap();
+        tx.send(Event::InFlight { value: 2 }).unwrap();
+        tx.send(Event::RunFinished).unwrap();
+        assert!(handle.await.unwrap().is_empty());
+
+        let flushed = flushed.lock().unwrap();
+        assert_eq!(1, flushed.len());
+        let key = |stage_index| SeriesKey { journey: "browse".to_string(), endpoint: "GET /ok".to_string(), stage_index };
+        assert_eq!(Some(&SeriesWindow { ok: 1, error: 1, latencies_ms: vec![10, 30] }), flushed[0].series.get(&key(0)));
+        assert_eq!(Some(&SeriesWindow { ok: 2, error: 0, latencies_ms: vec![20, 40] }), flushed[0].series.get(&key(1)));
+        assert_eq!(vec![(span, key(1))], flushed[0].spans.iter().map(|s| (s.span, s.key.clone())).collect::<Vec<_>>());
+        assert!(flushed[0].start_unix_ns <= flushed[0].end_unix_ns);
+    }
+
+    #[tokio::test]
+    async fn it_counts_failed_flushes() {
+        let mut outputs = Outputs::new("demo", vec![Box::new(Unreachable)]);
+        for _ in 0..3 {
+            outputs.on_event(request("browse", "GET /ok", 0, 10, true));
+            outputs.flush().await;
+        }
+
+        assert_eq!(vec!["output: influxdb write to http://db: connection refused (3 flushes)"], outputs.failures());
+    }
+
+    #[test]
+    fn it_computes_window_latency() {
+        let window = SeriesWindow { ok: 4, error: 1, latencies_ms: vec![50, 10, 40, 20, 30] };
+
+        assert_eq!(5, window.count());
+        assert_eq!(30.0, window.mean_ms());
+        assert_eq!(50, window.max_ms());
+        assert_eq!(30, window.percentile_ms(0.5));
+        assert_eq!(50, window.percentile_ms(0.95));
+        assert_eq!(0, SeriesWindow::default().percentile_ms(0.95));
+    }
+}
@@ -0,0 +1,309 @@
+use crate::outputs::proto::Message;
+use crate::outputs::{http_client, Batch, OutputError, OutputSink, SeriesKey, SpanRecord};
+use async_trait::async_trait;
+
+/// Upper bounds of the latency histogram buckets, in ms
+const LATENCY_BOUNDS_MS: [f64; 12] = [5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0, 30000.0];
+
+const AGGREGATION_TEMPORALITY_DELTA: u64 = 1;
+const SPAN_KIND_CLIENT: u64 = 3;
+const STATUS_CODE_ERROR: u64 = 2;
+
+/// Exports metrics and sampled request spans over OTLP/HTTP with protobuf payloads,
+/// to `<endpoint>/v1/metrics` and `<endpoint>/v1/traces`.
+pub struct OtlpSink {
+    client: reqwest::Client,
+    metrics_url: String,
+    traces_url: String,
+}
+
+impl OtlpSink {
+    pub fn new(endpoint: &str) -> Result<Self, OutputError> {
+        reqwest::Url::parse(endpoint).map_err(|e| OutputError::new(format!("otlp endpoint '{}': {}", endpoint, e)))?;
+        let endpoint = endpoint.trim_end_matches('/');
+        Ok(OtlpSink {
+            client: http_client()?,
+            metrics_url: format!("{}/v1/metrics", endpoint),
+            traces_url: format!("{}/v1/traces", endpoint),
+        })
+    }
+
+    async fn post(&self, url: &str, body: Vec<u8>) -> Result<(), OutputError> {
+        let response = self.client.post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/x-protobuf")
+            .body(body)
+            .send().await
+            .map_err(|e| OutputError::new(format!("otlp export to {}: {}", url, e)))?;
+        if !response.status().is_success() {
+            return Err(OutputError::new(format!("otlp export to {}: status {}", url, response.status())));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OutputSink for OtlpSink {
+    async fn flush(&mut self, scenario: &str, batch: &Batch) -> Result<(), OutputError> {
+        self.post(&self.metrics_url, metrics_request(scenario, batch)).await?;
+        if !batch.spans.is_empty() {
+            self.post(&self.traces_url, traces_request(scenario, &batch.spans)).await?;
+        }
+        Ok(())
+    }
+}
+
+enum Value<'a> {
+    Str(&'a str),
+    Int(i64),
+}
+
+fn key_value(key: &str, value: Value) -> Message {
+    let value = match value {
+        Value::Str(s) => Message::new().string(1, s),
+        Value::Int(i) => Message::new().varint(3, i as u64),
+    };
+    Message::new().string(1, key).message(2, value)
+}
+
+fn resource(scenario: &str) -> Message {
+    Message::new().messages(1, [
+        key_value("service.name", Value::Str("rload")),
+        key_value("rload.scenario", Value::Str(scenario)),
+    ])
+}
+
+fn scope() -> Message {
+    Message::new().string(1, "rload").string(2, env!("CARGO_PKG_VERSION"))
+}
+
+fn series_attributes(key: &SeriesKey) -> Vec<Message> {
+    vec![
+        key_value("rload.journey", Value::Str(&key.journey)),
+        key_value("rload.endpoint", Value::Str(&key.endpoint)),
+        key_value("rload.stage", Value::Int(key.stage_index as i64)),
+    ]
+}
+
+/// `ExportMetricsServiceRequest` with delta sums `rload.requests` and `rload.request.errors`
+/// and the `rload.request.duration` histogram, one data point per series
+pub(crate) fn metrics_request(scenario: &str, batch: &Batch) -> Vec<u8> {
+    let number_point = |key: &SeriesKey, value: u64| Message::new()
+        .fixed64(2, batch.start_unix_ns)
+        .fixed64(3, batch.end_unix_ns)
+        .sfixed64(6, value as i64)
+        .messages(7, series_attributes(key));
+    let sum = |name: &str, description: &str, value: fn(&crate::outputs::SeriesWindow) -> u64| Message::new()
+        .string(1, name)
+        .string(2, description)
+        .string(3, "1")
+        .message(7, Message::new()
+            .messages(1, batch.series.iter().map(|(key, series)| number_point(key, value(series))))
+            .varint(2, AGGREGATION_TEMPORALITY_DELTA)
+            .varint(3, 1));
+
+    let histogram_points = batch.series.iter().map(|(key, series)| {
+        let mut counts = vec![0u64; LATENCY_BOUNDS_MS.len() + 1];
+        for &latency_ms in &series.latencies_ms {
+            let bucket = LATENCY_BOUNDS_MS.iter().position(|&bound| latency_ms as f64 <= bound).unwrap_or(LATENCY_BOUNDS_MS.len());
+            counts[bucket] += 1;
+        }
+        Message::new()
+            .fixed64(2, batch.start_unix_ns)
+            .fixed64(3, batch.end_unix_ns)
+            .fixed64(4, series.latencies_ms.len() as u64)
+            .double(5, series.latencies_ms.iter().map(|&l| l as f64).sum())
+            .packed_fixed64(6, &counts)
+            .packed_double(7, &LATENCY_BOUNDS_MS)
+            .messages(9, series_attributes(key))
+            .double(11, series.latencies_ms.iter().copied().min().unwrap_or(0) as f64)
+            .double(12, series.max_ms() as f64)
+    });
+    let duration = Message::new()
+        .string(1, "rload.request.duration")
+        .string(2, "Request latency, response body included")
+        .string(3, "ms")
+        .message(9, Message::new()
+            .messages(1, histogram_points)
+            .varint(2, AGGREGATION_TEMPORALITY_DELTA));
+
+    let scope_metrics = Message::new()
+        .message(1, scope())
+        .message(2, sum("rload.requests", "Finished requests", |s| s.count()))
+        .message(2, sum("rload.request.errors", "Failed requests", |s| s.error))
+        .message(2, duration);
+    let resource_metrics = Message::new()
+        .message(1, resource(scenario))
+        .message(2, scope_metrics);
+
+    Message::new().message(1, resource_metrics).into_bytes()
+}
+
+/// `ExportTraceServiceRequest` with a client span per sampled request
+pub(crate) fn traces_request(scenario: &str, spans: &[SpanRecord]) -> Vec<u8> {
+    let spans = spans.iter().map(|record| {
+        let mut attributes = series_attributes(&record.key);
+        attributes.push(key_value("rload.step", Value::Int(record.span.step_index as i64)));
+        if let Some(status) = record.status {
+            attributes.push(key_value("http.response.status_code", Value::Int(status as i64)));
+        }
+        if let Some(error) = &record.error {
+            attributes.push(key_value("error.type", Value::Str(error)));
+        }
+        let span = Message::new()
+            .bytes(1, &record.span.trace_id)
+            .bytes(2, &record.span.span_id)
+            .string(5, &record.key.endpoint)
+            .varint(6, SPAN_KIND_CLIENT)
+            .fixed64(7, record.span.start_unix_ns)
+            .fixed64(8, record.span.end_unix_ns)
+            .messages(9, attributes);
+        match &record.error {
+            Some(error) => span.message(15, Message::new().string(2, error).varint(3, STATUS_CODE_ERROR)),
+            None => span,
+        }
+    });
+    let scope_spans = Message::new()
+        .message(1, scope())
+        .messages(2, spans);
+    let resource_spans = Message::new()
+        .message(1, resource(scenario))
+        .message(2, scope_spans);
+
+    Message::new().message(1, resource_spans).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::outputs::otlp::{metrics_request, traces_request, OtlpSink};
+    use crate::outputs::proto::decode::{all, one, Field};
+    use crate::outputs::{Batch, OutputSink, SeriesKey, SeriesWindow, SpanRecord};
+    use crate::trace_context::RequestSpan;
+    use std::collections::BTreeMap;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::mpsc;
+
+    fn key() -> SeriesKey {
+        SeriesKey { journey: "browse".to_string(), endpoint: "GET /users/{{id}}".to_string(), stage_index: 1 }
+    }
+
+    fn batch() -> Batch {
+        let span = RequestSpan { trace_id: [7; 16], span_id: [9; 8], start_unix_ns: 1_000, end_unix_ns: 41_000, step_index: 2 };
+        Batch {
+            start_unix_ns: 100,
+            end_unix_ns: 200,
+            series: BTreeMap::from([(key(), SeriesWindow { ok: 2, error: 1, latencies_ms: vec![3, 40, 20_000] })]),
+            spans: vec![SpanRecord { span, key: key(), status: Some(503), error: Some("http_503".to_string()) }],
+        }
+    }
+
+    /// attribute key → value message
+    fn attributes(message: &[u8], number: u32) -> BTreeMap<String, Vec<u8>> {
+        all(message, number).iter()
+            .map(|kv| (one(kv.as_bytes(), 1).as_str().to_string(), one(kv.as_bytes(), 2).as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn it_encodes_metrics() {
+        let request = metrics_request("demo", &batch());
+
+        let resource_metrics = one(&request, 1);
+        let resource = one(resource_metrics.as_bytes(), 1);
+        assert_eq!("demo", one(&attributes(resource.as_bytes(), 1)["rload.scenario"], 1).as_str());
+        let scope_metrics = one(resource_metrics.as_bytes(), 2);
+        let metrics = all(scope_metrics.as_bytes(), 2);
+        let names: Vec<String> = metrics.iter().map(|m| one(m.as_bytes(), 1).as_str().to_string()).collect();
+        assert_eq!(vec!["rload.requests", "rload.request.errors", "rload.request.duration"], names);
+
+        let requests = one(one(metrics[0].as_bytes(), 7).as_bytes(), 1);
+        assert_eq!(3, one(requests.as_bytes(), 6).as_u64());
+        assert_eq!((100, 200), (one(requests.as_bytes(), 2).as_u64(), one(requests.as_bytes(), 3).as_u64()));
+        let labels = attributes(requests.as_bytes(), 7);
+        assert_eq!("GET /users/{{id}}", one(&labels["rload.endpoint"], 1).as_str());
+        assert_eq!(1, one(&labels["rload.stage"], 3).as_u64());
+
+        let errors = one(one(metrics[1].as_bytes(), 7).as_bytes(), 1);
+        assert_eq!(1, one(errors.as_bytes(), 6).as_u64());
+
+        let histogram = one(one(metrics[2].as_bytes(), 9).as_bytes(), 1);
+        assert_eq!(3, one(histogram.as_bytes(), 4).as_u64());
+        let counts: Vec<u64> = one(histogram.as_bytes(), 6).as_bytes()
+            .chunks(8).map(|c| u64::from_le_bytes(c.try_into().unwrap())).collect();
+        assert_eq!(vec![1, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1, 0], counts);
+        assert_eq!(Field::Fixed64(20_000f64.to_bits()), one(histogram.as_bytes(), 12));
+    }
+
+    #[test]
+    fn it_encodes_spans() {
+        let request = traces_request("demo", &batch().spans);
+
+        let scope_spans = one(one(&request, 1).as_bytes(), 2);
+        let span = one(scope_spans.as_bytes(), 2);
+        assert_eq!(vec![7; 16], one(span.as_bytes(), 1).as_bytes());
+        assert_eq!(vec![9; 8], one(span.as_bytes(), 2).as_bytes());
+        assert_eq!("GET /users/{{id}}", one(span.as_bytes(), 5).as_str());
+        assert_eq!(3, one(span.as_bytes(), 6).as_u64());
+        assert_eq!((1_000, 41_000), (one(span.as_bytes(), 7).as_u64(), one(span.as_bytes(), 8).as_u64()));
+        let labels = attributes(span.as_bytes(), 9);
+        assert_eq!("browse", one(&labels["rload.journey"], 1).as_str());
+        assert_eq!(2, one(&labels["rload.step"], 3).as_u64());
+        assert_eq!(503, one(&labels["http.response.status_code"], 3).as_u64());
+        assert_eq!(2, one(one(span.as_bytes(), 15).as_bytes(), 3).as_u64());
+    }
+
+    /// Sends `(request line, content-type, body)` of every request, answers 200
+    async fn spawn_collector() -> (String, mpsc::UnboundedReceiver<(String, String, Vec<u8>)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(serve(socket, tx.clone()));
+            }
+        });
+        (endpoint, rx)
+    }
+
+    async fn serve(mut socket: TcpStream, tx: mpsc::UnboundedSender<(String, String, Vec<u8>)>) {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+                match socket.read(&mut chunk).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                }
+                continue;
+            };
+            let head = String::from_utf8_lossy(&buf[..end]).to_string();
+            let header = |name: &str| head.lines()
+                .find_map(|line| line.split_once(':').filter(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.trim().to_string()))
+                .unwrap_or_default();
+            let length: usize = header("content-length").parse().unwrap_or(0);
+            while buf.len() < end + 4 + length {
+                let n = socket.read(&mut chunk).await.unwrap();
+                buf.extend_from_slice(&chunk[..n]);
+            }
+            let body = buf[end + 4..end + 4 + length].to_vec();
+            buf.drain(..end + 4 + length);
+            tx.send((head.lines().next().unwrap().to_string(), header("content-type"), body)).unwrap();
+            socket.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n").await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn it_posts_metrics_and_traces() {
+        let (endpoint, mut rx) = spawn_collector().await;
+        let mut sink = OtlpSink::new(&format!("{}/", endpoint)).unwrap();
+
+        sink.flush("demo", &batch()).await.unwrap();
+
+        let (line, content_type, body) = rx.recv().await.unwrap();
+        assert_eq!(("POST /v1/metrics HTTP/1.1", "application/x-protobuf"), (line.as_str(), content_type.as_str()));
+        assert_eq!(metrics_request("demo", &batch()), body);
+        let (line, _, body) = rx.recv().await.unwrap();
+        assert_eq!("POST /v1/traces HTTP/1.1", line);
+        assert_eq!(traces_request("demo", &batch().spans), body);
+    }
+}
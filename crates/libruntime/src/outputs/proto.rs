@@ -0,0 +1,197 @@
+/// Minimal protobuf writer for the OTLP messages. Fields are written in call order,
+/// nested messages are length-delimited.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Message {
+    buf: Vec<u8>,
+}
+
+const VARINT: u64 = 0;
+const FIXED64: u64 = 1;
+const LEN: u64 = 2;
+
+impl Message {
+    pub fn new() -> Self {
+        Message::default()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+
+    /// uint64, enums and bools
+    pub fn varint(mut self, field: u32, value: u64) -> Self {
+        self.key(field, VARINT);
+        self.raw_varint(value);
+        self
+    }
+
+    pub fn fixed64(mut self, field: u32, value: u64) -> Self {
+        self.key(field, FIXED64);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn sfixed64(self, field: u32, value: i64) -> Self {
+        self.fixed64(field, value as u64)
+    }
+
+    pub fn double(self, field: u32, value: f64) -> Self {
+        self.fixed64(field, value.to_bits())
+    }
+
+    pub fn bytes(mut self, field: u32, value: &[u8]) -> Self {
+        self.key(field, LEN);
+        self.raw_varint(value.len() as u64);
+        self.buf.extend_from_slice(value);
+        self
+    }
+
+    pub fn string(self, field: u32, value: &str) -> Self {
+        self.bytes(field, value.as_bytes())
+    }
+
+    pub fn message(self, field: u32, value: Message) -> Self {
+        self.bytes(field, &value.buf)
+    }
+
+    pub fn messages(self, field: u32, values: impl IntoIterator<Item = Message>) -> Self {
+        values.into_iter().fold(self, |message, value| message.message(field, value))
+    }
+
+    pub fn packed_fixed64(self, field: u32, values: &[u64]) -> Self {
+        let packed: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        self.bytes(field, &packed)
+    }
+
+    pub fn packed_double(self, field: u32, values: &[f64]) -> Self {
+        let packed: Vec<u8> = values.iter().flat_map(|v| v.to_bits().to_le_bytes()).collect();
+        self.bytes(field, &packed)
+    }
+
+    fn key(&mut self, field: u32, wire_type: u64) {
+        self.raw_varint(((field as u64) << 3) | wire_type);
+    }
+
+    fn raw_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push((value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+}
+
+/// Reads back what `Message` writes, for tests
+#[cfg(test)]
+pub(crate) mod decode {
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) enum Field {
+        Varint(u64),
+        Fixed64(u64),
+        Bytes(Vec<u8>),
+    }
+
+    impl Field {
+        pub fn as_bytes(&self) -> &[u8] {
+            match self {
+                Field::Bytes(bytes) => bytes,
+                other => panic!("not length-delimited: {:?}", other),
+            }
+        }
+
+        pub fn as_str(&self) -> &str {
+            std::str::from_utf8(self.as_bytes()).unwrap()
+        }
+
+        pub fn as_u64(&self) -> u64 {
+            match self {
+                Field::Varint(v) | Field::Fixed64(v) => *v,
+                other => panic!("not a number: {:?}", other),
+            }
+        }
+    }
+
+    pub(crate) fn fields(mut buf: &[u8]) -> Vec<(u32, Field)> {
+        let mut fields = Vec::new();
+        while !buf.is_empty() {
+            let key = varint(&mut buf);
+            let field = match key & 0x7 {
+                0 => Field::Varint(varint(&mut buf)),
+                1 => {
+                    let (value, rest) = buf.split_at(8);
+                    buf = rest;
+                    Field::Fixed64(u64::from_le_bytes(value.try_into().unwrap()))
+                }
+                2 => {
+                    let len = varint(&mut buf) as usize;
+                    let (value, rest) = buf.split_at(len);
+                    buf = rest;
+                    Field::Bytes(value.to_vec())
+                }
+                wire_type => panic!("unexpected wire type {}", wire_type),
+            };
+            fields.push(((key >> 3) as u32, field));
+        }
+        fields
+    }
+
+    /// Every value of field `number`
+    pub(crate) fn all(buf: &[u8], number: u32) -> Vec<Field> {
+        fields(buf).into_iter().filter(|(n, _)| *n == number).map(|(_, f)| f).collect()
+    }
+
+    /// The only value of field `number`
+    pub(crate) fn one(buf: &[u8], number: u32) -> Field {
+        let mut values = all(buf, number);
+        assert_eq!(1, values.len(), "field {}", number);
+        values.remove(0)
+    }
+
+    fn varint(buf: &mut &[u8]) -> u64 {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = buf[0];
+            *buf = &buf[1..];
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte < 0x80 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::outputs::proto::decode::{fields, Field};
+    use crate::outputs::proto::Message;
+
+    #[test]
+    fn it_encodes_wire_format() {
+        let key_value = Message::new()
+            .string(1, "a")
+            .message(2, Message::new().string(1, "b"));
+        assert_eq!(vec![0x0a, 0x01, b'a', 0x12, 0x03, 0x0a, 0x01, b'b'], key_value.into_bytes());
+
+        assert_eq!(vec![0x08, 0x96, 0x01], Message::new().varint(1, 150).into_bytes());
+        assert_eq!(vec![0x39, 1, 0, 0, 0, 0, 0, 0, 0], Message::new().fixed64(7, 1).into_bytes());
+    }
+
+    #[test]
+    fn it_reads_back_fields() {
+        let bytes = Message::new()
+            .varint(1, 300)
+            .double(2, 1.5)
+            .sfixed64(3, -2)
+            .packed_fixed64(4, &[1, 2])
+            .into_bytes();
+
+        assert_eq!(vec![
+            (1, Field::Varint(300)),
+            (2, Field::Fixed64(1.5f64.to_bits())),
+            (3, Field::Fixed64(-2i64 as u64)),
+            (4, Field::Bytes([1u64.to_le_bytes(), 2u64.to_le_bytes()].concat())),
+        ], fields(&bytes));
+    }
+}
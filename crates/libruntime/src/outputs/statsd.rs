@@ -0,0 +1,141 @@
+use crate::outputs::{Batch, OutputError, OutputSink, Window};
+use async_trait::async_trait;
+use std::net::SocketAddr;
+use tokio::net::UdpSocket;
+
+/// Keeps a datagram within a typical Ethernet MTU
+const MAX_PACKET_BYTES: usize = 1432;
+
+/// Sends counters and timings over UDP, tagged DogStatsD-style (`|#key:value`).
+pub struct StatsdSink {
+    socket: UdpSocket,
+}
+
+impl StatsdSink {
+    pub async fn connect(addr: &str) -> Result<Self, OutputError> {
+        let error = |e: std::io::Error| OutputError::new(format!("statsd {}: {}", addr, e));
+        let target = tokio::net::lookup_host(addr).await.map_err(error)?.next()
+            .ok_or_else(|| OutputError::new(format!("statsd {}: no address found", addr)))?;
+        // the local socket must be of the same family as the target
+        let local = match target {
+            SocketAddr::V4(_) => "0.0.0.0:0",
+            SocketAddr::V6(_) => "[::]:0",
+        };
+        let socket = UdpSocket::bind(local).await.map_err(error)?;
+        socket.connect(target).await.map_err(error)?;
+        Ok(StatsdSink { socket })
+    }
+}
+
+#[async_trait]
+impl OutputSink for StatsdSink {
+    async fn flush(&mut self, scenario: &str, batch: &Batch) -> Result<(), OutputError> {
+        for packet in packets(scenario, &batch.series) {
+            self.socket.send(packet.as_bytes()).await
+                .map_err(|e| OutputError::new(format!("statsd send: {}", e)))?;
+        }
+        Ok(())
+    }
+}
+
+/// `rload.requests` and `rload.errors` counters per series, one `rload.latency` timing per request,
+/// packed newline-separated into datagrams
+pub(crate) fn packets(scenario: &str, window: &Window) -> Vec<String> {
+    let mut packets = Vec::new();
+    let mut packet = String::new();
+    let mut push = |line: String| {
+        if !packet.is_empty() && packet.len() + 1 + line.len() > MAX_PACKET_BYTES {
+            packets.push(std::mem::take(&mut packet));
+        }
+        if !packet.is_empty() {
+            packet.push('\n');
+        }
+        packet.push_str(&line);
+    };
+
+    for (key, series) in window {
+        let tags = format!(
+            "#scenario:{},journey:{},endpoint:{},stage:{}",
+            sanitize(scenario),
+            sanitize(&key.journey),
+            sanitize(&key.endpoint),
+            key.stage_index,
+        );
+        push(format!("rload.requests:{}|c|{}", series.count(), tags));
+        if series.error > 0 {
+            push(format!("rload.errors:{}|c|{}", series.error, tags));
+        }
+        for latency_ms in &series.latencies_ms {
+            push(format!("rload.latency:{}|ms|{}", latency_ms, tags));
+        }
+    }
+    if !packet.is_empty() {
+        packets.push(packet);
+    }
+    packets
+}
+
+/// Tag values keep letters, digits and `_-./`, anything else becomes `_`
+fn sanitize(value: &str) -> String {
+    value.chars()
+        .map(|c| match c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/') {
+            true => c,
+            false => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::outputs::statsd::{packets, StatsdSink, MAX_PACKET_BYTES};
+    use crate::outputs::{Batch, OutputSink, SeriesKey, SeriesWindow, Window};
+    use tokio::net::UdpSocket;
+
+    fn batch(series: Window) -> Batch {
+        Batch { series, ..Batch::default() }
+    }
+
+    fn window(latencies_ms: Vec<u32>) -> Window {
+        let mut window = Window::new();
+        window.insert(
+            SeriesKey { journey: "browse".to_string(), endpoint: "GET /users/{{id}}".to_string(), stage_index: 2 },
+            SeriesWindow { ok: latencies_ms.len() as u64 - 1, error: 1, latencies_ms },
+        );
+        window
+    }
+
+    #[test]
+    fn it_formats_tagged_metrics() {
+        let tags = "#scenario:demo,journey:browse,endpoint:GET_/users/__id__,stage:2";
+
+        assert_eq!(
+            vec![format!("rload.requests:2|c|{tags}\nrload.errors:1|c|{tags}\nrload.latency:12|ms|{tags}\nrload.latency:340|ms|{tags}")],
+            packets("demo", &window(vec![12, 340]))
+        );
+    }
+
+    #[test]
+    fn it_splits_packets() {
+        let packets = packets("demo", &window(vec![5; 100]));
+
+        assert!(packets.len() > 1);
+        assert!(packets.iter().all(|p| p.len() <= MAX_PACKET_BYTES));
+        assert_eq!(102, packets.iter().map(|p| p.lines().count()).sum::<usize>());
+    }
+
+    #[tokio::test]
+    async fn it_sends_over_udp_to_ipv4_and_ipv6() {
+        for addr in ["127.0.0.1:0", "[::1]:0"] {
+            let listener = UdpSocket::bind(addr).await.unwrap();
+            let mut sink = StatsdSink::connect(&listener.local_addr().unwrap().to_string()).await.unwrap();
+
+            sink.flush("demo", &batch(window(vec![7]))).await.unwrap();
+
+            let mut buf = [0u8; MAX_PACKET_BYTES];
+            let n = listener.recv(&mut buf).await.unwrap();
+            let packet = String::from_utf8_lossy(&buf[..n]);
+            assert!(packet.starts_with("rload.requests:1|c|#scenario:demo,journey:browse"), "{}", packet);
+            assert!(packet.contains("rload.latency:7|ms|"), "{}", packet);
+        }
+    }
+}
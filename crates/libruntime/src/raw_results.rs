@@ -0,0 +1,188 @@
+use crate::outputs::OutputError;
+use crate::vu_runner::ResponseResult;
+use serde::Serialize;
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum RawFormat {
+    Ndjson,
+    Csv,
+}
+
+impl RawFormat {
+    /// `.csv` files get CSV, anything else NDJSON
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => RawFormat::Csv,
+            _ => RawFormat::Ndjson,
+        }
+    }
+}
+
+/// One completed request
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct RawRecord {
+    /// Unix time the request finished
+    pub timestamp_ms: u64,
+    pub vu_id: u32,
+    pub journey_id: u64,
+    pub step_index: usize,
+    pub endpoint: String,
+    pub status: Option<u16>,
+    /// See `ResponseResult::error_label`
+    pub error: Option<String>,
+    pub latency_us: u64,
+    pub stage_index: u64,
+}
+
+const CSV_HEADER: &str = "timestamp_ms,vu_id,journey_id,step_index,endpoint,status,error,latency_us,stage_index";
+
+impl RawRecord {
+    pub fn new(timestamp_ms: u64, vu_id: u32, res: &ResponseResult) -> Self {
+        RawRecord {
+            timestamp_ms,
+            vu_id,
+            journey_id: res.journey_id,
+            step_index: res.step_index,
+            endpoint: res.endpoint_key.clone(),
+            status: res.status,
+            error: res.error_label(),
+            // the mock executor only reports ms
+            latency_us: match res.latency_us {
+                0 => res.latency_ms * 1000,
+                us => us,
+            },
+            stage_index: res.stage_index,
+        }
+    }
+
+    fn line(&self, format: RawFormat) -> String {
+        match format {
+            RawFormat::Ndjson => serde_json::to_string(self).expect("raw record serializes"),
+            RawFormat::Csv => [
+                self.timestamp_ms.to_string(),
+                self.vu_id.to_string(),
+                self.journey_id.to_string(),
+                self.step_index.to_string(),
+                csv_field(&self.endpoint),
+                self.status.map(|s| s.to_string()).unwrap_or_default(),
+                self.error.as_deref().map(csv_field).unwrap_or_default(),
+                self.latency_us.to_string(),
+                self.stage_index.to_string(),
+            ].join(","),
+        }
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+/// Streams raw records to a file from a background task, so the run loop only pays for a channel send.
+/// The file is complete once every clone is dropped and the writer handle has finished.
+#[derive(Debug, Clone)]
+pub(crate) struct RawResults {
+    tx: mpsc::UnboundedSender<RawRecord>,
+}
+
+impl RawResults {
+    pub async fn create(path: &Path) -> Result<(RawResults, JoinHandle<Result<(), OutputError>>), OutputError> {
+        let error = |e: std::io::Error| OutputError::new(format!("raw results {}: {}", path.display(), e));
+        let file = File::create(path).await.map_err(error)?;
+        let format = RawFormat::from_path(path);
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        let path = path.to_path_buf();
+        let handle = tokio::spawn(async move {
+            write_all(BufWriter::new(file), format, rx).await
+                .map_err(|e| OutputError::new(format!("raw results {}: {}", path.display(), e)))
+        });
+        Ok((RawResults { tx }, handle))
+    }
+
+    pub fn record(&self, timestamp_ms: u64, vu_id: u32, res: &ResponseResult) {
+        let _ = self.tx.send(RawRecord::new(timestamp_ms, vu_id, res));
+    }
+}
+
+async fn write_all(mut out: BufWriter<File>, format: RawFormat, mut rx: mpsc::UnboundedReceiver<RawRecord>) -> std::io::Result<()> {
+    if format == RawFormat::Csv {
+        out.write_all(CSV_HEADER.as_bytes()).await?;
+        out.write_all(b"\n").await?;
+    }
+    while let Some(record) = rx.recv().await {
+        out.write_all(record.line(format).as_bytes()).await?;
+        out.write_all(b"\n").await?;
+    }
+    out.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::raw_results::{RawFormat, RawRecord, RawResults};
+    use std::path::Path;
+
+    fn record(endpoint: &str, error: Option<&str>) -> RawRecord {
+        RawRecord {
+            timestamp_ms: 1_700_000_000_123,
+            vu_id: 7,
+            journey_id: 1,
+            step_index: 2,
+            endpoint: endpoint.to_string(),
+            status: error.is_none().then_some(200),
+            error: error.map(str::to_string),
+            latency_us: 12_345,
+            stage_index: 0,
+        }
+    }
+
+    async fn write(name: &str, records: Vec<RawRecord>) -> String {
+        let path = std::env::temp_dir().join(format!("rload-raw-{}-{}", std::process::id(), name));
+        let (raw, handle) = RawResults::create(&path).await.unwrap();
+        for record in records {
+            let _ = raw.tx.send(record);
+        }
+        drop(raw);
+        handle.await.unwrap().unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        content
+    }
+
+    #[test]
+    fn it_picks_format_from_extension() {
+        assert_eq!(RawFormat::Csv, RawFormat::from_path(Path::new("out/results.CSV")));
+        assert_eq!(RawFormat::Ndjson, RawFormat::from_path(Path::new("results.ndjson")));
+        assert_eq!(RawFormat::Ndjson, RawFormat::from_path(Path::new("results")));
+    }
+
+    #[tokio::test]
+    async fn it_writes_ndjson() {
+        let content = write("results.ndjson", vec![record("GET /ok", None), record("POST /orders", Some("timeout"))]).await;
+
+        assert_eq!(
+            "{\"timestamp_ms\":1700000000123,\"vu_id\":7,\"journey_id\":1,\"step_index\":2,\"endpoint\":\"GET /ok\",\"status\":200,\"error\":null,\"latency_us\":12345,\"stage_index\":0}\n\
+             {\"timestamp_ms\":1700000000123,\"vu_id\":7,\"journey_id\":1,\"step_index\":2,\"endpoint\":\"POST /orders\",\"status\":null,\"error\":\"timeout\",\"latency_us\":12345,\"stage_index\":0}\n",
+            content
+        );
+    }
+
+    #[tokio::test]
+    async fn it_writes_csv() {
+        let content = write("results.csv", vec![record("GET /search?q=a,b", None), record("GET /ok", Some("http_503"))]).await;
+
+        assert_eq!(
+            "timestamp_ms,vu_id,journey_id,step_index,endpoint,status,error,latency_us,stage_index\n\
+             1700000000123,7,1,2,\"GET /search?q=a,b\",200,,12345,0\n\
+             1700000000123,7,1,2,GET /ok,,http_503,12345,0\n",
+            content
+        );
+    }
+}
@@ -0,0 +1,46 @@
+//! The public shape of a run report. Any change to it bumps `REPORT_VERSION`
+//! and is locked by the schema snapshot below.
+
+use schemars::schema_for;
+use std::path::Path;
+
+pub use crate::run_engine::{
+    ByJourney, ByStage, CheckCounts, CorrectedLatency, EndpointStats, ErrorAndQuality, Journey, LatencySummary, Requests,
+    Rps, Run, RunReport, Scenario, TicksArrival, Time, VuUtilization,
+};
+pub use crate::thresholds::{ThresholdResult, ThresholdsSummary};
+pub use crate::timeseries::{LatencySnapshot, TimeBucket, TimeSeriesReport};
+
+/// Written to `RunReport::report_version`
+pub const REPORT_VERSION: u32 = 2;
+
+/// JSON Schema of `RunReport`, with its version in `$version` like the scenario schema
+pub fn schema() -> serde_json::Value {
+    let mut schema = schema_for!(RunReport);
+    schema.insert("$version".to_string(), serde_json::Value::String(REPORT_VERSION.to_string()));
+    schema.to_value()
+}
+
+pub fn export_schema(out_path: impl AsRef<Path>) -> std::io::Result<()> {
+    std::fs::write(out_path, serde_json::to_string_pretty(&schema()).expect("schema serializes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::report::{schema, RunReport, REPORT_VERSION};
+
+    #[test]
+    fn it_exports_report_schema() {
+        insta::assert_snapshot!(serde_json::to_string_pretty(&schema()).unwrap());
+    }
+
+    #[test]
+    fn it_reads_reports_saved_before_versioning() {
+        let mut saved = serde_json::to_value(RunReport::new(&crate::scheduler::Scheduler::new(&Default::default()))).unwrap();
+        assert_eq!(REPORT_VERSION as u64, saved["report_version"].as_u64().unwrap());
+
+        saved.as_object_mut().unwrap().remove("report_version");
+        let report: RunReport = serde_json::from_value(saved).unwrap();
+        assert_eq!(0, report.report_version);
+    }
+}
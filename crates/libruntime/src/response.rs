@@ -0,0 +1,158 @@
+use crate::execution_plan::ExecutionPlan;
+use libprotocol::json_path::JsonPath;
+use libprotocol::schema::{Check, Extract};
+use serde_json::Value;
+
+/// What the executor captured from a response, kept only while the request is processed.
+pub(crate) struct ResponseData<'a> {
+    pub(crate) status: u16,
+    pub(crate) headers: &'a reqwest::header::HeaderMap,
+    pub(crate) body: &'a str,
+}
+
+impl ResponseData<'_> {
+    fn json(&self) -> Option<Value> {
+        serde_json::from_str(self.body).ok()
+    }
+}
+
+/// Applies the `extract` rules of a step. `None` means the value was not found in the response.
+pub(crate) fn extract(plan: &ExecutionPlan, rules: &[Extract], response: &ResponseData) -> Vec<(String, Option<String>)> {
+    if rules.is_empty() {
+        return Vec::new();
+    }
+    let json = rules.iter().any(|r| matches!(r, Extract::Json { .. }))
+        .then(|| response.json())
+        .flatten();
+
+    rules.iter().map(|rule| {
+        let value = match rule {
+            Extract::Json { path, .. } => JsonPath::parse(path).ok()
+                .and_then(|path| json.as_ref().and_then(|json| path.select(json)).map(json_to_string)),
+            Extract::Header { header, .. } => response.headers.get(header)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string),
+            Extract::Regex { pattern, .. } => plan.regex(pattern)
+                .and_then(|re| re.captures(response.body))
+                .and_then(|caps| caps.get(1).or_else(|| caps.get(0)))
+                .map(|m| m.as_str().to_string()),
+        };
+        (rule.name().to_string(), value)
+    }).collect()
+}
+
+/// Evaluates the `checks` of a step, one `(label, passed)` per check.
+pub(crate) fn check(plan: &ExecutionPlan, checks: &[Check], response: &ResponseData) -> Vec<(String, bool)> {
+    if checks.is_empty() {
+        return Vec::new();
+    }
+    let json = checks.iter().any(|c| matches!(c, Check::JsonEquals { .. }))
+        .then(|| response.json())
+        .flatten();
+
+    checks.iter().map(|check| {
+        let passed = match check {
+            Check::Status { values } => values.contains(&response.status),
+            Check::BodyContains { value } => response.body.contains(value.as_str()),
+            Check::BodyRegex { pattern } => plan.regex(pattern).is_some_and(|re| re.is_match(response.body)),
+            Check::JsonEquals { path, value } => JsonPath::parse(path).ok()
+                .and_then(|path| json.as_ref().and_then(|json| path.select(json)))
+                .is_some_and(|found| found == value),
+            Check::MaxBodySize { bytes } => response.body.len() as u64 <= *bytes,
+            Check::HeaderPresent { header } => response.headers.contains_key(header.as_str()),
+        };
+        (check.to_string(), passed)
+    }).collect()
+}
+
+/// A response is ok when its status is expected and every check passed.
+/// Without a `status` check any 2xx status is expected.
+pub(crate) fn is_ok(checks: &[Check], results: &[(String, bool)], status: u16) -> bool {
+    let status_checked = checks.iter().any(|c| matches!(c, Check::Status { .. }));
+    (status_checked || (200..300).contains(&status)) && results.iter().all(|(_, passed)| *passed)
+}
+
+/// Strings are taken without quotes, everything else as JSON text
+fn json_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::execution_plan::ExecutionPlan;
+    use crate::response::{check, extract, is_ok, ResponseData};
+    use libprotocol::schema::{Check, Extract};
+    use libprotocol::Scenario;
+    use reqwest::header::HeaderMap;
+
+    #[test]
+    fn it_extracts_values_from_response() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-session", "s-123".parse().unwrap());
+        let response = ResponseData {
+            status: 200,
+            headers: &headers,
+            body: r#"{"data": {"token": "abc", "items": [{"id": 7}]}, "csrf": "<input value=\"q1w2\">"}"#,
+        };
+        let rules = vec![
+            Extract::Json { name: "token".to_string(), path: "$.data.token".to_string() },
+            Extract::Json { name: "item_id".to_string(), path: "$.data.items[0].id".to_string() },
+            Extract::Json { name: "missing".to_string(), path: "$.data.nope".to_string() },
+            Extract::Header { name: "session".to_string(), header: "X-Session".to_string() },
+            Extract::Regex { name: "csrf".to_string(), pattern: r#"value=\\"(\w+)\\""#.to_string() },
+        ];
+        let plan = ExecutionPlan::from(&Scenario::default());
+
+        let values = extract(&plan, &rules, &response);
+
+        assert_eq!(vec![
+            ("token".to_string(), Some("abc".to_string())),
+            ("item_id".to_string(), Some("7".to_string())),
+            ("missing".to_string(), None),
+            ("session".to_string(), Some("s-123".to_string())),
+            ("csrf".to_string(), Some("q1w2".to_string())),
+        ], values);
+    }
+
+    #[test]
+    fn it_checks_response() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-session", "s-123".parse().unwrap());
+        let response = ResponseData {
+            status: 404,
+            headers: &headers,
+            body: r#"{"error": {"code": 42}, "message": "not found"}"#,
+        };
+        let checks = vec![
+            Check::Status { values: vec![200, 404] },
+            Check::BodyContains { value: "not found".to_string() },
+            Check::BodyRegex { pattern: r#""code": \d+"#.to_string() },
+            Check::JsonEquals { path: "$.error.code".to_string(), value: serde_json::json!(42) },
+            Check::JsonEquals { path: "$.message".to_string(), value: serde_json::json!("found") },
+            Check::MaxBodySize { bytes: 10 },
+            Check::HeaderPresent { header: "X-Session".to_string() },
+            Check::HeaderPresent { header: "x-missing".to_string() },
+        ];
+        let plan = ExecutionPlan::from(&Scenario::default());
+
+        let results = check(&plan, &checks, &response);
+
+        assert_eq!(vec![
+            ("status in [200, 404]".to_string(), true),
+            ("body contains \"not found\"".to_string(), true),
+            ("body matches /\"code\": \\d+/".to_string(), true),
+            ("$.error.code == 42".to_string(), true),
+            ("$.message == \"found\"".to_string(), false),
+            ("body size <= 10".to_string(), false),
+            ("header X-Session present".to_string(), true),
+            ("header x-missing present".to_string(), false),
+        ], results);
+        assert!(!is_ok(&checks, &results, 404));
+        assert!(is_ok(&checks[..1], &results[..1], 404));
+        assert!(!is_ok(&[], &[], 404));
+        assert!(is_ok(&[], &[], 204));
+    }
+}
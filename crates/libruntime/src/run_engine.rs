@@ -1,18 +1,23 @@
 use crate::execution_plan::ExecutionPlan;
-use crate::metrics::MetricsAggregator;
+use crate::metrics::{LatencyHistograms, MetricsAggregator};
 use crate::scheduler::Scheduler;
+use crate::timeseries::TimeSeriesReport;
 use crate::vu_runner;
 use crate::vu_runner::NextAction::{NotReady, Ready};
 use crate::vu_runner::{Ctx, ErrorType, ExecutorAbstract, ExecutorHttp, ExecutorMock, ResponseResult, VUState, VuPool, VuRuntime};
-use libprotocol::schema::Workload;
-use serde::{Serialize};
+use libprotocol::schema::{Workload, WorkloadExecutor};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::sync::Arc;
-use std::time::Duration;
-use hdrhistogram::Histogram;
+use std::time::{Duration, SystemTime};
 use tokio::sync::{mpsc, Semaphore};
 use tokio::time::Instant;
-use crate::events::{Event, EventSink};
+use crate::events::{Event, EventSink, StopSignal};
+use crate::raw_results::RawResults;
+use crate::thresholds::{self, ThresholdsSummary};
+use crate::trace_context::unix_ns;
+use crate::report::REPORT_VERSION;
 
 pub const LOWEST_US: u64 = 1;
 pub const HIGHEST_US: u64 = 60_000_000;
@@ -20,7 +25,10 @@ pub const SIGFIG: u8 = 3;
 
 pub(crate) struct RunEngine {
     pub is_mock: bool,
-    pub is_real_time: bool
+    pub is_real_time: bool,
+    /// `--out raw=`, fed from the completed requests
+    pub raw_results: Option<RawResults>,
+    pub stop: StopSignal,
 }
 
 pub enum RunMode {
@@ -28,19 +36,40 @@ pub enum RunMode {
     Deterministic,
 }
 
-struct Completed {
-    vu_idx: usize,
-    now_ms: u64,
-    res: ResponseResult,
-    last_request_started_ms: u64
+pub(crate) struct Completed {
+    pub(crate) vu_idx: usize,
+    pub(crate) vu_id: u32,
+    pub(crate) now_ms: u64,
+    pub(crate) res: ResponseResult,
+    pub(crate) last_request_started_ms: u64
 }
 
 impl RunEngine {
     pub fn new(is_mock: Option<bool>, is_real_time: Option<bool>) -> Self {
-        Self { is_mock: is_mock.unwrap_or(false), is_real_time: is_real_time.unwrap_or(true) }
+        Self { is_mock: is_mock.unwrap_or(false), is_real_time: is_real_time.unwrap_or(true), raw_results: None, stop: StopSignal::default() }
+    }
+
+    pub fn with_raw_results(mut self, raw_results: RawResults) -> Self {
+        self.raw_results = Some(raw_results);
+        self
+    }
+
+    pub fn with_stop(mut self, stop: StopSignal) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    /// `finished_ms` is relative to `origin_unix_ms`, the start of the run
+    fn record_raw(&self, origin_unix_ms: u64, vu_id: u32, finished_ms: u64, res: &ResponseResult) {
+        if let Some(raw) = &self.raw_results {
+            raw.record(origin_unix_ms + finished_ms, vu_id, res);
+        }
     }
 
     pub async fn run(&self, plan: &ExecutionPlan, scenario: &libprotocol::schema::Scenario, sink: EventSink<Event>) -> RunReport {
+        if let Some(WorkloadExecutor::ConstantVus { vus, duration_sec }) = scenario.workload.executor {
+            return self.run_constant_vus(plan, scenario, vus, duration_sec, sink).await;
+        }
         let vus = 1000;
         let start_time_ms = tokio::time::Instant::now();
         let mut first_tick_real_ms: Option<u64> = None;
@@ -51,44 +80,21 @@ impl RunEngine {
             true => RunMode::Real,
         };
 
-        let scheduler: &mut Scheduler = &mut Scheduler::new(&scenario.workload);
+        let scheduler: &mut Scheduler = &mut Scheduler::with_seed(&scenario.workload, &plan.weight_sampler.seed);
         let mut run_report = RunReport::new(scheduler);
 
         let planned_duration_ms = scheduler.planned_duration_ms;
 
         let workload: &Workload = &scenario.workload;
-        let mut metrics = MetricsAggregator::new();
-        let sampler = &plan.weight_sampler;
+        let mut metrics = MetricsAggregator::with_timeseries_interval_ms(plan.timeseries_interval_ms);
         #[allow(dead_code)]
         let _runner_ctx = Ctx{};
 
-        let executor_instance: Box<dyn ExecutorAbstract> = match self.is_mock {
-            true => ExecutorMock::new_instance(),
-            false => ExecutorHttp::new_instance()
-        };
-        let executor = Arc::new(executor_instance);
+        let executor = self.executor(plan);
         let plan = Arc::new(plan.clone());
 
         let runtime = VuRuntime{};
-        let mut journey_per_vu: BTreeMap<usize, u64> = BTreeMap::new();
-        let mut pool_vec = Vec::new();
-        for i in 0..=vus {
-            let stable_key = format!("{}-{}", i, sampler.seed);
-            if let Some(journey_id) = sampler.peek(&stable_key){
-                // metrics, journey_per_vu
-                journey_per_vu.entry(journey_id as usize).and_modify(|journey_count| *journey_count += 1).or_insert(1);
-
-                pool_vec.push(VUState{
-                    vu_id: i,
-                    journey_id: journey_id as u32,
-                    step_index: 0,
-                    next_ready_at_ms: 0,
-                    iteration_count: 0,
-                    total_sleep_ms: 0,
-                });
-            };
-        }
-        let mut pool = VuPool::new(pool_vec);
+        let (mut pool, journey_per_vu) = build_pool(&plan, 0..=vus);
 
         let mut total_ticks = 0;
         let mut missed_ticks = 0;
@@ -105,6 +111,7 @@ impl RunEngine {
 
         // Real-time origin
         let origin = Instant::now();
+        let origin_unix_ms = unix_ns(SystemTime::now()) / 1_000_000;
         // ---- MAIN LOOP ----
         for tick in scheduler {
             sink.send(Event::TickExecuted{tick});
@@ -139,29 +146,42 @@ impl RunEngine {
             }
 
             last_tick_real_ms = now;
-            if now > stop_at_ms + 1 {
-                break; // window finished — no new requests started
+            if now > stop_at_ms + 1 || self.stop.is_stopped() {
+                break; // window finished or stopped — no new requests started
             }
 
             // 2) read from channel
             while let Ok(done) = rx.try_recv() {
-                sink.send(Event::RequestFinished{ok: done.res.ok, latency_ms: done.res.latency_ms as u32 });
-                metrics.consume(done.res, done.last_request_started_ms);
+                sink.send(Event::request_finished(&done.res));
+                self.record_raw(origin_unix_ms, done.vu_id, done.now_ms, &done.res);
                 let vu = pool.get_mut(done.vu_idx).unwrap();
-                runtime.on_request_executed(&plan, vu, done.now_ms);
+                runtime.on_request_executed(&plan, vu, done.now_ms, &done.res);
+                metrics.consume(done.res, done.last_request_started_ms);
+            }
+            if matches!(mode, RunMode::Real) {
+                sink.send(Event::InFlight { value: (max_in_flight - sem.available_permits()) as i32 });
             }
 
-            // 3) pick VU
-            let Some(vu_idx) = pool.pick_ready_vu(now) else {
+            // 3) pick VU (VUs entering think time are skipped)
+            let mut picked = None;
+            while let Some(vu_idx) = pool.pick_ready_vu(now) {
+                let vu = pool.get_mut(vu_idx).unwrap();
+                match runtime.next_action(&plan, vu, now).await {
+                    NotReady(_) => continue,
+                    action => {
+                        picked = Some((vu_idx, action));
+                        break;
+                    }
+                }
+            }
+            let Some((vu_idx, action)) = picked else {
                 missed_ticks += 1;
                 // take some relaxation
                 tokio::time::sleep(Duration::from_millis(300)).await;
                 continue;
             };
 
-            let vu = pool.get_mut(vu_idx).unwrap();
-
-            match runtime.next_action(&plan, vu, now).await {
+            match action {
                 NotReady(_next_ready_at) => { /* possible bug pick_ready_vu */ missed_ticks += 1; run_report.vus.no_ready_ticks += 1; }
                 vu_runner::NextAction::CompletedIteration => { /* no-op */ }
                 Ready(mut req) => {
@@ -175,80 +195,66 @@ impl RunEngine {
                                 .execute(&plan, &req, total_ticks)
                                 .await
                                 .unwrap_or_else(|e| {
-                                    eprintln!("executor error: {e}");
+                                    sink.send(Event::Warning { message: format!("executor error: {e}") });
                                     let stage_start_ms = match tick.is_new_stage {
                                         true => planned_now,
                                         false => 0,
                                     };
-                                    ResponseResult {
-                                        ok: false,
-                                        latency_ms: 0,
-                                        latency_us: 0,
-                                        error_kind: Some(ErrorType::ConnectionError),
-                                        endpoint_key: req.endpoint_key.clone(),
-                                        journey_name: "".to_string(),
-                                        journey_id: req.journey_id,
-                                        stage_index: tick.stage_index,
-                                        stage_start_ms,
-                                    }
+                                    let mut res = ResponseResult::error(&req, ErrorType::ConnectionError);
+                                    res.stage_start_ms = stage_start_ms;
+                                    res
                                 });
 
                             if tick.is_new_stage {
                                 res.stage_start_ms = planned_now;
                             }
+                            // virtual time: every request starts on schedule
+                            res.schedule(planned_now, started_ms);
 
                             let finished_ms = started_ms + res.latency_ms;
 
-                            metrics.consume(res, started_ms);
                             let vu = pool.get_mut(vu_idx).unwrap();
-                            runtime.on_request_executed(&plan, vu, finished_ms);
+                            self.record_raw(origin_unix_ms, vu.vu_id, finished_ms, &res);
+                            runtime.on_request_executed(&plan, vu, finished_ms, &res);
+                            metrics.consume(res, started_ms);
                         }
 
                         RunMode::Real => {
                             // ASYNC EXECUTION (spawn + sem + tx)
                             let permit = sem.clone().acquire_owned().await.unwrap();
                             let last_request_started_ms = origin.elapsed().as_millis() as u64;
+                            let vu_id = pool.get_mut(vu_idx).unwrap().vu_id;
 
                             let tx = tx.clone();
                             let executor = executor.clone();
                             let plan = plan.clone();
-                            let start_request = Instant::now();
+                            let sink = sink.clone();
 
-                            let stage_index = tick.stage_index;
-                            let sink_clone = sink.clone();
                             let handle = tokio::spawn(async move {
                                 let _permit = permit;
 
                                 let mut res = executor.execute(&plan, &req, total_ticks).await
                                     .unwrap_or_else(|e| {
-                                        eprintln!("executor error: {e}");
+                                        sink.send(Event::Warning { message: format!("executor error: {e}") });
                                         let stage_start_ms = match tick.is_new_stage {
                                             true => planned_now,
                                             false => 0,
                                         };
-                                        sink_clone.send(Event::RequestFinished{ok: false, latency_ms: start_request.elapsed().as_millis() as u32 });
-
-                                        ResponseResult {
-                                            ok: false,
-                                            latency_ms: 0,
-                                            latency_us: 0,
-                                            error_kind: Some(ErrorType::ConnectionError),
-                                            endpoint_key: req.endpoint_key.clone(),
-                                            journey_name: "".to_string(),
-                                            journey_id: req.journey_id,
-                                            stage_index,
-                                            stage_start_ms,
-                                        }
+                                        let mut res = ResponseResult::error(&req, ErrorType::ConnectionError);
+                                        res.stage_start_ms = stage_start_ms;
+                                        res
                                     });
 
                                 if tick.is_new_stage {
                                     res.stage_start_ms = last_request_started_ms;
                                 }
+                                res.schedule(planned_now, last_request_started_ms);
 
                                 let finished_ms = last_request_started_ms.saturating_add(res.latency_ms);
 
                                 let _ = tx.send(Completed {
                                     vu_idx,
+                                    vu_id,
                                     now_ms: finished_ms,
                                     res,
                                     last_request_started_ms,
@@ -280,10 +286,11 @@ impl RunEngine {
             }
 
             while let Ok(done) = rx.try_recv() {
-                sink.send(Event::RequestFinished{ok: done.res.ok, latency_ms: done.res.latency_ms as u32 });
-                metrics.consume(done.res, done.last_request_started_ms);
+                sink.send(Event::request_finished(&done.res));
+                self.record_raw(origin_unix_ms, done.vu_id, done.now_ms, &done.res);
                 let vu = pool.get_mut(done.vu_idx).unwrap();
-                runtime.on_request_executed(&plan, vu, done.now_ms);
+                runtime.on_request_executed(&plan, vu, done.now_ms, &done.res);
+                metrics.consume(done.res, done.last_request_started_ms);
             }
         }
 
@@ -308,91 +315,157 @@ impl RunEngine {
                 total_real_interval / (total_ticks - 1);
         }
 
-        // By endpoint
-        run_report.by_endpoint = metrics.by_endpoint;
-
-        // By journeys
-        let by_journey = metrics.by_journey.iter().map(|(key, (journey_id, count))| {
-            let per_vu = journey_per_vu.get(journey_id).unwrap_or(&0);
-            ByJourney {
-                id: *journey_id,
-                key: key.clone(),
-                per_vu: *per_vu,
-                per_request: *count,
-            }
-        }).collect::<Vec<ByJourney>>();
-
-        run_report.by_journey = by_journey;
-
         // RPS
         run_report.rps.planned_avg = workload.get_rps_avg() as u64;
         run_report.rps.achieved_avg_including_drain = (metrics.total_requests as f64 / run_report.time.real_time_duration_sec as f64) as u64;
         run_report.rps.achieved_avg = (metrics.total_requests as f64 / run_report.time.real_time_duration_sec as f64 - drain_time as f64 / run_report.time.real_time_duration_sec as f64) as u64;
-        run_report.rps.by_stage = metrics.by_stage;
-        // Error and quality
-        run_report.error_and_quality.http_error_rate = ((metrics.error_requests as f64 / metrics.total_requests as f64)*100.00_f64).round();
 
         // Vu Utilization
         run_report.vus.count = vus as u64;
+        run_report.vus.iterations = pool.get_total_iterations();
         run_report.vus.no_ready_ratio = (run_report.vus.no_ready_ticks as f64 / total_ticks as f64).round();
 
-        run_report.scenario = Scenario{ name: scenario.name.clone(), version: scenario.version.to_string() };
-
+        run_report.run.mode = "open".to_string();
         run_report.run.total_ticks = total_ticks;
         run_report.run.duration_sec_planned = scheduler.planned_duration_sec as u64;
 
-        run_report.requests.total = metrics.total_requests;
-        run_report.requests.ok = metrics.ok_requests;
-        run_report.requests.error = metrics.error_requests;
+        run_report.sleep = pool.get_total_sleep_ms();
 
-        // Latency by stage
-        run_report.latency_by_stage = metrics.latency_by_stage.iter()
-            .map(|(stage_index, hist)| (*stage_index, LatencySummary::summarize(hist))).collect();
+        run_report.missed_tick_count = missed_ticks as u16;
 
-        // Latency by endpoint
-        for (key, rec) in run_report.by_endpoint.iter_mut() {
-            let lat_summ = metrics
-                .latency_by_endpoint
-                .get(key)
-                .expect("histogram missing for endpoint");
+        finish_report(run_report, metrics, scenario, &journey_per_vu)
+    }
 
-            rec.latency_summary = LatencySummary::summarize(lat_summ);
+    pub(crate) fn executor(&self, plan: &ExecutionPlan) -> Arc<Box<dyn ExecutorAbstract>> {
+        let executor_instance: Box<dyn ExecutorAbstract> = match self.is_mock {
+            true => ExecutorMock::new_instance(),
+            false => ExecutorHttp::new_instance(&plan.tls)
+                .expect("client configuration is checked before the run")
+        };
+        Arc::new(executor_instance)
+    }
+}
+
+/// Creates one VU per id, each bound to a journey picked by the weight sampler.
+/// Returns the pool and the number of VUs per journey id.
+pub(crate) fn build_pool(plan: &ExecutionPlan, vu_ids: impl IntoIterator<Item = u32>) -> (VuPool, BTreeMap<usize, u64>) {
+    let sampler = &plan.weight_sampler;
+    let mut journey_per_vu: BTreeMap<usize, u64> = BTreeMap::new();
+    let mut pool_vec = Vec::new();
+    for i in vu_ids {
+        let stable_key = format!("{}-{}", i, sampler.seed);
+        if let Some(journey_id) = sampler.peek(&stable_key){
+            // metrics, journey_per_vu
+            journey_per_vu.entry(journey_id as usize).and_modify(|journey_count| *journey_count += 1).or_insert(1);
+
+            pool_vec.push(VUState::new(i, journey_id as u32));
+        };
+    }
+    (VuPool::new(pool_vec), journey_per_vu)
+}
+
+/// Fills the model-independent parts of the report from the aggregated metrics and evaluates thresholds.
+pub(crate) fn finish_report(mut run_report: RunReport, metrics: MetricsAggregator, scenario: &libprotocol::schema::Scenario, journey_per_vu: &BTreeMap<usize, u64>) -> RunReport {
+    // By endpoint
+    run_report.by_endpoint = metrics.by_endpoint;
+
+    // By journeys
+    let by_journey = metrics.by_journey.iter().map(|(key, (journey_id, requests))| {
+        let per_vu = journey_per_vu.get(journey_id).unwrap_or(&0);
+        let latency_summary = metrics.latency_by_journey.get(key)
+            .map(LatencySummary::summarize)
+            .unwrap_or_default();
+        ByJourney {
+            id: *journey_id,
+            key: key.clone(),
+            per_vu: *per_vu,
+            per_request: requests.total,
+            request: *requests,
+            latency_summary,
         }
+    }).collect::<Vec<ByJourney>>();
 
+    run_report.by_journey = by_journey;
 
-        run_report.latency_overall_summary = LatencySummary::summarize(&metrics.overall_latency);
+    run_report.rps.by_stage = metrics.by_stage;
+    // a closed model has no planned rate
+    if !matches!(scenario.workload.executor, Some(WorkloadExecutor::ConstantVus { .. })) {
+        for (stage_index, stage) in run_report.rps.by_stage.iter_mut() {
+            stage.planned_rps = scenario.workload.stages.get(*stage_index as usize)
+                .map(|planned| planned.avg_rps().max(0.0) as u64)
+                .unwrap_or_default();
+        }
+    }
+    run_report.timeseries = metrics.timeseries.report();
+    // Error and quality
+    run_report.error_and_quality.http_error_rate = ((metrics.error_requests as f64 / metrics.total_requests as f64)*100.00_f64).round();
 
-        run_report.sleep = pool.get_total_sleep_ms();
+    run_report.scenario = Scenario{ name: scenario.name.clone(), version: scenario.version.to_string() };
 
-        run_report.missed_tick_count = missed_ticks as u16;
+    run_report.requests.total = metrics.total_requests;
+    run_report.requests.ok = metrics.ok_requests;
+    run_report.requests.error = metrics.error_requests;
+
+    // Latency by stage
+    run_report.latency_by_stage = metrics.latency_by_stage.iter()
+        .map(|(stage_index, hist)| (*stage_index, LatencySummary::summarize(hist))).collect();
+
+    // Latency by endpoint
+    for (key, rec) in run_report.by_endpoint.iter_mut() {
+        let lat_summ = metrics
+            .latency_by_endpoint
+            .get(key)
+            .expect("histogram missing for endpoint");
 
-        run_report
+        rec.latency_summary = LatencySummary::summarize(lat_summ);
     }
+
+
+    run_report.latency_overall_summary = LatencySummary::summarize(&metrics.overall_latency);
+
+    // Thresholds
+    run_report.thresholds = thresholds::evaluate(scenario.thresholds.as_deref().unwrap_or_default(), &run_report);
+
+    run_report
 }
 
-#[derive(Debug, Serialize)]
+/// Outcome of `run` and `run-mock`, printed as JSON. Versioned by `report_version`, see `crate::report`.
+/// Latencies are in ms, times are ms since the start of the run unless stated otherwise.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct RunReport {
-    scenario: Scenario,
-    run: Run,
-    ticks_arrival: TicksArrival,
-    rps: Rps,
-    journeys: Vec<Journey>,
-    requests: Requests,
-    latency_overall_summary: LatencySummary,
-    latency_by_stage: BTreeMap<u64, LatencySummary>,
-    time: Time,
-    missed_tick_count: u16,
-    by_endpoint: BTreeMap<String, EndpointStats>,
-    by_journey: Vec<ByJourney>,
-    sleep: u64,
-    error_and_quality: ErrorAndQuality,
-    vus: VuUtilization
+    /// Shape of the report, `crate::report::REPORT_VERSION`; 0 for reports saved before versioning
+    #[serde(default)]
+    pub report_version: u32,
+    pub scenario: Scenario,
+    pub run: Run,
+    pub ticks_arrival: TicksArrival,
+    pub rps: Rps,
+    /// Not filled yet, always empty. See `by_journey`
+    pub journeys: Vec<Journey>,
+    /// Every completed request
+    pub requests: Requests,
+    pub latency_overall_summary: LatencySummary,
+    /// stage index → latency
+    pub latency_by_stage: BTreeMap<u64, LatencySummary>,
+    pub time: Time,
+    /// `ticks_arrival.missed` cast to `u16`
+    pub missed_tick_count: u16,
+    /// `"METHOD /path"` → stats
+    pub by_endpoint: BTreeMap<String, EndpointStats>,
+    pub by_journey: Vec<ByJourney>,
+    /// Total think time of the VUs, ms
+    pub sleep: u64,
+    pub error_and_quality: ErrorAndQuality,
+    pub vus: VuUtilization,
+    /// Metrics per `report.timeseries_interval_ms`
+    pub timeseries: TimeSeriesReport,
+    pub thresholds: ThresholdsSummary,
 }
 
-
 impl RunReport {
     pub fn new(scheduler: &Scheduler) -> Self {
     Self{
+        report_version: REPORT_VERSION,
         scenario: Scenario { name: "".to_string(), version: "".to_string() },
         run: Default::default(),
         ticks_arrival: Default::default(),
@@ -413,28 +486,48 @@ impl RunReport {
         by_journey: vec![],
         sleep: 0,
         error_and_quality: ErrorAndQuality { http_error_rate: 0.0 },
-        vus: VuUtilization { count: 0, no_ready_ticks: 0, no_ready_ratio: 0.0 }
+        vus: VuUtilization { count: 0, iterations: 0, no_ready_ticks: 0, no_ready_ratio: 0.0 },
+        timeseries: TimeSeriesReport::default(),
+        thresholds: ThresholdsSummary::default(),
     } }
+
+    pub fn thresholds(&self) -> &ThresholdsSummary {
+        &self.thresholds
+    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct VuUtilization {
-    count: u64,
-    no_ready_ticks: u64,
-    no_ready_ratio: f64
+    /// Virtual users in the pool
+    pub count: u64,
+    /// Journey iterations the VUs completed
+    pub iterations: u64,
+    /// Ticks whose picked VU had nothing to send, open model only
+    pub no_ready_ticks: u64,
+    /// `no_ready_ticks` over all ticks, as a ratio rounded to 0 or 1
+    pub no_ready_ratio: f64
 }
-#[derive(Debug, Serialize)]
+/// Requests of one stage, keyed by the stage of the tick that started them
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ByStage {
+    /// 0-based, in `workload.stages` order
     pub stage_index: u64,
+    /// Average rate the stage was planned at, requests per second, 0 for a closed model
+    #[serde(default)]
+    pub planned_rps: u64,
+    /// `request_count` over `stage_duration_ms`, requests per second
     pub achieved_rps: u64,
     pub request_count: u64,
+    /// When the stage started
     pub stage_started_ms: u64,
+    /// From `stage_started_ms` to the start of its last request
     pub stage_duration_ms: u64,
 }
 impl Default for ByStage {
     fn default() -> ByStage {
         ByStage {
             stage_index: 0,
+            planned_rps: 0,
             achieved_rps: 0,
             request_count: 1,
             stage_started_ms: 0,
@@ -442,14 +535,21 @@ impl Default for ByStage {
         }
     }
 }
-#[derive(Debug, Serialize, Copy, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct EndpointStats {
     pub request: Requests,
     pub latency_summary: LatencySummary,
+    /// Requests per second between `first_at_ms` and `last_at_ms`, rounded
     pub achieved_rps: f64,
+    /// Start of the first request to the endpoint
     pub first_at_ms: u64,
+    /// Start of the last request to the endpoint
     pub last_at_ms: u64,
-    pub count: u64
+    /// Same as `request.total`
+    pub count: u64,
+    /// check label → pass/fail counts
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub checks: BTreeMap<String, CheckCounts>,
 }
 impl EndpointStats {
     pub(crate) fn default() -> EndpointStats {
@@ -464,52 +564,92 @@ impl EndpointStats {
             first_at_ms: 0,
             last_at_ms: 0,
             count: 0,
+            checks: BTreeMap::new(),
         }
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ErrorAndQuality {
-    http_error_rate: f64,
+    /// Failed requests as a percentage of all requests, 0 to 100, rounded to a whole number.
+    /// Thresholds on `http.error_rate` use the unrounded value
+    pub http_error_rate: f64,
 }
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Time {
+    /// Not filled yet, always 0
     pub planned_start_ms: u64,
+    /// Not filled yet, always 0
     pub planned_end_ms: u64,
+    /// Sum of the stage durations, or the `constant_vus` duration
     pub planned_duration_ms: u64,
+    /// `planned_duration_ms` in seconds
     pub planned_duration_sec: f64,
+    /// Wall-clock length of the run in whole seconds, draining the last requests included
     pub real_time_duration_sec: u64
 }
-#[derive(Debug, Serialize, Default)]
+/// Rates in requests per second
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
 pub struct Rps {
+    /// Average planned rate over the stages, 0 for a closed model
     pub planned_avg: u64,
+    /// Completed requests over the run duration, the drain after the last tick excluded
     pub achieved_avg: u64,
+    /// Completed requests over `time.real_time_duration_sec`
     pub achieved_avg_including_drain: u64,
+    /// stage index → rates
     pub by_stage: BTreeMap<u64, ByStage>,
 }
-#[derive(Debug, Serialize, Default)]
+/// How the scheduler ticks of the open model were served. Empty for a closed model
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
 pub struct TicksArrival {
+    /// Ticks the scheduler emitted before the run ended
     pub total: u64,
+    /// Completed requests
     pub executed: u64,
+    /// Ticks without a ready VU, no request was sent for them
     pub missed: u64,
+    /// `missed` over `total`, as a ratio from 0.0 to 1.0
     pub missed_ratio: f64,
+    /// Average wall-clock time between two ticks
     pub tick_interval_ms: u64,
+    /// Wall-clock time from the start of the engine to the first tick
     pub first_tick_ms: u64,
+    /// When the last tick was handled
     pub last_tick_ms: u64
 }
-#[derive(Debug, Serialize)]
-struct ByJourney {
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ByJourney {
+    /// Index of the journey in the scenario
     pub id: usize,
+    /// Journey name
     pub key: String,
+    /// VUs bound to the journey
     pub per_vu: u64,
+    /// Same as `request.total`
     pub per_request: u64,
+    pub request: Requests,
+    pub latency_summary: LatencySummary,
+}
+
+/// Service time, from the moment each request was actually sent. `count` is a number of requests,
+/// the other values are ms, truncated from µs
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Copy, Clone, Default)]
+pub struct LatencySummary {
+    pub count: u64, pub min: u64, pub max: u64, pub mean: u64, pub p50: u64, pub p90: u64, pub p95: u64, pub p99: u64,
+    /// From the intended start of each request, corrected for coordinated omission
+    #[serde(default)]
+    pub corrected: CorrectedLatency,
 }
 
-#[derive(Debug, Serialize, Copy, Clone, Default)]
-pub struct LatencySummary { count: u64, min: u64, max: u64, mean: u64, p50: u64, p90: u64, p95: u64, p99: u64 }
+/// What users of a stalled target would see, in ms like `LatencySummary`, one sample per request
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Copy, Clone, Default)]
+pub struct CorrectedLatency { pub count: u64, pub max: u64, pub mean: u64, pub p50: u64, pub p90: u64, pub p95: u64, pub p99: u64 }
 
 impl LatencySummary {
-    pub fn summarize(histogram: &Histogram<u64>) -> Self {
+    pub fn summarize(histograms: &LatencyHistograms) -> Self {
+        let histogram = &histograms.service;
+        let corrected = &histograms.corrected;
         LatencySummary{
             count: histogram.len(),
             min: histogram.min()/1000,
@@ -519,36 +659,62 @@ impl LatencySummary {
             p90: histogram.value_at_quantile(0.90)/1000,
             p95: histogram.value_at_quantile(0.95)/1000,
             p99: histogram.value_at_quantile(0.99)/1000,
+            corrected: CorrectedLatency {
+                count: corrected.len(),
+                max: corrected.max()/1000,
+                mean: corrected.mean() as u64/1000,
+                p50: corrected.value_at_quantile(0.50)/1000,
+                p90: corrected.value_at_quantile(0.90)/1000,
+                p95: corrected.value_at_quantile(0.95)/1000,
+                p99: corrected.value_at_quantile(0.99)/1000,
+            },
         }
     }
 }
 
-#[derive(Debug, Serialize)]
-struct Scenario {
-    name: String,
-    version: String
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Scenario {
+    pub name: String,
+    /// Scenario format version
+    pub version: String
 }
-#[derive(Debug, Serialize, Default)]
-struct Run {
-    mode: String,
-    seed: String,
-    total_ticks: u64,
-    duration_sec_planned: u64
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
+pub struct Run {
+    /// `open` for the stage-driven rate, `constant_vus` for the closed model
+    pub mode: String,
+    /// Not filled yet, always empty
+    pub seed: String,
+    /// Ticks the scheduler emitted, 0 for a closed model
+    pub total_ticks: u64,
+    /// Planned duration in whole seconds
+    pub duration_sec_planned: u64
 }
-#[derive(Debug, Serialize)]
-struct Journey {
-    name: String,
-    weight: usize,
-    picked: u32,
-    share: String
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Journey {
+    pub name: String,
+    /// `weight` of the scenario journey
+    pub weight: usize,
+    /// How many times the journey was picked
+    pub picked: u32,
+    /// `picked` as a percentage of all picks, formatted
+    pub share: String
 }
-#[derive(Debug, Serialize, Copy, Clone, Default)]
-pub(crate) struct Requests {
+/// Request counts. A request is an error when it got no response, failed a check, or got a non-2xx
+/// status without a `status` check
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Copy, Clone, Default, PartialEq)]
+pub struct Requests {
     pub total: u64,
     pub ok: u64,
     pub error: u64
 }
 
+/// Requests that passed or failed one check
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Copy, Clone, Default)]
+pub struct CheckCounts {
+    pub passed: u64,
+    pub failed: u64,
+}
+
 #[cfg(test)]
 mod tests {
     use crate::execution_plan::ExecutionPlan;
@@ -556,21 +722,37 @@ mod tests {
     use libprotocol::Scenario;
     use test_support::fixture_path;
     use crate::events::{Event, EventSink};
+    use crate::raw_results::RawResults;
+    use crate::test_runs::{fixture_scenario, mock_report, RUN_ENGINE_FIXTURE};
 
     #[tokio::test]
     async fn it_run_mock_and_check_run_report() {
-        let path = fixture_path("crates/libruntime/tests/fixtures/valid-extended-scenario-for_check_run_engine.json");
-        let content = std::fs::read_to_string(&path).unwrap();
-        let scenario: Scenario = serde_json::from_str(&content).unwrap();
-        let execution_plan = ExecutionPlan::from(&scenario);
-        let sink = EventSink::<Event>::noop();
-
-        let mut report = RunEngine::new(Some(true), Some(false)).run(&execution_plan, &scenario, sink).await;
+        let mut report = mock_report(RUN_ENGINE_FIXTURE).await;
         report.time.real_time_duration_sec = 3; // flaky test
         insta::assert_debug_snapshot!(report);
         println!("{}", serde_json::to_string_pretty(&report).unwrap());
     }
 
+    #[tokio::test]
+    async fn it_writes_one_raw_record_per_request() {
+        let scenario = fixture_scenario(RUN_ENGINE_FIXTURE);
+        let execution_plan = ExecutionPlan::from(&scenario);
+        let raw_path = std::env::temp_dir().join(format!("rload-engine-{}.ndjson", std::process::id()));
+        let (raw, writer) = RawResults::create(&raw_path).await.unwrap();
+
+        let engine = RunEngine::new(Some(true), Some(false)).with_raw_results(raw);
+        let report = engine.run(&execution_plan, &scenario, EventSink::<Event>::noop()).await;
+        drop(engine);
+        writer.await.unwrap().unwrap();
+
+        let content = std::fs::read_to_string(&raw_path).unwrap();
+        std::fs::remove_file(&raw_path).unwrap();
+        let records: Vec<serde_json::Value> = content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(report.requests.total, records.len() as u64);
+        let errors = records.iter().filter(|r| !r["error"].is_null()).count() as u64;
+        assert_eq!(report.requests.error, errors);
+    }
+
     #[tokio::test]
     #[ignore] // Non deterministic
     async fn it_run_and_check_run_report() {
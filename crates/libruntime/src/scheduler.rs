@@ -1,5 +1,6 @@
-use libprotocol::schema::{Stage, Workload};
+use libprotocol::schema::{Arrival, Stage, Workload};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::str;
 
@@ -13,12 +14,21 @@ pub struct Scheduler {
     stage_max_ticks: HashMap<usize, i32>,
     pub(crate) planned_duration_ms: u64,
     pub(crate) planned_duration_sec: f64,
+    seed: String,
+    /// Poisson arrival: sum of the exponential spacings of the current stage and the part consumed so far
+    spacing_total: f64,
+    spacing_acc: f64,
 }
 
 impl Scheduler {
     pub fn new(workload: &Workload) -> Self {
+        Self::with_seed(workload, "")
+    }
+
+    /// Randomized arrival modes derive their spacing from `seed`, same seed gives the same ticks
+    pub fn with_seed(workload: &Workload, seed: &str) -> Self {
         let stage_max_ticks: HashMap<usize, i32> = workload.stages.iter().enumerate()
-            .map(|(i, stage)| (i, stage.duration_sec * stage.rps)).collect();
+            .map(|(i, stage)| (i, stage.planned_ticks())).collect();
         let planned_duration_ms: i32 = workload.stages.iter()
             .map(| stage| stage.duration_sec * 1000).sum();
         let planned_duration_sec: i32 = workload.stages.iter()
@@ -36,11 +46,94 @@ impl Scheduler {
             total_ticks,
             planned_duration_ms: planned_duration_ms as u64,
             planned_duration_sec: planned_duration_sec as f64,
+            seed: seed.to_string(),
+            spacing_total: 0.0,
+            spacing_acc: 0.0,
         }
     }
     pub fn get_stage_max_ticks(&self, stage_index: usize) -> Option<i32> {
         self.stage_max_ticks.get(&stage_index).copied()
     }
+
+    pub fn stage_plans(&self) -> Vec<StagePlan> {
+        let mut start_ms = 0;
+        self.stages.iter().enumerate().map(|(i, stage)| {
+            let plan = StagePlan {
+                stage_index: i as u64,
+                start_ms,
+                duration_sec: stage.duration_sec as u64,
+                start_rps: stage.rps as u32,
+                end_rps: stage.end_rps() as u32,
+                arrival: stage.arrival(),
+                planned_ticks: self.get_stage_max_ticks(i).unwrap_or(0) as u64,
+            };
+            start_ms += stage.duration_sec as u64 * 1000;
+            plan
+        }).collect()
+    }
+
+    /// Uniform value in [0, 1) derived from the seed, the stage and the tick
+    fn unit(&self, stage_index: usize, tick_in_stage: u64) -> f64 {
+        let key = format!("stage={stage_index}:tick={tick_in_stage}:seed={}", self.seed);
+        let digest = Sha256::digest(key.as_bytes());
+        let first8: [u8; 8] = digest[0..8].try_into().unwrap();
+
+        (u64::from_be_bytes(first8) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Exponential spacing with mean 1
+    fn spacing(&self, stage_index: usize, tick_in_stage: u64) -> f64 {
+        -(1.0 - self.unit(stage_index, tick_in_stage)).ln()
+    }
+
+    /// Fractional position of the tick in the stage, in ticks (0..planned_ticks).
+    fn tick_position(&mut self, stage: &Stage, tick_in_stage: u64) -> f64 {
+        match stage.arrival() {
+            Arrival::Constant => tick_in_stage as f64,
+            Arrival::UniformJitter => tick_in_stage as f64 + self.unit(self.current_stage_index, tick_in_stage),
+            Arrival::Poisson => {
+                // The tick count of the stage is kept: given their count, Poisson arrivals are
+                // cumulative exponential spacings normalized by the sum of one spacing more.
+                let ticks = stage.planned_ticks().max(0) as u64;
+                if tick_in_stage == 0 {
+                    self.spacing_total = (0..=ticks).map(|k| self.spacing(self.current_stage_index, k)).sum();
+                    self.spacing_acc = 0.0;
+                }
+                self.spacing_acc += self.spacing(self.current_stage_index, tick_in_stage);
+                if self.spacing_total <= 0.0 {
+                    return tick_in_stage as f64;
+                }
+                ticks as f64 * self.spacing_acc / self.spacing_total
+            }
+        }
+    }
+}
+
+/// Offset of the k-th tick from the stage start and the rate at that moment.
+/// On a ramp the rate changes linearly, so the k-th tick is planned where the
+/// integral of the rate (`rps * t + slope * t^2 / 2`) reaches k.
+fn tick_offset(stage: &Stage, tick_in_stage: u64) -> (u64, u32) {
+    if stage.end_rps() == stage.rps {
+        return (tick_in_stage * 1000 / (stage.rps as u64), stage.rps as u32);
+    }
+    position_offset(stage, tick_in_stage as f64)
+}
+
+/// Same as `tick_offset` for a fractional position, used by the randomized arrival modes.
+fn position_offset(stage: &Stage, k: f64) -> (u64, u32) {
+    if k <= 0.0 {
+        return (0, stage.rps as u32);
+    }
+    let start = stage.rps as f64;
+    if stage.end_rps() == stage.rps {
+        return ((k * 1000.0 / start) as u64, stage.rps as u32);
+    }
+    let slope = (stage.end_rps() - stage.rps) as f64 / stage.duration_sec as f64;
+    // stable root of the quadratic, valid for ramping up and down
+    let t = 2.0 * k / (start + (start * start + 2.0 * slope * k).max(0.0).sqrt());
+    let rate = start + slope * t;
+
+    ((t * 1000.0) as u64, rate.round() as u32)
 }
 impl Iterator for &mut Scheduler {
     type Item = Tick;
@@ -65,23 +158,41 @@ impl Iterator for &mut Scheduler {
             return None;
         }
 
-        let stage = self.stages.get(self.current_stage_index).expect("stage not found");
+        let stage = self.stages.get(self.current_stage_index).expect("stage not found").clone();
 
         let tick_in_stage = self.current_step_index as u64;
 
         self.current_step_index+=1;
-        let tick_offset_ms = tick_in_stage * 1000 / (stage.rps as u64);
+        let (tick_offset_ms, target_rps) = match stage.arrival() {
+            Arrival::Constant => tick_offset(&stage, tick_in_stage),
+            _ => {
+                let position = self.tick_position(&stage, tick_in_stage);
+                position_offset(&stage, position)
+            }
+        };
 
         Some(Tick{
             tick_index: tick_in_stage,
             stage_index: self.current_stage_index as u64,
             planned_at_ms: self.stage_start_ms + tick_offset_ms,
-            target_rps: stage.rps as u32,
+            target_rps,
             is_new_stage,
         })
     }
 }
 
+/// Per-stage summary of the plan, used by the dry-run report
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StagePlan {
+    pub stage_index: u64,
+    pub start_ms: u64,
+    pub duration_sec: u64,
+    pub start_rps: u32,
+    pub end_rps: u32,
+    pub arrival: Arrival,
+    pub planned_ticks: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 pub struct Tick {
     pub tick_index: u64,
@@ -94,7 +205,7 @@ pub struct Tick {
 #[cfg(test)]
 mod tests {
     use crate::scheduler::{Scheduler, Tick};
-    use libprotocol::schema::{Stage, Workload};
+    use libprotocol::schema::{Arrival, Stage, Workload};
 
     #[test]
     fn it_one_stage_2sec_5rps() {
@@ -103,11 +214,16 @@ mod tests {
                 Stage {
                     duration_sec: 2,
                     rps: 5,
+                    target_rps: None,
+                    arrival: None,
                 },
                 Stage {
                     duration_sec: 1,
                     rps: 3,
+                    target_rps: None,
+                    arrival: None,
                 }),
+            executor: None,
         };
         let mut ticks: Vec<Tick> = Vec::new();
         let scheduler: &mut Scheduler = &mut Scheduler::new(&workload);
@@ -123,11 +239,16 @@ mod tests {
                 Stage {
                     duration_sec: 2,
                     rps: 5,
+                    target_rps: None,
+                    arrival: None,
                 },
                 Stage {
                     duration_sec: 2,
                     rps: 5,
-                })
+                    target_rps: None,
+                    arrival: None,
+                }),
+            executor: None,
         };
         let scheduler: &mut Scheduler = &mut Scheduler::new(&workload);
         let delta = 200;
@@ -139,4 +260,123 @@ mod tests {
             prev_planned_time = tick.planned_at_ms
         }
     }
+
+    #[test]
+    fn it_ramp_interpolates_tick_spacing() {
+        let workload = Workload {
+            stages: vec!(
+                Stage {
+                    duration_sec: 10,
+                    rps: 10,
+                    target_rps: Some(100),
+                    arrival: None,
+                }),
+            executor: None,
+        };
+        let scheduler: &mut Scheduler = &mut Scheduler::new(&workload);
+        assert_eq!(550, scheduler.total_ticks);
+
+        let ticks: Vec<Tick> = scheduler.into_iter().collect();
+        assert_eq!(550, ticks.len());
+        assert_eq!(10, ticks.first().unwrap().target_rps);
+        assert!(ticks.last().unwrap().target_rps >= 99);
+        assert!(ticks.last().unwrap().planned_at_ms < 10_000);
+
+        // spacing shrinks while the rate grows
+        let first_gap = ticks[1].planned_at_ms - ticks[0].planned_at_ms;
+        let last_gap = ticks[549].planned_at_ms - ticks[548].planned_at_ms;
+        assert!(first_gap > last_gap);
+        for pair in ticks.windows(2) {
+            assert!(pair[0].planned_at_ms <= pair[1].planned_at_ms);
+            assert!(pair[0].target_rps <= pair[1].target_rps);
+        }
+    }
+
+    #[test]
+    fn it_ramp_down_to_zero() {
+        let workload = Workload {
+            stages: vec!(
+                Stage {
+                    duration_sec: 2,
+                    rps: 10,
+                    target_rps: Some(0),
+                    arrival: None,
+                },
+                Stage {
+                    duration_sec: 1,
+                    rps: 4,
+                    target_rps: None,
+                    arrival: None,
+                }),
+            executor: None,
+        };
+        let mut ticks: Vec<Tick> = Vec::new();
+        let scheduler: &mut Scheduler = &mut Scheduler::new(&workload);
+        for tick in scheduler.into_iter() {
+            ticks.push(tick)
+        }
+        insta::assert_debug_snapshot!(ticks)
+    }
+
+    fn randomized(arrival: Arrival, seed: &str) -> Vec<Tick> {
+        let workload = Workload {
+            stages: vec!(
+                Stage {
+                    duration_sec: 10,
+                    rps: 100,
+                    target_rps: None,
+                    arrival: Some(arrival),
+                },
+                Stage {
+                    duration_sec: 10,
+                    rps: 100,
+                    target_rps: Some(200),
+                    arrival: Some(arrival),
+                }),
+            executor: None,
+        };
+        let scheduler: &mut Scheduler = &mut Scheduler::with_seed(&workload, seed);
+        scheduler.into_iter().collect()
+    }
+
+    #[test]
+    fn it_randomized_arrival_keeps_ticks_inside_stages() {
+        for arrival in [Arrival::Poisson, Arrival::UniformJitter] {
+            let ticks = randomized(arrival, "1000");
+            assert_eq!(1000 + 1500, ticks.len());
+            for pair in ticks.windows(2) {
+                assert!(pair[0].planned_at_ms <= pair[1].planned_at_ms, "{arrival:?}");
+            }
+            for tick in &ticks {
+                let stage_start = tick.stage_index * 10_000;
+                assert!(tick.planned_at_ms >= stage_start, "{arrival:?}");
+                assert!(tick.planned_at_ms < stage_start + 10_000, "{arrival:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn it_randomized_arrival_is_reproducible_by_seed() {
+        let planned = |ticks: Vec<Tick>| ticks.iter().map(|t| t.planned_at_ms).collect::<Vec<u64>>();
+
+        assert_eq!(planned(randomized(Arrival::Poisson, "1000")), planned(randomized(Arrival::Poisson, "1000")));
+        assert_ne!(planned(randomized(Arrival::Poisson, "1000")), planned(randomized(Arrival::Poisson, "1001")));
+        assert_ne!(planned(randomized(Arrival::UniformJitter, "1000")), planned(randomized(Arrival::UniformJitter, "1001")));
+    }
+
+    #[test]
+    fn it_poisson_gaps_are_exponential() {
+        let ticks: Vec<Tick> = randomized(Arrival::Poisson, "1000").into_iter()
+            .filter(|t| t.stage_index == 0)
+            .collect();
+        let gaps: Vec<f64> = ticks.windows(2)
+            .map(|pair| (pair[1].planned_at_ms - pair[0].planned_at_ms) as f64)
+            .collect();
+        let mean = gaps.iter().sum::<f64>() / gaps.len() as f64;
+        let variance = gaps.iter().map(|g| (g - mean).powi(2)).sum::<f64>() / gaps.len() as f64;
+
+        // 100 rps: mean gap of 10ms, and for exponential gaps the deviation equals the mean
+        assert!((mean - 10.0).abs() < 0.5, "mean {mean}");
+        assert!((variance.sqrt() / mean - 1.0).abs() < 0.15, "cv {}", variance.sqrt() / mean);
+    }
 }
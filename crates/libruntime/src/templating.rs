@@ -0,0 +1,142 @@
+use crate::execution_plan::ExecutionPlan;
+use crate::vu_runner::VUState;
+use libprotocol::template::{has_placeholders, Placeholder, Template};
+use sha2::{Digest, Sha256};
+
+/// Renders the placeholders of one request.
+/// Random values are derived from the plan `random_seed`, the VU, its iteration and the step,
+/// so runs with an explicit seed are reproducible while every request still gets its own values.
+pub(crate) struct RenderContext<'a> {
+    plan: &'a ExecutionPlan,
+    vu: &'a VUState,
+    /// Distinguishes random placeholders within one request
+    draws: u32,
+}
+
+impl<'a> RenderContext<'a> {
+    pub(crate) fn new(plan: &'a ExecutionPlan, vu: &'a VUState) -> Self {
+        RenderContext { plan, vu, draws: 0 }
+    }
+
+    pub(crate) fn render(&mut self, input: &str) -> String {
+        if !has_placeholders(input) {
+            return input.to_string();
+        }
+        let plan = self.plan;
+        if let Some(template) = plan.templates.get(input) {
+            return template.render(|placeholder| self.resolve(placeholder));
+        }
+        // the validator rejects broken templates, keep the raw value if one gets here anyway
+        let Ok(template) = Template::parse(input) else {
+            return input.to_string();
+        };
+        template.render(|placeholder| self.resolve(placeholder))
+    }
+
+    fn resolve(&mut self, placeholder: &Placeholder) -> String {
+        match placeholder {
+            Placeholder::VuId => self.vu.vu_id.to_string(),
+            Placeholder::Iteration => self.vu.iteration_count.to_string(),
+            Placeholder::RandomInt { min, max } => {
+                let span = (*max as i128 - *min as i128 + 1) as u128;
+                let n = u64::from_be_bytes(self.random_bytes()[0..8].try_into().unwrap());
+                (*min as i128 + (n as u128 % span) as i128).to_string()
+            }
+            Placeholder::Uuid => format_uuid(self.random_bytes()),
+            Placeholder::Env(name) => std::env::var(name).unwrap_or_default(),
+            Placeholder::Data { source, column } => self.plan.data.value(self.vu, source, column)
+                .unwrap_or_default()
+                .to_string(),
+            // extracted values shadow scenario variables
+            Placeholder::Var(name) => self.vu.vars.get(name)
+                .or_else(|| self.plan.variables.get(name))
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+
+    fn random_bytes(&mut self) -> [u8; 16] {
+        self.draws += 1;
+        let key = format!(
+            "{}-vu={}-iteration={}-step={}-draw={}:seed={}",
+            self.plan.scenario_name, self.vu.vu_id, self.vu.iteration_count, self.vu.step_index, self.draws, self.plan.random_seed
+        );
+        Sha256::digest(key.as_bytes())[0..16].try_into().unwrap()
+    }
+}
+
+fn format_uuid(mut bytes: [u8; 16]) -> String {
+    // version 4, RFC 4122 variant
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::execution_plan::ExecutionPlan;
+    use crate::templating::RenderContext;
+    use crate::vu_runner::VUState;
+    use libprotocol::Scenario;
+    use std::collections::BTreeMap;
+
+    fn plan() -> ExecutionPlan {
+        let mut plan = ExecutionPlan::from(&Scenario::default());
+        plan.variables = BTreeMap::from([("tenant".to_string(), "acme".to_string())]);
+        plan
+    }
+
+    fn vu(vu_id: u32, iteration: u64) -> VUState {
+        let mut vu = VUState::new(vu_id, 0);
+        vu.iteration_count = iteration;
+        vu
+    }
+
+    #[test]
+    fn it_renders_placeholders() {
+        let plan = plan();
+        let mut vu = vu(7, 3);
+        vu.vars.insert("token".to_string(), "abc".to_string());
+        let mut ctx = RenderContext::new(&plan, &vu);
+
+        assert_eq!("/static", ctx.render("/static"));
+        assert_eq!("/acme/users/7?i=3", ctx.render("/{{tenant}}/users/{{ vu_id }}?i={{iteration}}"));
+        assert_eq!("", ctx.render("{{env.RLOAD_SURELY_NOT_SET}}"));
+        assert_eq!("Bearer abc", ctx.render("Bearer {{token}}"));
+
+        let n: i64 = ctx.render("{{random_int(1,1000)}}").parse().unwrap();
+        assert!((1..=1000).contains(&n));
+        assert_eq!("5", ctx.render("{{random_int(5,5)}}"));
+
+        let uuid = ctx.render("{{uuid}}");
+        assert_eq!(36, uuid.len());
+        assert_eq!(Some('4'), uuid.chars().nth(14));
+    }
+
+    #[test]
+    fn it_renders_reproducible_random_values() {
+        let plan = plan();
+        let render = |vu_id: u32, iteration: u64| {
+            RenderContext::new(&plan, &vu(vu_id, iteration)).render("{{uuid}} {{uuid}} {{random_int(0,1000000)}}")
+        };
+
+        assert_eq!(render(1, 1), render(1, 1));
+        assert_ne!(render(1, 1), render(1, 2));
+        assert_ne!(render(1, 1), render(2, 1));
+
+        let values: Vec<String> = render(1, 1).split(' ').map(str::to_string).collect();
+        assert_ne!(values[0], values[1]);
+    }
+
+    #[test]
+    fn it_draws_other_random_values_on_every_run_unless_seeded() {
+        let render = |plan: &ExecutionPlan| RenderContext::new(plan, &vu(1, 1)).render("{{uuid}}");
+
+        assert_ne!(render(&plan()), render(&plan()));
+        let seeded = crate::plan(&Scenario::default(), 7);
+        assert_eq!(render(&seeded), render(&crate::plan(&Scenario::default(), 7)));
+        assert_ne!(render(&seeded), render(&crate::plan(&Scenario::default(), 8)));
+    }
+}
@@ -0,0 +1,27 @@
+//! Mock runs of the unit test fixtures, shared by the tests of the report consumers
+
+use crate::events::{Event, EventSink};
+use crate::execution_plan::ExecutionPlan;
+use crate::run_engine::{RunEngine, RunReport};
+use libprotocol::Scenario;
+use test_support::fixture_path;
+
+/// The run engine fixture most report tests start from
+pub(crate) const RUN_ENGINE_FIXTURE: &str = "valid-extended-scenario-for_check_run_engine.json";
+
+/// A scenario of `crates/libruntime/tests/fixtures`, deserialized as it is
+pub(crate) fn fixture_scenario(name: &str) -> Scenario {
+    let path = fixture_path(&format!("crates/libruntime/tests/fixtures/{}", name));
+    serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap()
+}
+
+/// Runs `scenario` with the mock executor in virtual time
+pub(crate) async fn mock_run(scenario: &Scenario) -> RunReport {
+    let execution_plan = ExecutionPlan::from(scenario);
+    RunEngine::new(Some(true), Some(false)).run(&execution_plan, scenario, EventSink::<Event>::noop()).await
+}
+
+/// Report of a mock run of the fixture `name`
+pub(crate) async fn mock_report(name: &str) -> RunReport {
+    mock_run(&fixture_scenario(name)).await
+}
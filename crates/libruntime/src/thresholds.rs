@@ -0,0 +1,236 @@
+use crate::run_engine::{LatencySummary, Requests, RunReport};
+use libprotocol::schema::{Threshold, ThresholdOperator, ThresholdScope};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// One scenario threshold and how the run compared to it
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct ThresholdResult {
+    /// e.g. `latency.p95` in ms or `http.error_rate` in percent, see `Threshold`
+    pub metric: String,
+    pub op: ThresholdOperator,
+    /// The limit, in the unit of `metric`
+    pub value: f64,
+    pub scope: Option<ThresholdScope>,
+    /// `None` when the metric has no data for the given scope
+    pub observed: Option<f64>,
+    pub passed: bool,
+    /// Human-readable outcome, printed for failed thresholds
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct ThresholdsSummary {
+    /// True when no threshold failed, also without thresholds
+    pub passed: bool,
+    pub total: usize,
+    pub failed: usize,
+    /// In the order of the scenario `thresholds`
+    pub results: Vec<ThresholdResult>,
+}
+
+impl Default for ThresholdsSummary {
+    fn default() -> Self {
+        Self { passed: true, total: 0, failed: 0, results: vec![] }
+    }
+}
+
+/// Returned by the CLI when at least one threshold failed, so `bin/rload` can map it to an exit code.
+#[derive(Debug)]
+pub struct ThresholdsFailed {
+    pub failed: usize,
+    pub total: usize,
+}
+
+impl std::fmt::Display for ThresholdsFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "thresholds failed: {} of {}", self.failed, self.total)
+    }
+}
+
+impl std::error::Error for ThresholdsFailed {}
+
+/// Metric values a threshold can be resolved against: the whole run, one endpoint or one journey.
+struct MetricSource<'a> {
+    requests: &'a Requests,
+    latency: &'a LatencySummary,
+    achieved_rps: Option<f64>,
+    planned_rps: Option<f64>,
+}
+
+impl MetricSource<'_> {
+    fn resolve(&self, metric: &str) -> Option<f64> {
+        let value = match metric {
+            "http.error_rate" => {
+                if self.requests.total == 0 {
+                    return None;
+                }
+                self.requests.error as f64 / self.requests.total as f64 * 100.0
+            }
+            "requests.total" => self.requests.total as f64,
+            "requests.ok" => self.requests.ok as f64,
+            "requests.error" => self.requests.error as f64,
+            "rps.achieved_avg" => self.achieved_rps?,
+            "rps.planned_avg" => self.planned_rps?,
+            latency => {
+                if self.latency.count == 0 {
+                    return None;
+                }
+                match latency {
+                    "latency.min" => self.latency.min as f64,
+                    "latency.max" => self.latency.max as f64,
+                    "latency.mean" => self.latency.mean as f64,
+                    "latency.p50" => self.latency.p50 as f64,
+                    "latency.p90" => self.latency.p90 as f64,
+                    "latency.p95" => self.latency.p95 as f64,
+                    "latency.p99" => self.latency.p99 as f64,
+                    _ => return None,
+                }
+            }
+        };
+        Some(value)
+    }
+}
+
+pub fn evaluate(thresholds: &[Threshold], report: &RunReport) -> ThresholdsSummary {
+    let results: Vec<ThresholdResult> = thresholds.iter()
+        .map(|threshold| evaluate_one(threshold, report))
+        .collect();
+    let failed = results.iter().filter(|r| !r.passed).count();
+
+    ThresholdsSummary {
+        passed: failed == 0,
+        total: results.len(),
+        failed,
+        results,
+    }
+}
+
+fn evaluate_one(threshold: &Threshold, report: &RunReport) -> ThresholdResult {
+    let observed = match source_for(threshold.scope.as_ref(), report) {
+        Ok(source) => source.resolve(&threshold.metric),
+        Err(message) => return ThresholdResult {
+            metric: threshold.metric.clone(),
+            op: threshold.op,
+            value: threshold.value,
+            scope: threshold.scope.clone(),
+            observed: None,
+            passed: false,
+            message,
+        },
+    };
+
+    let (passed, message) = match observed {
+        Some(observed) => {
+            let passed = threshold.op.compare(observed, threshold.value);
+            let verdict = if passed { "passed" } else { "failed" };
+            (passed, format!("{} {} {} {} (observed {})", verdict, threshold.metric, threshold.op, threshold.value, observed))
+        }
+        None => (false, format!("failed {}: no data", threshold.metric)),
+    };
+
+    ThresholdResult {
+        metric: threshold.metric.clone(),
+        op: threshold.op,
+        value: threshold.value,
+        scope: threshold.scope.clone(),
+        observed,
+        passed,
+        message,
+    }
+}
+
+fn source_for<'a>(scope: Option<&ThresholdScope>, report: &'a RunReport) -> Result<MetricSource<'a>, String> {
+    match scope {
+        Some(ThresholdScope { endpoint: Some(endpoint), journey: None }) => {
+            let stats = report.by_endpoint.get(endpoint)
+                .ok_or_else(|| format!("failed: endpoint '{}' has no requests", endpoint))?;
+            Ok(MetricSource {
+                requests: &stats.request,
+                latency: &stats.latency_summary,
+                achieved_rps: Some(stats.achieved_rps),
+                planned_rps: None,
+            })
+        }
+        Some(ThresholdScope { endpoint: None, journey: Some(journey) }) => {
+            let stats = report.by_journey.iter().find(|j| &j.key == journey)
+                .ok_or_else(|| format!("failed: journey '{}' has no requests", journey))?;
+            Ok(MetricSource {
+                requests: &stats.request,
+                latency: &stats.latency_summary,
+                achieved_rps: None,
+                planned_rps: None,
+            })
+        }
+        Some(ThresholdScope { endpoint: Some(_), journey: Some(_) }) => {
+            Err("failed: scope must have either endpoint or journey, not both".to_string())
+        }
+        _ => Ok(MetricSource {
+            requests: &report.requests,
+            latency: &report.latency_overall_summary,
+            achieved_rps: Some(report.rps.achieved_avg as f64),
+            planned_rps: Some(report.rps.planned_avg as f64),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::thresholds::{evaluate, MetricSource};
+    use crate::test_runs::{mock_report, RUN_ENGINE_FIXTURE};
+    use libprotocol::schema::{Threshold, ThresholdOperator, ThresholdScope};
+
+    #[tokio::test]
+    async fn it_evaluate_thresholds_against_mock_report() {
+        let report = mock_report(RUN_ENGINE_FIXTURE).await;
+
+        let thresholds = vec![
+            Threshold { metric: "http.error_rate".to_string(), op: ThresholdOperator::Lt, value: 1.0, scope: None },
+            Threshold { metric: "requests.total".to_string(), op: ThresholdOperator::Eq, value: 6000.0, scope: None },
+            Threshold { metric: "latency.p95".to_string(), op: ThresholdOperator::Gt, value: 100.0, scope: None },
+            Threshold {
+                metric: "requests.error".to_string(),
+                op: ThresholdOperator::Eq,
+                value: 0.0,
+                scope: Some(ThresholdScope { endpoint: Some("GET /test".to_string()), journey: None }),
+            },
+            Threshold {
+                metric: "requests.total".to_string(),
+                op: ThresholdOperator::Gt,
+                value: 0.0,
+                scope: Some(ThresholdScope { endpoint: None, journey: Some("default B".to_string()) }),
+            },
+            Threshold {
+                metric: "latency.p99".to_string(),
+                op: ThresholdOperator::Lt,
+                value: 500.0,
+                scope: Some(ThresholdScope { endpoint: Some("GET /missing".to_string()), journey: None }),
+            },
+        ];
+
+        let summary = evaluate(&thresholds, &report);
+
+        let verdicts: Vec<bool> = summary.results.iter().map(|r| r.passed).collect();
+        assert_eq!(vec![true, true, false, true, true, false], verdicts);
+        assert!(!summary.passed);
+        assert_eq!(2, summary.failed);
+        assert_eq!(6, summary.total);
+        assert_eq!(None, summary.results[5].observed);
+    }
+
+    #[test]
+    fn it_compares_the_unrounded_error_rate() {
+        let requests = crate::run_engine::Requests { total: 1000, ok: 994, error: 6 };
+        let source = MetricSource { requests: &requests, latency: &Default::default(), achieved_rps: None, planned_rps: None };
+
+        assert_eq!(Some(0.6), source.resolve("http.error_rate"));
+        assert!(ThresholdOperator::Lt.compare(source.resolve("http.error_rate").unwrap(), 1.0));
+    }
+
+    #[test]
+    fn it_passes_without_thresholds() {
+        let summary = evaluate(&[], &crate::run_engine::RunReport::new(&crate::scheduler::Scheduler::new(&Default::default())));
+        assert!(summary.passed);
+        assert_eq!(0, summary.total);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::run_engine::Requests;
+use hdrhistogram::Histogram;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Per-interval metrics of a run. A request is counted in the bucket where it finished,
+/// and as in flight at the end of every bucket it outlived.
+#[derive(Debug)]
+pub(crate) struct TimeSeries {
+    pub interval_ms: u64,
+    buckets: BTreeMap<u64, Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    requests: Requests,
+    /// latency in ms, resized on demand: a run has many buckets
+    latency: Histogram<u64>,
+    in_flight: u64,
+}
+
+impl Default for Bucket {
+    fn default() -> Self {
+        Bucket {
+            requests: Requests::default(),
+            latency: Histogram::new(2).expect("histogram by bucket creation failed"),
+            in_flight: 0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+pub struct TimeBucket {
+    /// Bucket start, ms since the start of the run
+    pub start_ms: u64,
+    /// Requests that finished in the bucket
+    pub requests: Requests,
+    /// `requests.total` per second of the bucket
+    pub achieved_rps: f64,
+    /// Requests still running at the end of the bucket
+    pub in_flight: u64,
+    pub latency: LatencySnapshot,
+}
+
+/// Latency percentiles of the requests that finished in the bucket, in ms
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, Default, PartialEq)]
+pub struct LatencySnapshot {
+    pub p50: u64,
+    pub p95: u64,
+    pub p99: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
+pub struct TimeSeriesReport {
+    /// Length of a bucket
+    pub interval_ms: u64,
+    /// One per interval from the start of the run, quiet intervals included
+    pub buckets: Vec<TimeBucket>,
+}
+
+impl TimeSeries {
+    pub fn new(interval_ms: u64) -> Self {
+        TimeSeries { interval_ms: interval_ms.max(1), buckets: BTreeMap::new() }
+    }
+
+    pub fn record(&mut self, started_ms: u64, latency_ms: u64, ok: bool) {
+        let finished_ms = started_ms + latency_ms;
+
+        let bucket = self.buckets.entry(finished_ms / self.interval_ms).or_default();
+        bucket.requests.total += 1;
+        match ok {
+            true => bucket.requests.ok += 1,
+            false => bucket.requests.error += 1,
+        }
+        bucket.latency.record(latency_ms).expect("cant crate record in hist for by bucket");
+
+        let mut index = started_ms / self.interval_ms;
+        while (index + 1) * self.interval_ms < finished_ms {
+            self.buckets.entry(index).or_default().in_flight += 1;
+            index += 1;
+        }
+    }
+
+    /// One bucket per interval from the start of the run, quiet intervals included
+    pub fn report(&self) -> TimeSeriesReport {
+        let Some(&last) = self.buckets.keys().next_back() else {
+            return TimeSeriesReport { interval_ms: self.interval_ms, buckets: Vec::new() };
+        };
+        let empty = Bucket::default();
+        let buckets = (0..=last).map(|index| {
+            let bucket = self.buckets.get(&index).unwrap_or(&empty);
+            TimeBucket {
+                start_ms: index * self.interval_ms,
+                requests: bucket.requests,
+                achieved_rps: bucket.requests.total as f64 * 1000.0 / self.interval_ms as f64,
+                in_flight: bucket.in_flight,
+                latency: LatencySnapshot {
+                    p50: bucket.latency.value_at_quantile(0.50),
+                    p95: bucket.latency.value_at_quantile(0.95),
+                    p99: bucket.latency.value_at_quantile(0.99),
+                },
+            }
+        }).collect();
+
+        TimeSeriesReport { interval_ms: self.interval_ms, buckets }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::timeseries::{LatencySnapshot, TimeSeries};
+
+    #[test]
+    fn it_buckets_requests_by_finish_time() {
+        let mut series = TimeSeries::new(1000);
+        series.record(100, 50, true);
+        series.record(200, 20, false);
+        series.record(900, 300, true);
+        series.record(500, 2_600, true);
+
+        let report = series.report();
+
+        assert_eq!(1000, report.interval_ms);
+        assert_eq!(vec![0, 1000, 2000, 3000], report.buckets.iter().map(|b| b.start_ms).collect::<Vec<_>>());
+        assert_eq!(vec![2, 1, 0, 1], report.buckets.iter().map(|b| b.requests.total).collect::<Vec<_>>());
+        assert_eq!(vec![1, 0, 0, 0], report.buckets.iter().map(|b| b.requests.error).collect::<Vec<_>>());
+        assert_eq!(vec![2.0, 1.0, 0.0, 1.0], report.buckets.iter().map(|b| b.achieved_rps).collect::<Vec<_>>());
+        assert_eq!(vec![2, 1, 1, 0], report.buckets.iter().map(|b| b.in_flight).collect::<Vec<_>>());
+        assert_eq!(LatencySnapshot { p50: 20, p95: 50, p99: 50 }, report.buckets[0].latency);
+        assert_eq!(LatencySnapshot::default(), report.buckets[2].latency);
+    }
+
+    #[test]
+    fn it_uses_configured_interval() {
+        let mut series = TimeSeries::new(250);
+        series.record(0, 100, true);
+        series.record(600, 10, true);
+
+        let report = series.report();
+
+        assert_eq!(vec![0, 250, 500], report.buckets.iter().map(|b| b.start_ms).collect::<Vec<_>>());
+        assert_eq!(4.0, report.buckets[0].achieved_rps);
+    }
+}
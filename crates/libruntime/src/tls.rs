@@ -0,0 +1,127 @@
+use libprotocol::schema::{Target, TlsVersion};
+use std::path::{Path, PathBuf};
+
+/// TLS settings of the target, with file paths resolved against the scenario directory.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TlsOptions {
+    pub insecure: bool,
+    pub ca_bundle: Option<PathBuf>,
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+    pub min_version: Option<TlsVersion>,
+    pub max_version: Option<TlsVersion>,
+}
+
+impl From<&Target> for TlsOptions {
+    fn from(target: &Target) -> Self {
+        Self {
+            insecure: target.insecure_tls.unwrap_or(false),
+            ca_bundle: target.ca_bundle.as_ref().map(PathBuf::from),
+            client_cert: target.client_cert.as_ref().map(PathBuf::from),
+            client_key: target.client_key.as_ref().map(PathBuf::from),
+            min_version: target.min_tls_version,
+            max_version: target.max_tls_version,
+        }
+    }
+}
+
+impl TlsOptions {
+    /// Relative paths are taken from `dir`, the directory of the scenario file
+    pub fn resolve_paths(&mut self, dir: &Path) {
+        for path in [&mut self.ca_bundle, &mut self.client_cert, &mut self.client_key].into_iter().flatten() {
+            if path.is_relative() {
+                *path = dir.join(&*path);
+            }
+        }
+    }
+}
+
+/// The HTTP client could not be configured, e.g. a certificate file is missing or malformed.
+#[derive(Debug)]
+pub struct ClientConfigError {
+    pub message: String,
+}
+
+impl std::fmt::Display for ClientConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid client configuration: {}", self.message)
+    }
+}
+
+impl std::error::Error for ClientConfigError {}
+
+pub(crate) fn build_client(tls: &TlsOptions) -> Result<reqwest::Client, ClientConfigError> {
+    let mut builder = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::limited(10))
+        .pool_max_idle_per_host(1000)
+        .tls_danger_accept_invalid_certs(tls.insecure);
+
+    if let Some(path) = &tls.ca_bundle {
+        let certs = reqwest::Certificate::from_pem_bundle(&read(path, "ca_bundle")?)
+            .map_err(|e| error(path, "ca_bundle", e))?;
+        builder = builder.tls_certs_merge(certs);
+    }
+    if let (Some(cert_path), Some(key_path)) = (&tls.client_cert, &tls.client_key) {
+        let mut pem = read(cert_path, "client_cert")?;
+        pem.push(b'\n');
+        pem.extend(read(key_path, "client_key")?);
+        let identity = reqwest::Identity::from_pem(&pem)
+            .map_err(|e| error(cert_path, "client_cert", e))?;
+        builder = builder.identity(identity);
+    }
+    if let Some(version) = tls.min_version {
+        builder = builder.tls_version_min(reqwest_version(version));
+    }
+    if let Some(version) = tls.max_version {
+        builder = builder.tls_version_max(reqwest_version(version));
+    }
+
+    builder.build().map_err(|e| ClientConfigError { message: e.to_string() })
+}
+
+fn reqwest_version(version: TlsVersion) -> reqwest::tls::Version {
+    match version {
+        TlsVersion::Tls12 => reqwest::tls::Version::TLS_1_2,
+        TlsVersion::Tls13 => reqwest::tls::Version::TLS_1_3,
+    }
+}
+
+fn read(path: &Path, field: &str) -> Result<Vec<u8>, ClientConfigError> {
+    std::fs::read(path).map_err(|e| error(path, field, e))
+}
+
+fn error(path: &Path, field: &str, e: impl std::fmt::Display) -> ClientConfigError {
+    ClientConfigError { message: format!("{} '{}': {}", field, path.display(), e) }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tls::{build_client, TlsOptions};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn it_resolves_relative_paths() {
+        let mut tls = TlsOptions {
+            ca_bundle: Some(PathBuf::from("certs/ca.pem")),
+            client_cert: Some(PathBuf::from("/etc/rload/client.pem")),
+            ..TlsOptions::default()
+        };
+
+        tls.resolve_paths(Path::new("/scenarios"));
+
+        assert_eq!(Some(PathBuf::from("/scenarios/certs/ca.pem")), tls.ca_bundle);
+        assert_eq!(Some(PathBuf::from("/etc/rload/client.pem")), tls.client_cert);
+    }
+
+    #[test]
+    fn it_reports_missing_certificate_files() {
+        let tls = TlsOptions {
+            ca_bundle: Some(PathBuf::from("/surely/not/there/ca.pem")),
+            ..TlsOptions::default()
+        };
+
+        let err = build_client(&tls).unwrap_err();
+
+        assert!(err.to_string().starts_with("invalid client configuration: ca_bundle '/surely/not/there/ca.pem'"), "{}", err);
+    }
+}
@@ -0,0 +1,110 @@
+use libprotocol::schema::TracingOptions;
+use sha2::{Digest, Sha256};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `Scenario.tracing` with defaults applied.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TracingSettings {
+    pub traceparent: bool,
+    pub sample_ratio: f64,
+}
+
+impl From<Option<&TracingOptions>> for TracingSettings {
+    fn from(options: Option<&TracingOptions>) -> Self {
+        TracingSettings::resolve(options, false)
+    }
+}
+
+impl TracingSettings {
+    /// `traceparent` defaults to on when the spans are exported, otherwise the exporter has none
+    pub(crate) fn resolve(options: Option<&TracingOptions>, exports_spans: bool) -> Self {
+        let options = options.cloned().unwrap_or_default();
+        TracingSettings {
+            traceparent: options.traceparent.unwrap_or(exports_spans),
+            sample_ratio: options.sample_ratio(),
+        }
+    }
+}
+
+/// W3C trace context of a single request, the load generator side is the root span.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceContext {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    pub sampled: bool,
+}
+
+impl TraceContext {
+    /// Ids are taken from the hash of `seed`. The sampling decision depends on the trace id only,
+    /// as in the OpenTelemetry ratio sampler
+    pub(crate) fn from_seed(seed: &str, sample_ratio: f64) -> Self {
+        let hash = Sha256::digest(seed.as_bytes());
+        let trace_id: [u8; 16] = hash[0..16].try_into().unwrap();
+        let span_id: [u8; 8] = hash[16..24].try_into().unwrap();
+        let n = u64::from_be_bytes(trace_id[8..16].try_into().unwrap());
+        let sampled = sample_ratio >= 1.0 || (n as f64) < sample_ratio * u64::MAX as f64;
+
+        TraceContext { trace_id, span_id, sampled }
+    }
+
+    /// `traceparent` header value, version 00
+    pub fn traceparent(&self) -> String {
+        format!("00-{}-{}-{}", hex(&self.trace_id), hex(&self.span_id), if self.sampled { "01" } else { "00" })
+    }
+}
+
+/// A sampled request, exported as a client span.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RequestSpan {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    pub start_unix_ns: u64,
+    pub end_unix_ns: u64,
+    /// Index of the step in its journey
+    pub step_index: usize,
+}
+
+pub(crate) fn unix_ns(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0)
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trace_context::{TraceContext, TracingSettings};
+    use libprotocol::schema::TracingOptions;
+
+    #[test]
+    fn it_sends_traceparent_by_default_when_spans_are_exported() {
+        let off = TracingOptions { traceparent: Some(false), sample_ratio: None };
+
+        assert_eq!(TracingSettings { traceparent: true, sample_ratio: 1.0 }, TracingSettings::resolve(None, true));
+        assert!(!TracingSettings::resolve(None, false).traceparent);
+        assert!(!TracingSettings::resolve(Some(&off), true).traceparent);
+    }
+
+    #[test]
+    fn it_formats_traceparent() {
+        let context = TraceContext::from_seed("run:1", 1.0);
+        let traceparent = context.traceparent();
+
+        let parts: Vec<&str> = traceparent.split('-').collect();
+        assert_eq!(4, parts.len());
+        assert_eq!(("00", 32, 16, "01"), (parts[0], parts[1].len(), parts[2].len(), parts[3]));
+        assert_eq!(context, TraceContext::from_seed("run:1", 1.0));
+        assert_ne!(context.trace_id, TraceContext::from_seed("run:2", 1.0).trace_id);
+    }
+
+    #[test]
+    fn it_samples_by_ratio() {
+        let sampled = |ratio| (0..10_000).filter(|i| TraceContext::from_seed(&i.to_string(), ratio).sampled).count();
+
+        assert_eq!(0, sampled(0.0));
+        assert_eq!(10_000, sampled(1.0));
+        assert!((900..1100).contains(&sampled(0.1)), "{}", sampled(0.1));
+        assert!(TraceContext::from_seed("x", 0.0).traceparent().ends_with("-00"));
+    }
+}
@@ -1,10 +1,16 @@
 use crate::execution_plan::ExecutionPlan;
+use crate::templating::RenderContext;
+use crate::tls::{build_client, ClientConfigError, TlsOptions};
+use crate::trace_context::{unix_ns, RequestSpan, TraceContext};
 use crate::vu_runner::NextAction::NotReady;
 use async_trait::async_trait;
+use crate::response::{check, extract, is_ok, ResponseData};
 use libprotocol::schema::Step::{Request, Sleep};
-use libprotocol::schema::StepMethod;
+use libprotocol::schema::{Check, Extract, StepMethod};
 use sha2::{Digest, Sha256};
-use std::time::Duration;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
 
 pub struct VuPool {
     vus: Vec<VUState>,
@@ -22,22 +28,64 @@ impl VuPool {
         }
         None
     }
+    /// VU that becomes ready first (closed model, virtual time)
+    pub fn next_ready_vu(&self) -> Option<usize> {
+        self.vus.iter().enumerate()
+            .min_by_key(|(_, vu)| vu.next_ready_at_ms)
+            .map(|(idx, _)| idx)
+    }
+    pub fn into_vus(self) -> Vec<VUState> {
+        self.vus
+    }
     pub fn get_total_sleep_ms(&self) -> u64 {
         self.vus.iter().map(|vu| vu.total_sleep_ms).sum()
     }
+    pub fn get_total_iterations(&self) -> u64 {
+        self.vus.iter().map(|vu| vu.iteration_count).sum()
+    }
     pub fn get_mut(&mut self, vu_idx: usize) -> Result<&mut VUState, String> {
         self.vus.get_mut(vu_idx).ok_or("VU index out of bounds".parse().unwrap())
     }
 }
 
 pub struct VUState{
-    #[allow(dead_code)]
     pub vu_id: u32,
     pub journey_id: u32,
     pub step_index: usize, // (на каком шаге стоим)
     pub next_ready_at_ms: u64, // (когда VU “готов” к следующему request после sleeps)
     pub iteration_count: u64, // (сколько раз завершили journey и начали заново) — опционально
-    pub total_sleep_ms: u64 // сколько эта vu спала
+    pub total_sleep_ms: u64, // сколько эта vu спала
+    /// Values extracted from responses, used by templates of later steps
+    pub vars: HashMap<String, String>,
+    /// Row index per data source, drawn for iteration `data_iteration`
+    pub data_rows: HashMap<String, usize>,
+    pub data_iteration: Option<u64>,
+}
+
+impl VUState {
+    pub fn new(vu_id: u32, journey_id: u32) -> Self {
+        VUState {
+            vu_id,
+            journey_id,
+            step_index: 0,
+            next_ready_at_ms: 0,
+            iteration_count: 0,
+            total_sleep_ms: 0,
+            vars: HashMap::new(),
+            data_rows: HashMap::new(),
+            data_iteration: None,
+        }
+    }
+
+    /// A value missing from the response is forgotten, so a stale token is not reused
+    pub fn remember(&mut self, extracted: &[(String, Option<String>)]) {
+        for (name, value) in extracted {
+            match value {
+                Some(value) => self.vars.insert(name.clone(), value.clone()),
+                None => self.vars.remove(name),
+            };
+        }
+    }
 }
 
 pub struct Ctx {
@@ -45,13 +93,22 @@ pub struct Ctx {
 }
 
 pub struct RequestSpec {
-    #[allow(dead_code)]
     pub(crate) method: StepMethod,
+    /// Rendered path, placeholders resolved
     pub path: String,
+    /// Default headers of the target merged with the step headers, rendered
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+    pub extract: Vec<Extract>,
+    pub checks: Vec<Check>,
+    /// Built from the path template, so rendered values do not split endpoint stats
     pub endpoint_key: String,
     #[allow(dead_code)]
     pub timeout_ms: u64,
     pub journey_id: u64,
+    pub journey_name: String,
+    /// Index of the step in its journey
+    pub step_index: usize,
     pub stage_start_ms: u64,
     pub stage_index: u64,
 }
@@ -60,6 +117,8 @@ pub struct VuRuntime {
 
 }
 
+// one per VU step, short-lived: not worth a box
+#[allow(clippy::large_enum_variant)]
 pub enum NextAction {
     NotReady(u64),
     Ready(RequestSpec),
@@ -67,7 +126,8 @@ pub enum NextAction {
 }
 
 impl VuRuntime {
-    pub fn on_request_executed(&self, plan: &ExecutionPlan, vu: &mut VUState, now_ms: u64) {
+    pub fn on_request_executed(&self, plan: &ExecutionPlan, vu: &mut VUState, now_ms: u64, res: &ResponseResult) {
+        vu.remember(&res.extracted);
         vu.step_index +=1;
         vu.next_ready_at_ms = now_ms;
         let steps = plan.get_journey(vu.journey_id as i32).steps.clone();
@@ -83,7 +143,8 @@ impl VuRuntime {
             return NotReady(vu.next_ready_at_ms)
         }
 
-        let steps = plan.get_journey(vu.journey_id as i32).steps.clone();
+        let journey = plan.get_journey(vu.journey_id as i32);
+        let steps = journey.steps.clone();
 
         if steps.len() <= vu.step_index {
             vu.step_index = 0;
@@ -101,17 +162,35 @@ impl VuRuntime {
                     vu.next_ready_at_ms = base + *duration_ms as u64;
                     vu.step_index +=1;
                     vu.total_sleep_ms += *duration_ms as u64;
+                    if *duration_ms > 0 {
+                        // think time: VU is busy until the sleep is over
+                        return NotReady(vu.next_ready_at_ms);
+                    }
                     continue;
                 },
-                #[allow(dead_code)]
-                Request { method, path, headers: _headers, body: _body, timeout_ms } => {
+                Request { method, path, headers, body, timeout_ms, extract, checks } => {
                     vu.next_ready_at_ms = u64::MAX;
+                    // a VU left without data rows is never ready again
+                    if !plan.data.draw(vu, &plan.random_seed) {
+                        return NotReady(u64::MAX);
+                    }
+                    let mut ctx = RenderContext::new(plan, vu);
+                    let headers = merge_headers(&plan.default_headers, headers.as_ref())
+                        .into_iter()
+                        .map(|(name, value)| (name, ctx.render(&value)))
+                        .collect();
                     NextAction::Ready(RequestSpec {
                         method: *method,
-                        path: path.clone(),
-                        endpoint_key: format!("{:?}-{:?}", method, path).to_string(),
+                        path: ctx.render(path),
+                        headers,
+                        body: body.as_ref().map(|body| ctx.render(body)),
+                        extract: extract.clone().unwrap_or_default(),
+                        checks: checks.clone().unwrap_or_default(),
+                        endpoint_key: format!("{:?} {}", method, path),
                         timeout_ms: timeout_ms.unwrap_or(0) as u64,
                         journey_id: vu.journey_id as u64,
+                        journey_name: journey.name.clone(),
+                        step_index: vu.step_index,
                         stage_start_ms: now_ms,
                         stage_index: 0,
                     })
@@ -126,6 +205,30 @@ impl VuRuntime {
     }
 }
 
+/// Step headers override default ones, header names compare case-insensitively.
+fn merge_headers(defaults: &BTreeMap<String, String>, step: Option<&HashMap<String, String>>) -> Vec<(String, String)> {
+    let mut merged: BTreeMap<String, (String, String)> = BTreeMap::new();
+    let step = step.into_iter().flat_map(|headers| {
+        let mut headers: Vec<_> = headers.iter().collect();
+        headers.sort();
+        headers
+    });
+    for (name, value) in defaults.iter().chain(step) {
+        merged.insert(name.to_ascii_lowercase(), (name.clone(), value.clone()));
+    }
+    merged.into_values().collect()
+}
+
+fn http_method(method: StepMethod) -> reqwest::Method {
+    match method {
+        StepMethod::GET => reqwest::Method::GET,
+        StepMethod::POST => reqwest::Method::POST,
+        StepMethod::PUT => reqwest::Method::PUT,
+        StepMethod::PATCH => reqwest::Method::PATCH,
+        StepMethod::DELETE => reqwest::Method::DELETE,
+    }
+}
+
 #[async_trait]
 pub trait ExecutorAbstract: Send + Sync {
     async fn execute(&self, plan: &ExecutionPlan, request: &RequestSpec, tick_ids: u64) -> Result<ResponseResult, String>;
@@ -155,31 +258,46 @@ impl ExecutorAbstract for ExecutorMock {
             ok: true,
             latency_ms,
             latency_us: 0,
+            status: Some(200),
             error_kind: None,
             endpoint_key: request.endpoint_key.clone(),
-            journey_name: plan.scenario_name.clone(),
+            journey_name: request.journey_name.clone(),
             journey_id: request.journey_id,
+            step_index: request.step_index,
             stage_start_ms: request.stage_start_ms,
             stage_index: request.stage_index,
+            extracted: request.extract.iter().map(|rule| (rule.name().to_string(), None)).collect(),
+            checks: Vec::new(),
+            span: None,
+            start_delay_us: 0,
         })
     }
 }
 
 pub struct ExecutorHttp {
-    pub client: reqwest::Client
+    pub client: reqwest::Client,
+    /// Trace ids differ between runs: they are hashed from the start time and a request counter
+    trace_seed: u64,
+    trace_seq: AtomicU64,
 }
 
 impl ExecutorHttp {
-    pub fn new_instance() -> Box<dyn ExecutorAbstract> {
-        // blocking reqwest клиент
-        let client = reqwest::Client::builder()
-            .redirect(reqwest::redirect::Policy::limited(10))
-            .pool_max_idle_per_host(1000)
-            .build()
-            .unwrap();
-        Box::new(Self {
-            client
-        })
+    pub fn new_instance(tls: &TlsOptions) -> Result<Box<dyn ExecutorAbstract>, ClientConfigError> {
+        let client = build_client(tls)?;
+        Ok(Box::new(Self {
+            client,
+            trace_seed: unix_ns(SystemTime::now()),
+            trace_seq: AtomicU64::new(0),
+        }))
+    }
+
+    fn trace_context(&self, plan: &ExecutionPlan) -> Option<TraceContext> {
+        if !plan.tracing.traceparent {
+            return None;
+        }
+        let seq = self.trace_seq.fetch_add(1, Ordering::Relaxed);
+        let seed = format!("{}-{}-{}", plan.scenario_name, self.trace_seed, seq);
+        Some(TraceContext::from_seed(&seed, plan.tracing.sample_ratio))
     }
 }
 
@@ -188,29 +306,66 @@ impl ExecutorAbstract for ExecutorHttp {
     async fn execute(&self, plan: &ExecutionPlan, request: &RequestSpec, _tick_idx: u64) -> Result<ResponseResult, String> {
         let client = self.client.clone();
         let base_url_clone = plan.base_url.clone();
+        let trace = self.trace_context(plan);
+        let started_at = SystemTime::now();
         let req_start = tokio::time::Instant::now();
 
-        let resp = client.get(format!("{}{}", base_url_clone, request.path))
-            .timeout(Duration::from_millis(request.timeout_ms))
-            .send()
-            .await;
+        let mut builder = client.request(http_method(request.method), format!("{}{}", base_url_clone, request.path));
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(trace) = &trace {
+            builder = builder.header("traceparent", trace.traceparent());
+        }
+        if let Some(body) = &request.body {
+            builder = builder.body(body.clone());
+        }
+        // no step timeout: the client has none either
+        if request.timeout_ms > 0 {
+            builder = builder.timeout(Duration::from_millis(request.timeout_ms));
+        }
+        let resp = builder.send().await;
+
+        // the body is read in full: latency covers the whole response and extraction needs it
+        let resp = match resp {
+            Ok(r) => {
+                let status = r.status().as_u16();
+                let headers = r.headers().clone();
+                r.text().await.map(|body| (status, headers, body))
+            }
+            Err(e) => Err(e),
+        };
         let req_finish = req_start.elapsed().as_millis();
         let req_finish_us = req_start.elapsed().as_micros();
+        let span = trace.filter(|trace| trace.sampled).map(|trace| RequestSpan {
+            trace_id: trace.trace_id,
+            span_id: trace.span_id,
+            start_unix_ns: unix_ns(started_at),
+            end_unix_ns: unix_ns(started_at) + req_finish_us as u64 * 1000,
+            step_index: request.step_index,
+        });
 
         match resp {
-            Ok(r) => {
-                let status = r.status().as_u16();
+            Ok((status, headers, body)) => {
+                let response = ResponseData { status, headers: &headers, body: &body };
+                let checks = check(plan, &request.checks, &response);
+                let checks_failed = checks.iter().any(|(_, passed)| !passed);
                 Ok(ResponseResult {
-                    ok: (200..300).contains(&status),
+                    ok: is_ok(&request.checks, &checks, status),
                     latency_ms: req_finish as u64,
                     latency_us: req_finish_us as u64,
-                    error_kind: None,
+                    status: Some(status),
+                    error_kind: checks_failed.then_some(ErrorType::CheckFailed),
                     endpoint_key: request.endpoint_key.clone(),
-                    journey_name: "test".to_string(),
+                    journey_name: request.journey_name.clone(),
                     journey_id: request.journey_id,
-                    // status_code: Some(status) если добавишь поле
+                    step_index: request.step_index,
                     stage_start_ms: request.stage_start_ms,
                     stage_index: request.stage_index,
+                    extracted: extract(plan, &request.extract, &response),
+                    checks,
+                    span,
+                    start_delay_us: 0,
                 })
             }
             Err(e) => {
@@ -223,17 +378,11 @@ impl ExecutorAbstract for ExecutorHttp {
                     ErrorType::Other
                 };
 
-                Ok(ResponseResult {
-                    ok: false,
-                    latency_ms: req_finish as u64,
-                    latency_us: 0,
-                    error_kind: Some(kind),
-                    endpoint_key: request.endpoint_key.clone(),
-                    journey_name: "test".to_string(),
-                    journey_id: request.journey_id,
-                    stage_start_ms: 0,
-                    stage_index: request.stage_index,
-                })
+                let mut res = ResponseResult::error(request, kind);
+                res.latency_ms = req_finish as u64;
+                res.latency_us = req_finish_us as u64;
+                res.span = span;
+                Ok(res)
             }
         }
     }
@@ -243,36 +392,105 @@ pub struct ResponseResult {
     pub ok: bool,
     pub(crate) latency_ms: u64,
     pub(crate) latency_us: u64,
-    #[allow(dead_code)]
+    /// HTTP status, `None` when no response arrived
+    pub status: Option<u16>,
     pub error_kind: Option<ErrorType>,
     pub(crate) endpoint_key: String,
     pub journey_name: String,
     pub journey_id: u64,
+    /// Index of the step in its journey
+    pub step_index: usize,
     pub stage_index: u64,
-    pub stage_start_ms: u64
+    pub stage_start_ms: u64,
+    /// Values for the VU state, see `VUState::remember`
+    pub extracted: Vec<(String, Option<String>)>,
+    /// `(label, passed)` per check of the step, empty when there was no response
+    pub checks: Vec<(String, bool)>,
+    /// Set for sampled requests when the scenario enables `traceparent`
+    pub span: Option<RequestSpan>,
+    /// How late the request was sent after its tick's `planned_at_ms`, see `ResponseResult::schedule`
+    pub(crate) start_delay_us: u64,
 }
 
+impl ResponseResult {
+    /// Ties the request to the tick that started it, for the coordinated-omission-corrected latency
+    pub(crate) fn schedule(&mut self, planned_at_ms: u64, started_ms: u64) {
+        self.start_delay_us = started_ms.saturating_sub(planned_at_ms) * 1000;
+    }
+
+    /// Failed request without a response
+    pub(crate) fn error(request: &RequestSpec, kind: ErrorType) -> Self {
+        ResponseResult {
+            ok: false,
+            latency_ms: 0,
+            latency_us: 0,
+            status: None,
+            error_kind: Some(kind),
+            endpoint_key: request.endpoint_key.clone(),
+            journey_name: request.journey_name.clone(),
+            journey_id: request.journey_id,
+            step_index: request.step_index,
+            stage_index: request.stage_index,
+            stage_start_ms: 0,
+            // nothing was found, so the extracted values are forgotten
+            extracted: request.extract.iter().map(|rule| (rule.name().to_string(), None)).collect(),
+            checks: Vec::new(),
+            span: None,
+            start_delay_us: 0,
+        }
+    }
+
+    /// Why the request failed: the error kind, or the unexpected status
+    pub fn error_label(&self) -> Option<String> {
+        match (self.ok, self.error_kind, self.status) {
+            (true, _, _) => None,
+            (false, Some(kind), _) => Some(kind.as_str().to_string()),
+            (false, None, Some(status)) => Some(format!("http_{}", status)),
+            (false, None, None) => Some(ErrorType::Other.as_str().to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ErrorType {
-    #[allow(dead_code)]
     Timeout,
-    #[allow(dead_code)]
     ConnectionError,
+    /// Response arrived but at least one check failed
+    CheckFailed,
     Other
 }
+
+impl ErrorType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorType::Timeout => "timeout",
+            ErrorType::ConnectionError => "connection",
+            ErrorType::CheckFailed => "check_failed",
+            ErrorType::Other => "other",
+        }
+    }
+}
 #[cfg(test)]
 mod tests {
+    use crate::data_feeds::DataFeeds;
     use crate::execution_plan::ExecutionPlan;
-    use crate::vu_runner::{ExecutorHttp, RequestSpec};
+    use crate::metrics::MetricsAggregator;
+    use crate::tls::TlsOptions;
+    use crate::trace_context::{hex, TracingSettings};
+    use crate::vu_runner::{merge_headers, ErrorType, ExecutorHttp, ExecutorMock, NextAction, RequestSpec, VUState, VuRuntime};
+    use libprotocol::schema::{Check, Extract, Journey, Step, StepMethod, TlsVersion};
+    use std::collections::{BTreeMap, HashMap};
     use libprotocol::schema::StepMethod::GET;
     use libprotocol::Scenario;
     use test_support::fixture_path;
+    use test_support::tls_server::{spawn_tls_test_server, tls_fixture_path, TlsServerOptions};
 
     #[tokio::test]
     async fn it_works() {
         let (base_url, shutdown_tx, handle) = test_support::test_server::spawn_test_server();
         tokio::task::yield_now().await;
 
-        let executor = ExecutorHttp::new_instance();
+        let executor = ExecutorHttp::new_instance(&TlsOptions::default()).unwrap();
         let path = fixture_path("crates/libruntime/tests/fixtures/valid-extended-scenario-for_check_run_engine.json");
         let content = std::fs::read_to_string(&path).unwrap();
         let scenario: Scenario = serde_json::from_str(&content).unwrap();
@@ -281,9 +499,15 @@ mod tests {
         let request = RequestSpec {
             method: GET,
             path: "ok".to_string(),
+            headers: vec![],
+            body: None,
+            extract: vec![],
+            checks: vec![],
             endpoint_key: "GET - /ok".to_string(),
             timeout_ms: 10,
             journey_id: 0,
+            journey_name: "default A".to_string(),
+            step_index: 0,
             stage_start_ms: 0,
             stage_index: 0,
         };
@@ -293,4 +517,309 @@ mod tests {
         let _ = shutdown_tx.send(());
         let _ = handle.await;
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn it_renders_templates_in_next_action() {
+        let scenario: Scenario = libprotocol::parse_scenario(fixture_path("crates/libprotocol/tests/fixtures/valid-templates-scenario.json"));
+        let execution_plan = ExecutionPlan::from(&scenario);
+        let mut vu = VUState::new(42, 0);
+        vu.step_index = 1;
+        vu.iteration_count = 5;
+
+        let NextAction::Ready(request) = VuRuntime{}.next_action(&execution_plan, &mut vu, 0).await else {
+            panic!("expected a request");
+        };
+
+        assert!(request.path.starts_with("/v2/acme/users/42?page="), "{}", request.path);
+        assert_eq!("POST /{{api_version}}/{{tenant}}/users/{{vu_id}}?page={{random_int(1,10)}}", request.endpoint_key);
+        assert_eq!(Some("{\"iteration\": 5}".to_string()), request.body);
+        let request_id = request.headers.iter().find(|(name, _)| name == "X-Request-Id").unwrap();
+        assert_eq!(36, request_id.1.len());
+        assert!(request.headers.iter().any(|(name, _)| name == "Content-Type"));
+    }
+
+    #[tokio::test]
+    async fn it_renders_data_rows_in_next_action() {
+        let path = fixture_path("crates/libprotocol/tests/fixtures/valid-data-sources-scenario.json");
+        let content = std::fs::read_to_string(&path).unwrap();
+        let scenario: Scenario = serde_json::from_str(&content).unwrap();
+        let mut execution_plan = ExecutionPlan::from(&scenario);
+        execution_plan.data = DataFeeds::load(&scenario, path.parent().unwrap()).unwrap();
+        let runtime = VuRuntime{};
+        let mut vu = VUState::new(1, 0);
+
+        let NextAction::Ready(login) = runtime.next_action(&execution_plan, &mut vu, 0).await else {
+            panic!("expected a request");
+        };
+        assert_eq!(Some("{\"password\": \"secret-b\"}".to_string()), login.body);
+        assert!(login.headers.contains(&("X-User".to_string(), "b@example.com".to_string())));
+
+        // the second step of the iteration sees the same rows
+        let res = ExecutorMock::new_instance().execute(&execution_plan, &login, 0).await.unwrap();
+        runtime.on_request_executed(&execution_plan, &mut vu, 0, &res);
+        let NextAction::Ready(cart) = runtime.next_action(&execution_plan, &mut vu, 0).await else {
+            panic!("expected a request");
+        };
+        let sku = &cart.path["/cart/".len().."/cart/".len() + 3];
+        assert!(cart.path.contains(&format!("qty={}", if sku == "A-1" { 2 } else { 1 })), "{}", cart.path);
+        assert!(cart.path.ends_with("&ref=A-1"), "{}", cart.path);
+
+        // users has 2 rows for unique_per_vu with stop_vu
+        let mut third = VUState::new(2, 0);
+        assert!(matches!(runtime.next_action(&execution_plan, &mut third, 0).await, NextAction::NotReady(u64::MAX)));
+    }
+
+    #[tokio::test]
+    async fn it_extracts_values_and_reuses_them_in_later_steps() {
+        let (base_url, shutdown_tx, handle) = test_support::test_server::spawn_test_server();
+        test_support::test_server::wait_until_ready(&base_url).await;
+
+        let path = fixture_path("crates/libruntime/tests/fixtures/extract-scenario.json");
+        let content = std::fs::read_to_string(&path).unwrap();
+        let scenario: Scenario = serde_json::from_str(&content).unwrap();
+        let mut execution_plan = ExecutionPlan::from(&scenario);
+        execution_plan.base_url = base_url.clone();
+        let executor = ExecutorHttp::new_instance(&TlsOptions::default()).unwrap();
+        let runtime = VuRuntime{};
+        let mut vu = VUState::new(1, 0);
+
+        for step in 0..2 {
+            let NextAction::Ready(request) = runtime.next_action(&execution_plan, &mut vu, 0).await else {
+                panic!("expected a request at step {step}");
+            };
+            let res = executor.execute(&execution_plan, &request, step).await.unwrap();
+            assert!(res.ok, "step {step} failed");
+            runtime.on_request_executed(&execution_plan, &mut vu, 0, &res);
+        }
+
+        assert_eq!(Some(&"t-42".to_string()), vu.vars.get("token"));
+        assert_eq!(Some(&"s-1".to_string()), vu.vars.get("session"));
+        assert_eq!(Some(&"/items/7?token=t-42&session=s-1".to_string()), vu.vars.get("echoed"));
+
+        let _ = shutdown_tx.send(());
+        let _ = handle.await;
+    }
+
+    async fn tls_request_ok(base_url: &str, tls: TlsOptions) -> bool {
+        let mut execution_plan = ExecutionPlan::from(&Scenario::default());
+        execution_plan.base_url = base_url.to_string();
+        let executor = ExecutorHttp::new_instance(&tls).unwrap();
+        let request = RequestSpec {
+            method: GET,
+            path: "/ok".to_string(),
+            headers: vec![],
+            body: None,
+            extract: vec![],
+            checks: vec![],
+            endpoint_key: "GET /ok".to_string(),
+            timeout_ms: 2000,
+            journey_id: 0,
+            journey_name: "default".to_string(),
+            step_index: 0,
+            stage_start_ms: 0,
+            stage_index: 0,
+        };
+        executor.execute(&execution_plan, &request, 0).await.is_ok_and(|res| res.ok)
+    }
+
+    #[tokio::test]
+    async fn it_applies_tls_options() {
+        let (base_url, shutdown_tx, handle) = spawn_tls_test_server(TlsServerOptions::default()).await;
+        let ca = TlsOptions { ca_bundle: Some(tls_fixture_path("ca.pem")), ..TlsOptions::default() };
+
+        assert!(!tls_request_ok(&base_url, TlsOptions::default()).await, "self-signed CA must be rejected");
+        assert!(tls_request_ok(&base_url, TlsOptions { insecure: true, ..TlsOptions::default() }).await);
+        assert!(tls_request_ok(&base_url, ca.clone()).await);
+        assert!(tls_request_ok(&base_url, TlsOptions { min_version: Some(TlsVersion::Tls13), ..ca.clone() }).await);
+
+        shutdown_tx.send(()).unwrap();
+        let _ = handle.await;
+
+        let (base_url, shutdown_tx, handle) = spawn_tls_test_server(TlsServerOptions { tls13_only: true, ..TlsServerOptions::default() }).await;
+        assert!(tls_request_ok(&base_url, ca.clone()).await);
+        assert!(!tls_request_ok(&base_url, TlsOptions { max_version: Some(TlsVersion::Tls12), ..ca.clone() }).await);
+
+        shutdown_tx.send(()).unwrap();
+        let _ = handle.await;
+    }
+
+    #[tokio::test]
+    async fn it_authenticates_with_client_certificate() {
+        let (base_url, shutdown_tx, handle) = spawn_tls_test_server(TlsServerOptions { require_client_cert: true, ..TlsServerOptions::default() }).await;
+        let ca = TlsOptions { ca_bundle: Some(tls_fixture_path("ca.pem")), ..TlsOptions::default() };
+        let mtls = TlsOptions {
+            client_cert: Some(tls_fixture_path("client.pem")),
+            client_key: Some(tls_fixture_path("client-key.pem")),
+            ..ca.clone()
+        };
+
+        assert!(!tls_request_ok(&base_url, ca).await, "server must require a client certificate");
+        assert!(tls_request_ok(&base_url, mtls).await);
+
+        shutdown_tx.send(()).unwrap();
+        let _ = handle.await;
+    }
+
+    #[tokio::test]
+    async fn it_fails_request_on_failed_checks() {
+        let (base_url, shutdown_tx, handle) = test_support::test_server::spawn_test_server();
+        test_support::test_server::wait_until_ready(&base_url).await;
+
+        let mut execution_plan = ExecutionPlan::from(&Scenario::default());
+        execution_plan.base_url = base_url.clone();
+        let executor = ExecutorHttp::new_instance(&TlsOptions::default()).unwrap();
+        let request = |checks: Vec<Check>| RequestSpec {
+            method: GET,
+            path: "/json".to_string(),
+            headers: vec![],
+            body: None,
+            extract: vec![],
+            checks,
+            endpoint_key: "GET /json".to_string(),
+            timeout_ms: 2000,
+            journey_id: 0,
+            journey_name: "default".to_string(),
+            step_index: 0,
+            stage_start_ms: 0,
+            stage_index: 0,
+        };
+        let mut metrics = MetricsAggregator::with_timeseries_interval_ms(1000);
+
+        let passing = vec![
+            Check::Status { values: vec![200] },
+            Check::JsonEquals { path: "$.data.token".to_string(), value: serde_json::json!("t-42") },
+            Check::HeaderPresent { header: "x-session".to_string() },
+        ];
+        let res = executor.execute(&execution_plan, &request(passing), 0).await.unwrap();
+        assert!(res.ok);
+        metrics.consume(res, 0);
+
+        let failing = vec![
+            Check::Status { values: vec![200] },
+            Check::BodyContains { value: "error page".to_string() },
+        ];
+        let res = executor.execute(&execution_plan, &request(failing), 1).await.unwrap();
+        assert!(!res.ok);
+        assert!(matches!(res.error_kind, Some(ErrorType::CheckFailed)));
+        metrics.consume(res, 1);
+
+        let stats = &metrics.by_endpoint["GET /json"];
+        assert_eq!(2, stats.request.total);
+        assert_eq!(1, stats.request.error);
+        assert_eq!(2, stats.checks["status in [200]"].passed);
+        assert_eq!(1, stats.checks["body contains \"error page\""].failed);
+        assert_eq!(1, stats.checks["header x-session present"].passed);
+
+        let _ = shutdown_tx.send(());
+        let _ = handle.await;
+    }
+
+    #[tokio::test]
+    async fn it_sends_method_headers_and_body() {
+        let (base_url, shutdown_tx, handle) = test_support::test_server::spawn_test_server();
+        test_support::test_server::wait_until_ready(&base_url).await;
+
+        let echoed = |name: &str, path: &str| Extract::Json { name: name.to_string(), path: path.to_string() };
+        let methods = [StepMethod::GET, StepMethod::POST, StepMethod::PUT, StepMethod::PATCH, StepMethod::DELETE];
+        let steps = methods.iter().map(|method| Step::Request {
+            method: *method,
+            path: "/echo".to_string(),
+            headers: Some(HashMap::from([("x-step".to_string(), "vu-{{vu_id}}".to_string())])),
+            body: Some(format!("{:?} body", method)),
+            timeout_ms: None,
+            extract: Some(vec![
+                echoed("method", "$.method"),
+                echoed("body", "$.body"),
+                echoed("step_header", "$.headers['x-step']"),
+                echoed("default_header", "$.headers['x-default']"),
+            ]),
+            checks: None,
+        }).collect();
+        let mut scenario = Scenario::default();
+        scenario.target.default_headers = Some(BTreeMap::from([("X-Default".to_string(), "default".to_string())]));
+        scenario.journeys = vec![Journey { name: "methods".to_string(), weight: 1, steps }];
+        let mut execution_plan = ExecutionPlan::from(&scenario);
+        execution_plan.base_url = base_url.clone();
+
+        let executor = ExecutorHttp::new_instance(&TlsOptions::default()).unwrap();
+        let runtime = VuRuntime{};
+        let mut vu = VUState::new(3, 0);
+
+        for method in methods {
+            let NextAction::Ready(request) = runtime.next_action(&execution_plan, &mut vu, 0).await else {
+                panic!("expected a request for {method:?}");
+            };
+            let res = executor.execute(&execution_plan, &request, 0).await.unwrap();
+            assert!(res.ok, "{method:?} failed");
+            runtime.on_request_executed(&execution_plan, &mut vu, 0, &res);
+
+            assert_eq!(Some(&format!("{:?}", method)), vu.vars.get("method"));
+            assert_eq!(Some(&format!("{:?} body", method)), vu.vars.get("body"));
+            assert_eq!(Some(&"vu-3".to_string()), vu.vars.get("step_header"));
+            assert_eq!(Some(&"default".to_string()), vu.vars.get("default_header"));
+        }
+
+        let _ = shutdown_tx.send(());
+        let _ = handle.await;
+    }
+
+    #[test]
+    fn it_merges_default_and_step_headers() {
+        let defaults = BTreeMap::from([
+            ("Content-Type".to_string(), "application/json".to_string()),
+            ("X-Default".to_string(), "default".to_string()),
+        ]);
+        let step = HashMap::from([("content-type".to_string(), "text/plain".to_string())]);
+
+        assert_eq!(vec![
+            ("content-type".to_string(), "text/plain".to_string()),
+            ("X-Default".to_string(), "default".to_string()),
+        ], merge_headers(&defaults, Some(&step)));
+        assert_eq!(2, merge_headers(&defaults, None).len());
+    }
+
+    #[tokio::test]
+    async fn it_propagates_traceparent() {
+        let (base_url, shutdown_tx, handle) = test_support::test_server::spawn_test_server();
+        test_support::test_server::wait_until_ready(&base_url).await;
+
+        let mut execution_plan = ExecutionPlan::from(&Scenario::default());
+        execution_plan.base_url = base_url.clone();
+        let executor = ExecutorHttp::new_instance(&TlsOptions::default()).unwrap();
+        let request = RequestSpec {
+            method: GET,
+            path: "/echo".to_string(),
+            headers: vec![],
+            body: None,
+            extract: vec![Extract::Json { name: "traceparent".to_string(), path: "$.headers.traceparent".to_string() }],
+            checks: vec![],
+            endpoint_key: "GET /echo".to_string(),
+            timeout_ms: 2000,
+            journey_id: 0,
+            journey_name: "default".to_string(),
+            step_index: 3,
+            stage_start_ms: 0,
+            stage_index: 0,
+        };
+
+        let res = executor.execute(&execution_plan, &request, 0).await.unwrap();
+        assert_eq!(vec![("traceparent".to_string(), None)], res.extracted);
+        assert_eq!(None, res.span);
+
+        execution_plan.tracing = TracingSettings { traceparent: true, sample_ratio: 1.0 };
+        let res = executor.execute(&execution_plan, &request, 1).await.unwrap();
+        let span = res.span.expect("sampled request has a span");
+        let expected = format!("00-{}-{}-01", hex(&span.trace_id), hex(&span.span_id));
+        assert_eq!(vec![("traceparent".to_string(), Some(expected))], res.extracted);
+        assert_eq!(3, span.step_index);
+        assert!(span.start_unix_ns < span.end_unix_ns);
+
+        execution_plan.tracing.sample_ratio = 0.0;
+        let res = executor.execute(&execution_plan, &request, 2).await.unwrap();
+        assert!(res.extracted[0].1.as_ref().unwrap().ends_with("-00"));
+        assert_eq!(None, res.span);
+
+        let _ = shutdown_tx.send(());
+        let _ = handle.await;
+    }
+}
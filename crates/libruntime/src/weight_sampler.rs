@@ -95,7 +95,7 @@ mod tests {
         weight_sampler.seed = "1000".to_string();
         for i in 0..10 {
             if let Some(res) = weight_sampler.peek(format!("{}-stable_key", i).as_str()){
-                assert!(res >= 0 && res < 10);
+                assert!((0..10).contains(&res));
             } else {
                 panic!("Unexpected None result from peek");
             }
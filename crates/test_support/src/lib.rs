@@ -1,4 +1,5 @@
 pub mod test_server;
+pub mod tls_server;
 
 use std::path::PathBuf;
 
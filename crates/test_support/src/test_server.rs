@@ -2,8 +2,8 @@ use axum::extract::OriginalUri;
 use axum::{
     http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
-    routing::get,
-    Router,
+    routing::{any, get},
+    Json, Router,
 };
 use http::Method;
 use std::net::TcpListener;
@@ -24,12 +24,47 @@ async fn sleep_ms(axum::extract::Path(ms): axum::extract::Path<u64>) -> impl Int
     "slept"
 }
 
+// json body + header, used by response extraction tests
+async fn json() -> impl IntoResponse {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, "application/json".parse().unwrap());
+    headers.insert("x-session", "s-1".parse().unwrap());
+    (StatusCode::OK, headers, r#"{"data": {"token": "t-42", "items": [{"id": 7}]}}"#)
+}
+
+// echoes the request back as json: method, headers (lowercase names) and body
+async fn echo(method: Method, headers: HeaderMap, body: String) -> impl IntoResponse {
+    let headers: serde_json::Map<String, serde_json::Value> = headers.iter()
+        .map(|(name, value)| (name.to_string(), value.to_str().unwrap_or_default().into()))
+        .collect();
+    Json(serde_json::json!({
+        "method": method.as_str(),
+        "headers": headers,
+        "body": body,
+    }))
+}
+
 async fn redir() -> impl IntoResponse {
     let mut headers = HeaderMap::new();
     headers.insert(header::LOCATION, "/ok".parse().unwrap());
     (StatusCode::FOUND, headers, "")
 }
 
+async fn fallback(method: Method, uri: OriginalUri) -> String {
+    format!("fallback: {} {}", method, uri.0)
+}
+
+pub(crate) fn app() -> Router {
+    Router::new()
+        .route("/ok", get(ok).put(ok).delete(ok))
+        .route("/err", get(err).put(err).delete(err))
+        .route("/sleep/{ms}", get(sleep_ms).patch(sleep_ms))
+        .route("/redir", get(redir))
+        .route("/json", get(json))
+        .route("/echo", any(echo))
+        .fallback(fallback)
+}
+
 /// Возвращает (base_url, shutdown_sender, join_handle)
 pub fn spawn_test_server() -> (String, oneshot::Sender<()>, tokio::task::JoinHandle<()>) {
     // 1) Listener на случайном свободном порту
@@ -38,17 +73,8 @@ pub fn spawn_test_server() -> (String, oneshot::Sender<()>, tokio::task::JoinHan
     let addr = listener.local_addr().unwrap();
     let base_url = format!("http://{}", addr);
 
-    async fn fallback(method: Method, uri: OriginalUri) -> String {
-        format!("fallback: {} {}", method, uri.0)
-    }
-
     // 2) Роуты
-    let app = Router::new()
-        .route("/ok", get(ok).put(ok).delete(ok))
-        .route("/err", get(err).put(err).delete(err))
-        .route("/sleep/{ms}", get(sleep_ms).patch(sleep_ms))
-        .route("/redir", get(redir))
-        .fallback(fallback);
+    let app = app();
 
     // 3) Канал для graceful shutdown
     let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
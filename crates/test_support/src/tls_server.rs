@@ -0,0 +1,99 @@
+use crate::test_server::app;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::service::TowerToHyperService;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio_rustls::TlsAcceptor;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TlsServerOptions {
+    /// Reject clients without a certificate signed by the test CA
+    pub require_client_cert: bool,
+    /// Only accept TLS 1.3 handshakes
+    pub tls13_only: bool,
+}
+
+/// PEM files of the test PKI: `ca.pem`, `server.pem`, `client.pem` and their `-key.pem`.
+/// The server certificate is valid for `localhost` and `127.0.0.1`.
+pub fn tls_fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join("tls")
+        .join(name)
+}
+
+/// Same routes as `spawn_test_server`, over https.
+/// Возвращает (base_url, shutdown_sender, join_handle)
+pub async fn spawn_tls_test_server(options: TlsServerOptions) -> (String, oneshot::Sender<()>, tokio::task::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("https://{}", listener.local_addr().unwrap());
+    let acceptor = TlsAcceptor::from(Arc::new(server_config(options)));
+    let app = app();
+
+    let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>();
+
+    let handle = tokio::spawn(async move {
+        loop {
+            let stream = tokio::select! {
+                _ = &mut shutdown_rx => break,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(_) => continue,
+                },
+            };
+            let acceptor = acceptor.clone();
+            let service = TowerToHyperService::new(app.clone());
+            tokio::spawn(async move {
+                // failed handshakes are what some tests expect, just drop the connection
+                let Ok(stream) = acceptor.accept(stream).await else {
+                    return;
+                };
+                let _ = Builder::new(TokioExecutor::new())
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+            });
+        }
+    });
+
+    (base_url, shutdown_tx, handle)
+}
+
+fn server_config(options: TlsServerOptions) -> ServerConfig {
+    let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+    let versions: &[&rustls::SupportedProtocolVersion] = match options.tls13_only {
+        true => &[&rustls::version::TLS13],
+        false => &[&rustls::version::TLS12, &rustls::version::TLS13],
+    };
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(versions)
+        .unwrap();
+
+    let builder = match options.require_client_cert {
+        true => {
+            let mut roots = RootCertStore::empty();
+            roots.add_parsable_certificates(load_certs("ca.pem"));
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .unwrap();
+            builder.with_client_cert_verifier(verifier)
+        }
+        false => builder.with_no_client_auth(),
+    };
+
+    let key = PrivateKeyDer::from_pem_file(tls_fixture_path("server-key.pem")).unwrap();
+    builder.with_single_cert(load_certs("server.pem"), key).unwrap()
+}
+
+fn load_certs(name: &str) -> Vec<CertificateDer<'static>> {
+    CertificateDer::pem_file_iter(tls_fixture_path(name))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}